@@ -1,5 +1,14 @@
-use crate::timeshift_lib::Timeshift;
+use crate::timeshift_lib::{
+    Backend, Bootloader, CancelHandle, Cancelled, Change, ChangeReport, CommandError, ConfigField,
+    CronJobs, Device, ExportFormat, FileDestination, FileDiff, FileEntry, FileKind,
+    FileRestorePlan, GROWTH_SAMPLE, InstalledPackages, Inventory, KeepReason, OPERATION_LOG_LINES,
+    OperationContext, OperationEvent, PackageChange, Preview, Progress, RestoreOptions,
+    RetentionPolicy, SNAPSHOT_NAME_FORMAT, Snapshot, SnapshotInfo, SnapshotSize, SnapshotTag,
+    SnapshotTree, Subvolume, Timeshift, TimeshiftConfig, browse, compare, diff, original_path,
+    packages, search_score,
+};
 use crate::ui::center;
+use chrono::{Local, TimeDelta};
 use ratatui::DefaultTerminal;
 use ratatui::Frame;
 use ratatui::crossterm::event;
@@ -14,27 +23,508 @@ use ratatui::{
     widgets::{Block, Widget},
 };
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::env;
+use std::fs;
 use std::io;
-use std::thread::JoinHandle;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 use std::time::Instant;
 use throbber_widgets_tui::ThrobberState;
 use tui_input::Input;
 
 #[derive(Debug, Default)]
-pub struct App {
+pub struct App<B> {
     pub exit: bool,
-    pub timeshift_instance: Timeshift,
+    pub timeshift_instance: Timeshift<B>,
     pub current_index: usize,
     pub current_device_name: String,
     pub current_display_screen: Screen,
-    pub operation_thread: Option<JoinHandle<Result<(), String>>>, // Threat that I use for creation
-    // and deletion
+    pub operation: Option<RunningOperation>, // Threat that I use for creation, deletion and
+    // restoration
+    /// What timeshift printed during the current operation
+    pub operation_log: Vec<String>,
+    /// Last progress timeshift gave us for the current operation
+    pub operation_progress: Option<Progress>,
     pub throbber_state: RefCell<ThrobberState>, // TODO : read doc about this
     pub current_action: CurrentAction,
     pub input_mode: InputMode,
     /// Current value of the input box
     pub input: Input,
+    pub snapshot_sort: TableSort<SnapshotColumn>,
+    pub device_sort: TableSort<DeviceColumn>,
+    /// Only the snapshots with this tag are displayed
+    pub tag_filter: Option<SnapshotTag>,
+    /// What is typed after `/`, the list only keeps the snapshots that match it, best first
+    pub search: Input,
+    /// Snapshots older than this are highlighted in the list, 30 days unless `--stale-days` says
+    /// otherwise
+    pub stale_after: TimeDelta,
+    pub restore_wizard: RestoreWizard,
+    /// What went wrong with the last operation, shown until the user dismisses it
+    pub operation_failure: Option<OperationFailure>,
+    /// The detail pane is displayed next to the snapshot list
+    pub show_details: bool,
+    /// The `info.json` of the snapshots already read, by device and snapshot name. Reading it
+    /// can mean mounting the device, so we only do it once.
+    pub snapshot_details: HashMap<(String, String), Result<SnapshotInfo, String>>,
+    /// The subvolumes of the btrfs snapshots already read, same keys as `snapshot_details`
+    pub snapshot_subvolumes: HashMap<(String, String), Result<Vec<Subvolume>, String>>,
+    /// What the package manager changed since each snapshot, same keys as `snapshot_details`.
+    /// Forgotten when the list is refreshed, the running system may have changed.
+    pub snapshot_packages: HashMap<(String, String), Result<Vec<PackageChange>, String>>,
+    /// The packages of the running system, read once per refresh by the details job
+    pub live_packages: Option<Arc<Result<InstalledPackages, String>>>,
+    /// Reads the details of one snapshot in the background, mounting and reading the package
+    /// databases takes a while
+    pub details_job: Option<Receiver<LoadedDetails>>,
+    pub settings: SettingsEditor,
+    /// In btrfs mode, whether the snapshot being created includes `@home`
+    pub create_include_home: bool,
+    /// What the rsync snapshots really take, by snapshot name. Measuring them means walking the
+    /// whole snapshot, so it is done in the background and only once.
+    pub snapshot_sizes: HashMap<SnapshotKey, Result<SnapshotSize, String>>,
+    /// The thread measuring the snapshots, while it runs
+    pub size_job: Option<SizeJob>,
+    pub bulk: BulkDeletion,
+    /// What the prune preview and the prune apply
+    pub retention: RetentionPolicy,
+    /// The names of the snapshots the user pinned, the policy keeps them
+    pub pinned: BTreeSet<String>,
+    /// The snapshot list shows what the retention policy keeps and deletes
+    pub prune_preview: bool,
+    /// Setting selected in the retention popup
+    pub retention_index: usize,
+    /// The snapshot being browsed, on the browser screen
+    pub browser: Option<FileBrowser>,
+    /// Format selected in the export popup
+    pub export_index: usize,
+    /// Where the last export was written
+    pub exported_path: Option<PathBuf>,
+    /// The file comparison being displayed
+    pub diff: Option<DiffView>,
+    /// What the selected snapshot can be compared with, in the compare popup
+    pub compare_targets: Vec<CompareTarget>,
+    /// Choice selected in the compare popup
+    pub compare_index: usize,
+    /// The change report, on the report screen
+    pub report: Option<ChangeReportView>,
+}
+
+/// The timeshift config edited on the Settings screen.
+#[derive(Debug, Default)]
+pub struct SettingsEditor {
+    /// The config with the changes of the user, `None` when it couldn't be read
+    pub config: Option<TimeshiftConfig>,
+    /// The config as it is on disk, to know if something changed
+    pub saved: Option<TimeshiftConfig>,
+    pub status: Option<SettingsStatus>,
+    /// Read when the screen is opened, to warn when the schedule cannot run
+    pub cron_jobs: CronJobs,
+}
+
+impl SettingsEditor {
+    pub fn is_modified(&self) -> bool {
+        self.config != self.saved
+    }
+}
+
+/// What to tell the user under the settings.
+#[derive(Debug, Clone)]
+pub enum SettingsStatus {
+    Saved,
+    Errors(Vec<String>),
+    /// The user tried to leave with unsaved changes, leaving again discards them
+    UnsavedChanges,
+}
+
+/// A line of the Settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsRow {
+    Field(ConfigField),
+    /// A rule of the include/exclude list, by position
+    Filter(usize),
+    AddFilter,
+}
+
+/// A snapshot of a device : (device name, snapshot name)
+pub type SnapshotKey = (String, String);
+
+/// Everything the detail pane shows about a snapshot, sent by the details job.
+#[derive(Debug)]
+pub struct LoadedDetails {
+    pub key: SnapshotKey,
+    pub info: Result<SnapshotInfo, String>,
+    /// `None` when not in btrfs mode
+    pub subvolumes: Option<Result<Vec<Subvolume>, String>>,
+    pub packages: Result<Vec<PackageChange>, String>,
+    /// Sent back so the next snapshots don't read it again
+    pub live_packages: Arc<Result<InstalledPackages, String>>,
+}
+
+/// How much the next snapshot of a device should take, shown under its free space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrowthEstimate {
+    /// The device has no snapshot besides the first full copy
+    NotEnoughSnapshots,
+    /// Some of the snapshots weren't measured, and aren't being measured
+    NotMeasured,
+    Measuring,
+    Unknown(String),
+    Average {
+        bytes: u64,
+        samples: usize,
+    },
+}
+
+/// The snapshots marked on the snapshot list, and the deletion going through them one by one.
+#[derive(Debug, Default)]
+pub struct BulkDeletion {
+    /// Names of the marked snapshots of the current device
+    pub marked: BTreeSet<String>,
+    /// Where `v` started a range, as an index in the visible list
+    pub range_start: Option<usize>,
+    /// Choice selected in the mark menu
+    pub menu_index: usize,
+    /// Set when the number of days typed for "older than" isn't one
+    pub invalid_days: bool,
+    /// Still to delete, in order. What is left when the user stops the deletion is skipped.
+    pub queue: VecDeque<String>,
+    /// The snapshot being deleted
+    pub current: Option<String>,
+    /// The snapshots deleted so far, with the error of the ones that failed
+    pub results: Vec<(String, Result<(), String>)>,
+    /// The user asked to stop after the current snapshot
+    pub stopping: bool,
+    /// The marks of the user while the prune uses the marks for what it deletes. They are put
+    /// back when the prune is cancelled or done.
+    pub saved_marks: Option<BTreeSet<String>>,
+}
+
+impl BulkDeletion {
+    pub fn total(&self) -> usize {
+        self.results.len() + self.current.iter().count() + self.queue.len()
+    }
+}
+
+/// The lines of the mark menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkChoice {
+    Tag(SnapshotTag),
+    OlderThan,
+    Unmark,
+}
+
+impl MarkChoice {
+    pub fn all() -> Vec<MarkChoice> {
+        let mut choices: Vec<MarkChoice> =
+            SnapshotTag::ALL.into_iter().map(MarkChoice::Tag).collect();
+        choices.push(MarkChoice::OlderThan);
+        choices.push(MarkChoice::Unmark);
+        choices
+    }
+}
+
+/// A snapshot opened in the file browser. Its device stays mounted until the browser is closed.
+#[derive(Debug)]
+pub struct FileBrowser {
+    pub tree: SnapshotTree,
+    pub snapshot_name: String,
+    /// The folder displayed, inside the snapshot
+    pub dir: PathBuf,
+    pub entries: Result<Vec<FileEntry>, String>,
+    /// The preview of the selected file, read again when the selection moves
+    pub preview: Option<(PathBuf, Result<Preview, String>)>,
+    /// Where the selection was on the snapshot list, to go back to it
+    pub snapshot_index: usize,
+    /// The files and folders to restore, inside the snapshot
+    pub marked: BTreeSet<PathBuf>,
+    /// Choice selected in the destination popup
+    pub destination_index: usize,
+    /// Set when the folder typed isn't an absolute path
+    pub invalid_folder: bool,
+    /// What the restore will do, from the confirmation to the end of the restore
+    pub restore_plan: Option<FileRestorePlan>,
+    /// What the selected file can be compared with, in the diff popup
+    pub diff_targets: Vec<CompareTarget>,
+    /// Choice selected in the diff popup
+    pub diff_index: usize,
+}
+
+/// What a snapshot, or a file of it, can be compared with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompareTarget {
+    /// The running system, the same path for a file
+    Live,
+    /// Another snapshot of the device
+    Snapshot(String),
+}
+
+/// Everything that changed between a snapshot and the running system or another snapshot.
+#[derive(Debug)]
+pub struct ChangeReportView {
+    /// The oldest side
+    pub old: ComparedSystem,
+    pub new: ComparedSystem,
+    /// Files scanned so far and the last one, while the comparison runs
+    pub progress: (usize, PathBuf),
+    pub result: Option<Result<ChangeReport, String>>,
+    /// The thread comparing the two sides, until it is done
+    pub job: Option<ReportJob>,
+    /// Keeps the snapshots mounted while the report is open. The thread has them while it runs,
+    /// so they are only unmounted once it stopped reading them.
+    trees: Vec<SnapshotTree>,
+    /// The files of the selected folder have the focus, instead of the folders
+    pub focus_files: bool,
+    pub file_index: usize,
+    /// Where the selection was on the snapshot list, to go back to it
+    pub snapshot_index: usize,
+}
+
+impl ChangeReportView {
+    /// The changes of the selected folder, `group_index` being the selected folder.
+    pub fn files(&self, group_index: usize) -> Vec<&Change> {
+        let Some(Ok(report)) = &self.result else {
+            return Vec::new();
+        };
+        match report.groups.get(group_index) {
+            Some(group) => report.changes_in(&group.name).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// A side of the change report.
+#[derive(Debug)]
+pub struct ComparedSystem {
+    /// `None` for the running system
+    pub snapshot: Option<String>,
+    /// Where its `/` is
+    pub root: PathBuf,
+}
+
+impl ComparedSystem {
+    fn snapshot(name: String, tree: &SnapshotTree) -> Self {
+        ComparedSystem {
+            snapshot: Some(name),
+            root: tree.system_root(),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        self.snapshot.as_deref().unwrap_or("running system")
+    }
+
+    /// How a file of this side is named in the diff.
+    fn file_label(&self, path: &Path) -> String {
+        match &self.snapshot {
+            Some(name) => format!("{name}:/{}", path.display()),
+            None => format!("/{}", path.display()),
+        }
+    }
+}
+
+/// The sizes sent by the thread measuring the snapshots. Walking a snapshot takes a while, it has
+/// to be cancelled when its sizes aren't wanted anymore.
+#[derive(Debug)]
+pub struct SizeJob {
+    pub sizes: Receiver<(SnapshotKey, Result<SnapshotSize, String>)>,
+    pub cancel: CancelHandle,
+}
+
+#[derive(Debug)]
+pub struct ReportJob {
+    pub events: Receiver<ReportEvent>,
+    pub cancel: CancelHandle,
+}
+
+/// Sent by the thread comparing the two sides.
+#[derive(Debug)]
+pub enum ReportEvent {
+    Progress(usize, PathBuf),
+    /// The trees come back with the result, to stay mounted for the diffs
+    Done(Result<ChangeReport, String>, Vec<SnapshotTree>),
+}
+
+/// Two versions of a file compared, the old one first.
+#[derive(Debug)]
+pub struct DiffView {
+    pub old_label: String,
+    pub new_label: String,
+    /// `None` while the files are read in the background, a big file takes a while to hash
+    pub diff: Option<FileDiff>,
+    job: Option<Receiver<Result<FileDiff, String>>>,
+    /// First line displayed
+    pub scroll: usize,
+}
+
+impl DiffView {
+    pub fn line_count(&self) -> usize {
+        match &self.diff {
+            Some(FileDiff::Text(lines)) => lines.len(),
+            _ => 0,
+        }
+    }
+}
+
+impl FileBrowser {
+    fn load(&mut self) {
+        self.entries = browse::read_dir(&self.tree.path(&self.dir)).map_err(|e| format!("{e:#}"));
+        self.preview = None;
+    }
+}
+
+/// The lines of the destination popup of the file restore, in order.
+pub const FILE_RESTORE_CHOICES: [&str; 2] = ["Back where they were", "In another folder..."];
+
+/// A create, delete or restore running in its own thread, and what it tells us while running.
+#[derive(Debug)]
+pub struct RunningOperation {
+    pub thread: JoinHandle<anyhow::Result<()>>,
+    pub events: Receiver<OperationEvent>,
+    pub cancel: CancelHandle,
+}
+
+/// An operation that failed, with what timeshift told us about it.
+#[derive(Debug, Clone)]
+pub struct OperationFailure {
+    pub title: String,
+    pub code: Option<i32>,
+    pub details: String,
+    /// The user stopped it, nothing went wrong
+    pub cancelled: bool,
+}
+
+/// The choices made so far in the restore wizard. The indexes point in `restore_targets` and
+/// `bootloader_choices`.
+#[derive(Debug, Default)]
+pub struct RestoreWizard {
+    pub snapshot_name: String,
+    pub target_index: usize,
+    pub bootloader_index: usize,
+    /// Set when the name typed to confirm doesn't match the snapshot
+    pub confirmation_mismatch: bool,
+}
+
+/// Order of a table. Timeshift gives the snapshots from the oldest to the newest.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn toggle(self) -> Self {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+}
+
+/// Which column a table is sorted by, and in which order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TableSort<C> {
+    pub column: C,
+    pub order: SortOrder,
+}
+
+impl<C: Copy + PartialEq> TableSort<C> {
+    /// Sorting by the same column again reverses the order.
+    pub fn by(&mut self, column: C) {
+        if self.column == column {
+            self.order = self.order.toggle();
+        } else {
+            self.column = column;
+            self.order = SortOrder::Ascending;
+        }
+    }
+
+    fn apply(&self, ordering: Ordering) -> Ordering {
+        match self.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    }
+}
+
+/// The columns of the snapshot table. The number keys sort by them, in this order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotColumn {
+    Num,
+    /// The name of a snapshot is its date
+    #[default]
+    Date,
+    Age,
+    Tags,
+    /// What deleting the snapshot frees, only for rsync
+    Size,
+    Comment,
+}
+
+impl SnapshotColumn {
+    pub const ALL: [SnapshotColumn; 6] = [
+        SnapshotColumn::Num,
+        SnapshotColumn::Date,
+        SnapshotColumn::Age,
+        SnapshotColumn::Tags,
+        SnapshotColumn::Size,
+        SnapshotColumn::Comment,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SnapshotColumn::Num => "#",
+            SnapshotColumn::Date => "Name",
+            SnapshotColumn::Age => "Age",
+            SnapshotColumn::Tags => "Tags",
+            SnapshotColumn::Size => "Unique / total",
+            SnapshotColumn::Comment => "Comment",
+        }
+    }
+}
+
+/// The columns of the device table, same as `SnapshotColumn`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceColumn {
+    /// The order timeshift lists them in
+    #[default]
+    Num,
+    Name,
+    Size,
+    Type,
+    Label,
+    Mount,
+    Snapshots,
+}
+
+impl DeviceColumn {
+    pub const ALL: [DeviceColumn; 7] = [
+        DeviceColumn::Num,
+        DeviceColumn::Name,
+        DeviceColumn::Size,
+        DeviceColumn::Type,
+        DeviceColumn::Label,
+        DeviceColumn::Mount,
+        DeviceColumn::Snapshots,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DeviceColumn::Num => "#",
+            DeviceColumn::Name => "Device",
+            DeviceColumn::Size => "Size",
+            DeviceColumn::Type => "Type",
+            DeviceColumn::Label => "Label",
+            DeviceColumn::Mount => "Mounted on",
+            DeviceColumn::Snapshots => "Snapshots",
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -48,10 +538,19 @@ pub enum InputMode {
 /// THis video opened my mind about how to handle things :
 /// https://www.youtube.com/watch?v=z-0-bbc80JM
 #[derive(Debug, Default)]
+#[allow(clippy::enum_variant_names)] // Screen::DeviceScreen reads better than Screen::Device
 pub enum Screen {
     #[default]
     DeviceScreen,
     SnapshotScreen,
+    /// The settings of timeshift itself, from `timeshift.json`
+    SettingsScreen,
+    /// The scheduled snapshots, also from `timeshift.json`
+    ScheduleScreen,
+    /// The files of a snapshot
+    BrowserScreen,
+    /// What changed between a snapshot and the running system or another snapshot
+    ReportScreen,
 }
 
 /// This enum represent the action that is done by user
@@ -62,22 +561,347 @@ pub enum CurrentAction {
     SnapshotCreation,
     SnapshotCreationPending,
     SnapshotDeletion,
-    SnapshotDeletionConfirmation, // Its not really an action done by the user, but its a state for
-                                  // the app
+    // Its not really an action done by the user, but its a state for the app
+    SnapshotDeletionConfirmation,
+    /// Restore wizard : choosing the device to restore on
+    RestoreTarget,
+    /// Restore wizard : choosing where to install GRUB, or to skip it
+    RestoreBootloader,
+    /// Restore wizard : everything that will be done, before confirming
+    RestoreSummary,
+    /// Restore wizard : the user has to type the name of the snapshot
+    RestoreConfirmation,
+    RestorePending,
+    /// The user pressed Esc during a creation, we ask before stopping it
+    SnapshotCreationCancelConfirmation,
+    /// Choosing which snapshots to mark : by tag, older than some days...
+    MarkMenu,
+    /// Typing the number of days for "older than"
+    MarkOlderThan,
+    BulkDeletionConfirmation,
+    /// The marked snapshots are deleted one after the other
+    BulkDeletion,
+    /// What was deleted and what failed, once the bulk deletion is over
+    BulkDeletionSummary,
+    /// Editing the retention policy, the snapshot list previews it
+    RetentionPolicy,
+    /// Typing the search, the list narrows at each key
+    Search,
+    /// Choosing the format of the inventory export
+    ExportMenu,
+    /// The inventory was written, we tell where
+    Exported,
+    /// Restoring files from the browser : back where they were, or in another folder
+    FileRestoreDestination,
+    /// Typing the folder the files are restored in
+    FileRestoreFolder,
+    /// What will be replaced, before confirming
+    FileRestoreConfirmation,
+    FileRestorePending,
+    /// The files are back, we tell where the replaced ones went
+    FileRestored,
+    /// Choosing what to compare the selected file with
+    DiffMenu,
+    /// The diff is displayed over the screen
+    Diff,
+    /// Choosing what to compare the selected snapshot with, for the change report
+    CompareMenu,
+    /// The last operation failed, the error popup is displayed
+    OperationFailed,
+    /// A value of the Settings screen is being typed
+    SettingsEditing,
 }
 
-impl App {
-    pub fn new(timeshift_instance: Timeshift) -> Self {
+impl<B: Backend> App<B> {
+    pub fn new(timeshift_instance: Timeshift<B>) -> Self {
+        // Pinning is not worth refusing to start over, a broken file shows when pinning
+        let pinned = timeshift_instance.backend.read_pins().unwrap_or_default();
         Self {
             exit: false,
             timeshift_instance,
             current_index: 0,
             current_device_name: String::new(),
-            operation_thread: None,
-            ..Default::default()
+            current_display_screen: Screen::default(),
+            operation: None,
+            operation_log: Vec::new(),
+            operation_progress: None,
+            throbber_state: RefCell::default(),
+            current_action: CurrentAction::default(),
+            input_mode: InputMode::default(),
+            input: Input::default(),
+            snapshot_sort: TableSort::default(),
+            device_sort: TableSort::default(),
+            tag_filter: None,
+            search: Input::default(),
+            restore_wizard: RestoreWizard::default(),
+            operation_failure: None,
+            show_details: false,
+            snapshot_details: HashMap::new(),
+            snapshot_subvolumes: HashMap::new(),
+            snapshot_packages: HashMap::new(),
+            live_packages: None,
+            details_job: None,
+            settings: SettingsEditor::default(),
+            create_include_home: false,
+            snapshot_sizes: HashMap::new(),
+            size_job: None,
+            bulk: BulkDeletion::default(),
+            retention: RetentionPolicy::default(),
+            pinned,
+            prune_preview: false,
+            retention_index: 0,
+            browser: None,
+            diff: None,
+            compare_targets: Vec::new(),
+            compare_index: 0,
+            report: None,
+            export_index: 0,
+            exported_path: None,
+            stale_after: TimeDelta::days(30),
+        }
+    }
+
+    pub fn with_stale_after(mut self, stale_after: TimeDelta) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    /// The snapshots of the current device, in the order they are displayed. `current_index`
+    /// is an index in this list, not in the one of timeshift.
+    pub fn visible_snapshots(&self) -> Vec<&Snapshot> {
+        let mut snapshots: Vec<&Snapshot> = self
+            .timeshift_instance
+            .devices_map_by_name
+            .get(&self.current_device_name)
+            .map(|snapshots| {
+                snapshots
+                    .iter()
+                    .filter(|s| self.tag_filter.is_none_or(|tag| s.tags.contains(&tag)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let size = |s: &Snapshot| {
+            self.snapshot_sizes
+                .get(&(self.current_device_name.clone(), s.name.clone()))
+                .and_then(|size| size.as_ref().ok())
+                .map(|size| size.unique)
+        };
+        // The date breaks the ties, so the snapshots with the same tags stay in order
+        snapshots.sort_by(|a, b| {
+            let ordering = match self.snapshot_sort.column {
+                SnapshotColumn::Num => a.num.cmp(&b.num),
+                SnapshotColumn::Date => a.date.cmp(&b.date),
+                SnapshotColumn::Age => b.date.cmp(&a.date),
+                SnapshotColumn::Tags => a.tags.cmp(&b.tags),
+                SnapshotColumn::Size => size(a).cmp(&size(b)),
+                SnapshotColumn::Comment => a.description.cmp(&b.description),
+            };
+            self.snapshot_sort.apply(ordering.then(a.date.cmp(&b.date)))
+        });
+        let query = self.search.value();
+        if query.trim().is_empty() {
+            return snapshots;
+        }
+        // The sort is stable, the snapshots that match as well keep the order by date
+        let mut ranked: Vec<(u32, &Snapshot)> = snapshots
+            .into_iter()
+            .filter_map(|s| search_score(s, query).map(|score| (score, s)))
+            .collect();
+        ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        ranked.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// After the search changed, the selected snapshot stays selected if it still matches.
+    pub fn update_search(&mut self, previous: Option<String>) {
+        self.bulk.range_start = None;
+        self.current_index = previous
+            .and_then(|name| {
+                self.visible_snapshots()
+                    .iter()
+                    .position(|snapshot| snapshot.name == name)
+            })
+            .unwrap_or(0);
+    }
+
+    /// The devices in the order they are displayed, `current_index` points in this list on the
+    /// device screen.
+    pub fn visible_devices(&self) -> Vec<&Device> {
+        let mut devices: Vec<&Device> = self.timeshift_instance.devices_map.keys().collect();
+        let snapshot_count = |device: &Device| {
+            self.timeshift_instance
+                .devices_map
+                .get(device)
+                .map_or(0, |snapshots| snapshots.len())
+        };
+        devices.sort_by(|a, b| {
+            let ordering = match self.device_sort.column {
+                DeviceColumn::Num => a.num.cmp(&b.num),
+                DeviceColumn::Name => a.device_name.cmp(&b.device_name),
+                DeviceColumn::Size => a.size_bytes().cmp(&b.size_bytes()),
+                DeviceColumn::Type => a.device_type.to_string().cmp(&b.device_type.to_string()),
+                DeviceColumn::Label => a.label.cmp(&b.label),
+                DeviceColumn::Mount => a.mount_point.cmp(&b.mount_point),
+                DeviceColumn::Snapshots => snapshot_count(a).cmp(&snapshot_count(b)),
+            };
+            self.device_sort.apply(ordering.then(a.num.cmp(&b.num)))
+        });
+        devices
+    }
+
+    /// The columns of the snapshot table, the sizes only mean something for rsync.
+    pub fn snapshot_columns(&self) -> Vec<SnapshotColumn> {
+        SnapshotColumn::ALL
+            .into_iter()
+            .filter(|column| *column != SnapshotColumn::Size || !self.timeshift_instance.btrfs_mode)
+            .collect()
+    }
+
+    /// Goes through no filter -> every tag -> no filter.
+    pub fn cycle_tag_filter(&mut self) {
+        self.tag_filter = match self.tag_filter {
+            None => Some(SnapshotTag::ALL[0]),
+            Some(tag) => SnapshotTag::ALL
+                .iter()
+                .position(|t| *t == tag)
+                .and_then(|i| SnapshotTag::ALL.get(i + 1))
+                .copied(),
+        };
+        self.current_index = 0;
+    }
+
+    /// The devices a snapshot can be restored on.
+    pub fn restore_targets(&self) -> Vec<String> {
+        self.timeshift_instance
+            .devices_map
+            .keys()
+            .map(|device| device.device_name.clone())
+            .collect()
+    }
+
+    /// The disks GRUB can be installed on, and skipping GRUB as the last choice.
+    pub fn bootloader_choices(&self) -> Vec<Bootloader> {
+        let mut choices: Vec<Bootloader> = Vec::new();
+        for device in self.timeshift_instance.devices_map.keys() {
+            let disk = Bootloader::Reinstall(device.disk());
+            if !choices.contains(&disk) {
+                choices.push(disk);
+            }
+        }
+        choices.push(Bootloader::Skip);
+        choices
+    }
+
+    /// What will be given to timeshift with the current choices of the wizard.
+    pub fn restore_options(&self) -> Option<RestoreOptions> {
+        Some(RestoreOptions {
+            snapshot_name: self.restore_wizard.snapshot_name.clone(),
+            snapshot_device: self.current_device_name.clone(),
+            target_device: self
+                .restore_targets()
+                .get(self.restore_wizard.target_index)?
+                .clone(),
+            bootloader: self
+                .bootloader_choices()
+                .get(self.restore_wizard.bootloader_index)?
+                .clone(),
+        })
+    }
+
+    pub fn current_snapshot(&self) -> Option<&Snapshot> {
+        self.visible_snapshots().get(self.current_index).copied()
+    }
+
+    /// The marked snapshots that still exist on the current device, oldest first. The ones
+    /// hidden by the tag filter or the search are included.
+    pub fn marked_snapshots(&self) -> Vec<&Snapshot> {
+        let mut snapshots: Vec<&Snapshot> = self
+            .timeshift_instance
+            .devices_map_by_name
+            .get(&self.current_device_name)
+            .into_iter()
+            .flatten()
+            .filter(|s| self.bulk.marked.contains(&s.name))
+            .collect();
+        snapshots.sort_by_key(|s| s.date);
+        snapshots
+    }
+
+    /// What the retention policy keeps among all the snapshots of the current device, the
+    /// tag filter doesn't change it.
+    pub fn prune_verdicts(&self) -> HashMap<String, KeepReason> {
+        let snapshots: Vec<&Snapshot> = self
+            .timeshift_instance
+            .devices_map_by_name
+            .get(&self.current_device_name)
+            .map(|snapshots| snapshots.iter().collect())
+            .unwrap_or_default();
+        self.retention
+            .evaluate(&snapshots, &self.pinned, Local::now())
+    }
+
+    /// Pins the selected snapshot, or unpins it. The file is read again first so nothing
+    /// pinned meanwhile by another instance is lost.
+    pub fn toggle_pin(&mut self) {
+        let Some(name) = self.current_snapshot().map(|s| s.name.clone()) else {
+            return;
+        };
+        let backend = &self.timeshift_instance.backend;
+        let pinned = backend.read_pins().and_then(|mut pinned| {
+            if !pinned.remove(&name) {
+                pinned.insert(name);
+            }
+            backend.write_pins(&pinned).map(|()| pinned)
+        });
+        match pinned {
+            Ok(pinned) => self.pinned = pinned,
+            Err(e) => self.show_failure("Error pinning snapshot", None, format!("{e:#}")),
         }
     }
 
+    /// The snapshots of the current device the retention policy would delete.
+    pub fn snapshots_to_prune(&self) -> Vec<&Snapshot> {
+        let verdicts = self.prune_verdicts();
+        self.timeshift_instance
+            .devices_map_by_name
+            .get(&self.current_device_name)
+            .into_iter()
+            .flatten()
+            .filter(|s| !verdicts.contains_key(&s.name))
+            .collect()
+    }
+
+    /// Deletes the next marked snapshot, or shows the summary once there is none left.
+    pub fn delete_next_marked(&mut self) {
+        let next = if self.bulk.stopping {
+            None
+        } else {
+            self.bulk.queue.pop_front()
+        };
+        let Some(snapshot_name) = next else {
+            self.bulk.current = None;
+            if let Some(saved) = self.bulk.saved_marks.take() {
+                self.bulk.marked = saved;
+            }
+            // Only the ones that failed stay marked, to try them again
+            for (name, result) in &self.bulk.results {
+                if result.is_ok() {
+                    self.bulk.marked.remove(name);
+                }
+            }
+            self.update_snapshot_list();
+            self.snapshot_sizes.clear();
+            self.stop_size_job();
+            self.current_index = 0;
+            self.current_action = CurrentAction::BulkDeletionSummary;
+            return;
+        };
+        self.bulk.current = Some(snapshot_name.clone());
+        let current_device = self.current_device_name.clone();
+        self.start_operation(CurrentAction::BulkDeletion, move |backend, context| {
+            backend.delete_snapshot(&snapshot_name, &current_device, context)
+        });
+    }
+
     /// runs the application's main loop until the user quits
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
         self.current_index = 0;
@@ -105,9 +929,34 @@ impl App {
     }
 
     fn update(&mut self) {
+        self.read_sizes();
+        self.read_details();
+        if self.show_details
+            && let Screen::SnapshotScreen = self.current_display_screen
+        {
+            self.load_current_details();
+        }
+        if let Screen::BrowserScreen = self.current_display_screen {
+            self.load_preview();
+        }
+        self.read_diff();
+        if self
+            .report
+            .as_ref()
+            .is_some_and(|report| report.job.is_some())
+        {
+            self.throbber_state.borrow_mut().calc_next();
+            self.read_report_events();
+        }
         match self.current_action {
-            CurrentAction::SnapshotCreationPending | CurrentAction::SnapshotDeletion => {
+            CurrentAction::SnapshotCreationPending
+            | CurrentAction::SnapshotCreationCancelConfirmation
+            | CurrentAction::SnapshotDeletion
+            | CurrentAction::BulkDeletion
+            | CurrentAction::RestorePending
+            | CurrentAction::FileRestorePending => {
                 self.throbber_state.borrow_mut().calc_next();
+                self.read_operation_events();
                 self.check_operation_status();
             }
             _ => (),
@@ -117,17 +966,40 @@ impl App {
     fn draw_frame(&self, frame: &mut Frame) {
         frame.render_widget(Clear, frame.area());
 
-        let cursor = if let CurrentAction::SnapshotCreation = self.current_action {
-            let popup_area = center(
-                frame.area(),
-                Constraint::Percentage(30),
-                Constraint::Length(10),
-            );
-            frame.render_widget(self, frame.area());
-            self.render_creation_popup(popup_area, frame.buffer_mut())
-        } else {
-            frame.render_widget(self, frame.area());
-            None
+        let cursor = match self.current_action {
+            CurrentAction::SnapshotCreation => {
+                let popup_area = center(
+                    frame.area(),
+                    Constraint::Percentage(30),
+                    Constraint::Length(10),
+                );
+                frame.render_widget(self, frame.area());
+                self.render_creation_popup(popup_area, frame.buffer_mut())
+            }
+            CurrentAction::RestoreConfirmation => {
+                frame.render_widget(self, frame.area());
+                self.render_restore_confirmation(frame.area(), frame.buffer_mut())
+            }
+            CurrentAction::SettingsEditing => {
+                frame.render_widget(self, frame.area());
+                self.render_settings_input(frame.area(), frame.buffer_mut())
+            }
+            CurrentAction::MarkOlderThan => {
+                frame.render_widget(self, frame.area());
+                self.render_mark_older_than(frame.area(), frame.buffer_mut())
+            }
+            CurrentAction::Search => {
+                frame.render_widget(self, frame.area());
+                self.search_cursor(frame.area())
+            }
+            CurrentAction::FileRestoreFolder => {
+                frame.render_widget(self, frame.area());
+                self.render_file_restore_folder(frame.area(), frame.buffer_mut())
+            }
+            _ => {
+                frame.render_widget(self, frame.area());
+                None
+            }
         };
 
         if let Some(pos) = cursor {
@@ -135,46 +1007,963 @@ impl App {
         }
     }
 
+    /// Starts reading the `info.json` of the selected snapshot and comparing its packages with
+    /// the running system, if it wasn't already done. One snapshot at a time : going through
+    /// the list quickly only loads the one the cursor stops on.
+    fn load_current_details(&mut self) {
+        if self.details_job.is_some() {
+            return;
+        }
+        let Some(snapshot) = self.current_snapshot() else {
+            return;
+        };
+        let key = (self.current_device_name.clone(), snapshot.name.clone());
+        if self.snapshot_details.contains_key(&key) && self.snapshot_packages.contains_key(&key) {
+            return;
+        }
+        let backend = self.timeshift_instance.backend.clone();
+        let btrfs = self.timeshift_instance.btrfs_mode && self.current_device_is_btrfs();
+        let live_packages = self.live_packages.clone();
+        let (sender, details) = mpsc::channel();
+        thread::spawn(move || {
+            let (device, name) = (&key.0, &key.1);
+            let info = backend
+                .snapshot_info(name, device)
+                .map_err(|e| format!("{e:#}"));
+            let subvolumes = btrfs.then(|| {
+                backend
+                    .snapshot_subvolumes(name, device)
+                    .map_err(|e| format!("{e:#}"))
+            });
+            let live_packages = live_packages.unwrap_or_else(|| {
+                Arc::new(backend.installed_packages().map_err(|e| format!("{e:#}")))
+            });
+            let packages = backend
+                .snapshot_packages(name, device)
+                .map_err(|e| format!("{e:#}"))
+                .and_then(|old| {
+                    let new = live_packages.as_ref().as_ref().map_err(String::clone)?;
+                    packages::diff_packages(&old, new).map_err(|e| format!("{e:#}"))
+                });
+            // The app may not want them anymore, nothing to do about it
+            let _ = sender.send(LoadedDetails {
+                key,
+                info,
+                subvolumes,
+                packages,
+                live_packages,
+            });
+        });
+        self.details_job = Some(details);
+    }
+
+    /// Keeps what the details job found, once it is done.
+    fn read_details(&mut self) {
+        let Some(details) = &self.details_job else {
+            return;
+        };
+        let loaded = match details.try_recv() {
+            Ok(loaded) => loaded,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.details_job = None;
+                return;
+            }
+        };
+        self.details_job = None;
+        if let Some(subvolumes) = loaded.subvolumes {
+            self.snapshot_subvolumes
+                .insert(loaded.key.clone(), subvolumes);
+        }
+        self.snapshot_packages
+            .insert(loaded.key.clone(), loaded.packages);
+        self.snapshot_details.insert(loaded.key, loaded.info);
+        self.live_packages = Some(loaded.live_packages);
+    }
+
+    fn current_device_is_btrfs(&self) -> bool {
+        self.timeshift_instance
+            .devices_map
+            .keys()
+            .any(|device| device.device_name == self.current_device_name && device.is_btrfs())
+    }
+
+    /// Measures the snapshots that haven't been yet, in their own thread : all the snapshots of
+    /// the current device, or on the device list the recent ones of the selected device, for the
+    /// growth estimate. It reads every file of the snapshots, so it only starts when asked.
+    pub fn start_size_job(&mut self) {
+        if self.size_job.is_some() || self.timeshift_instance.btrfs_mode {
+            return;
+        }
+        let (device_name, snapshots) = match self.current_display_screen {
+            Screen::DeviceScreen => {
+                let Some(device) = self.selected_device() else {
+                    return;
+                };
+                let device_name = device.device_name.clone();
+                let snapshots = self.growth_snapshots(&device_name);
+                (device_name, snapshots)
+            }
+            _ => (
+                self.current_device_name.clone(),
+                self.timeshift_instance
+                    .devices_map_by_name
+                    .get(&self.current_device_name)
+                    .map(|snapshots| snapshots.iter().collect())
+                    .unwrap_or_default(),
+            ),
+        };
+        let names: Vec<String> = snapshots
+            .into_iter()
+            .map(|snapshot| snapshot.name.clone())
+            .filter(|name| {
+                !self
+                    .snapshot_sizes
+                    .contains_key(&(device_name.clone(), name.clone()))
+            })
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        let backend = self.timeshift_instance.backend.clone();
+        let (sender, sizes) = mpsc::channel();
+        let cancel = CancelHandle::default();
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || {
+            backend.snapshot_sizes(&device_name, &names, &thread_cancel, &mut |name, size| {
+                // The app may not want them anymore, nothing to do about it
+                let _ = sender.send((
+                    (device_name.clone(), name.to_string()),
+                    size.map_err(|e| format!("{e:#}")),
+                ));
+            });
+        });
+        self.size_job = Some(SizeJob { sizes, cancel });
+    }
+
+    /// Stops measuring, the sizes being measured are thrown away.
+    fn stop_size_job(&mut self) {
+        if let Some(job) = self.size_job.take() {
+            job.cancel.cancel();
+        }
+    }
+
+    /// The device under the cursor on the device list.
+    pub fn selected_device(&self) -> Option<&Device> {
+        self.visible_devices().get(self.current_index).copied()
+    }
+
+    /// The newest snapshots of a device, which tell how much a new one takes. The oldest
+    /// snapshot is never one of them : alone, it holds a full copy of the system.
+    fn growth_snapshots(&self, device_name: &str) -> Vec<&Snapshot> {
+        let mut snapshots: Vec<&Snapshot> = self
+            .timeshift_instance
+            .devices_map_by_name
+            .get(device_name)
+            .map(|snapshots| snapshots.iter().collect())
+            .unwrap_or_default();
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.date));
+        snapshots.pop();
+        snapshots.truncate(GROWTH_SAMPLE);
+        snapshots
+    }
+
+    /// How much the recent snapshots of a device took on average.
+    pub fn growth_estimate(&self, device_name: &str) -> GrowthEstimate {
+        if self.timeshift_instance.btrfs_mode {
+            return GrowthEstimate::Unknown("btrfs snapshots share their data".to_string());
+        }
+        let snapshots = self.growth_snapshots(device_name);
+        if snapshots.is_empty() {
+            return GrowthEstimate::NotEnoughSnapshots;
+        }
+        let mut growths = Vec::new();
+        let mut error = None;
+        for snapshot in snapshots {
+            match self
+                .snapshot_sizes
+                .get(&(device_name.to_string(), snapshot.name.clone()))
+            {
+                None if self.size_job.is_some() => return GrowthEstimate::Measuring,
+                None => return GrowthEstimate::NotMeasured,
+                Some(Ok(size)) => growths.push(size.unique),
+                Some(Err(e)) => error = Some(e.clone()),
+            }
+        }
+        match (growths.len() as u64, error) {
+            (0, Some(error)) => GrowthEstimate::Unknown(error),
+            (0, None) => GrowthEstimate::NotEnoughSnapshots,
+            (samples, _) => GrowthEstimate::Average {
+                bytes: growths.iter().sum::<u64>() / samples,
+                samples: samples as usize,
+            },
+        }
+    }
+
+    fn read_sizes(&mut self) {
+        let Some(job) = &self.size_job else {
+            return;
+        };
+        // Sorted by size, the list moves as the sizes come in. The cursor stays on its snapshot,
+        // or `d` and `Enter` could act on another one than the one the user saw.
+        let selected = match self.current_display_screen {
+            Screen::SnapshotScreen => self.current_snapshot().map(|s| s.name.clone()),
+            _ => None,
+        };
+        loop {
+            match job.sizes.try_recv() {
+                Ok((key, size)) => {
+                    self.snapshot_sizes.insert(key, size);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.size_job = None;
+                    break;
+                }
+            }
+        }
+        if let Some(name) = selected
+            && let Some(index) = self
+                .visible_snapshots()
+                .iter()
+                .position(|snapshot| snapshot.name == name)
+        {
+            self.current_index = index;
+        }
+    }
+
+    /// The subvolumes of the selected snapshot, only read in btrfs mode.
+    pub fn current_subvolumes(&self) -> Option<&Result<Vec<Subvolume>, String>> {
+        let snapshot = self.current_snapshot()?;
+        self.snapshot_subvolumes
+            .get(&(self.current_device_name.clone(), snapshot.name.clone()))
+    }
+
+    /// The packages upgraded, downgraded, added and removed since the selected snapshot.
+    pub fn current_packages(&self) -> Option<&Result<Vec<PackageChange>, String>> {
+        let snapshot = self.current_snapshot()?;
+        self.snapshot_packages
+            .get(&(self.current_device_name.clone(), snapshot.name.clone()))
+    }
+
+    /// The details of the selected snapshot, once they have been read.
+    pub fn current_details(&self) -> Option<&Result<SnapshotInfo, String>> {
+        let snapshot = self.current_snapshot()?;
+        self.snapshot_details
+            .get(&(self.current_device_name.clone(), snapshot.name.clone()))
+    }
+
+    /// The lines of the Settings screen : the fields, then the filters.
+    pub fn settings_rows(&self) -> Vec<SettingsRow> {
+        let Some(config) = &self.settings.config else {
+            return Vec::new();
+        };
+        let mut rows: Vec<SettingsRow> = ConfigField::ALL
+            .into_iter()
+            .map(SettingsRow::Field)
+            .collect();
+        rows.extend((0..config.filters.len()).map(SettingsRow::Filter));
+        rows.push(SettingsRow::AddFilter);
+        rows
+    }
+
+    /// The newest snapshot with this tag, on every device.
+    pub fn last_snapshot_with(&self, tag: SnapshotTag) -> Option<&Snapshot> {
+        self.timeshift_instance
+            .devices_map_by_name
+            .values()
+            .flatten()
+            .filter(|snapshot| snapshot.tags.contains(&tag))
+            .max_by_key(|snapshot| snapshot.date)
+    }
+
+    /// A failure is shown in the error popup, unless the one of the operation already is.
     pub fn update_snapshot_list(&mut self) {
-        self.timeshift_instance.update();
+        // The packages of the system may have changed, a job still running compared the old ones
+        self.snapshot_packages.clear();
+        self.live_packages = None;
+        self.details_job = None;
+        if let Err(e) = self.timeshift_instance.update()
+            && !matches!(self.current_action, CurrentAction::OperationFailed)
+        {
+            let (code, details) = match e.downcast_ref::<CommandError>() {
+                Some(error) => (error.code, error.output.clone()),
+                None => (None, format!("{e:#}")),
+            };
+            self.show_failure("Couldn't refresh the snapshot list", code, details);
+        }
+    }
+
+    /// Runs `operation` in its own thread with a clone of the backend. What it sends is read in
+    /// `update`, and the result once the thread is done.
+    pub fn start_operation<F>(&mut self, action: CurrentAction, operation: F)
+    where
+        F: FnOnce(B, &OperationContext) -> anyhow::Result<()> + Send + 'static,
+    {
+        let backend = self.timeshift_instance.backend.clone();
+        let (sender, events) = mpsc::channel();
+        let context = OperationContext::new(sender);
+        let cancel = context.cancel_handle();
+        self.operation_log.clear();
+        self.operation_progress = None;
+        self.current_action = action;
+        self.operation = Some(RunningOperation {
+            thread: thread::spawn(move || operation(backend, &context)),
+            events,
+            cancel,
+        });
+    }
+
+    /// Asks the running operation to stop, the result comes back as usual once it is done.
+    pub fn cancel_operation(&mut self) {
+        if let Some(operation) = &self.operation {
+            operation.cancel.cancel();
+        }
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.operation
+            .as_ref()
+            .is_some_and(|operation| operation.cancel.is_cancelled())
+    }
+
+    fn read_operation_events(&mut self) {
+        let Some(operation) = &self.operation else {
+            return;
+        };
+        while let Ok(event) = operation.events.try_recv() {
+            match event {
+                OperationEvent::Line(line) => self.operation_log.push(line),
+                OperationEvent::Progress(progress) => self.operation_progress = Some(progress),
+            }
+        }
+        if self.operation_log.len() > OPERATION_LOG_LINES {
+            let extra = self.operation_log.len() - OPERATION_LOG_LINES;
+            self.operation_log.drain(..extra);
+        }
     }
 
     fn check_operation_status(&mut self) {
-        if let Some(handle) = self.operation_thread.take() {
+        if let Some(operation) = self.operation.take() {
+            let handle = operation.thread;
             if handle.is_finished() {
+                if let CurrentAction::BulkDeletion = self.current_action {
+                    // The failures are kept for the summary instead of stopping the queue
+                    let result = match handle.join() {
+                        Ok(Ok(())) => Ok(()),
+                        Ok(Err(e)) => Err(format!("{e:#}")),
+                        Err(_) => Err("Thread error while operating snapshot".to_string()),
+                    };
+                    if let Some(name) = self.bulk.current.take() {
+                        self.bulk.results.push((name, result));
+                    }
+                    self.delete_next_marked();
+                    return;
+                }
+                let title = match self.current_action {
+                    CurrentAction::SnapshotDeletion => "Error deleting snapshot",
+                    CurrentAction::SnapshotCreationPending
+                    | CurrentAction::SnapshotCreationCancelConfirmation => {
+                        "Error creating snapshot"
+                    }
+                    CurrentAction::RestorePending => "Error restoring snapshot",
+                    CurrentAction::FileRestorePending => "Error restoring files",
+                    _ => "Error",
+                };
+                let restoring_files =
+                    matches!(self.current_action, CurrentAction::FileRestorePending);
                 match handle.join() {
+                    Ok(Ok(())) if restoring_files => {
+                        if let Some(browser) = &mut self.browser {
+                            browser.marked.clear();
+                        }
+                        self.current_action = CurrentAction::FileRestored;
+                    }
                     Ok(Ok(())) => {
                         // Succès
                         self.current_action = CurrentAction::Idle;
-                        self.update_snapshot_list();
-                        self.current_index = 0;
                     }
                     Ok(Err(e)) => {
-                        match self.current_action {
-                            CurrentAction::SnapshotDeletion => {
-                                // Erreur de suppression
-                                panic!("Error deleting snapshot : {:?}", e.to_string());
+                        if let Some(cancelled) = e.downcast_ref::<Cancelled>() {
+                            // The user asked for it, we tell how the cancellation went
+                            self.show_failure(
+                                "Operation cancelled",
+                                None,
+                                cancelled.details.clone(),
+                            );
+                            if let Some(failure) = &mut self.operation_failure {
+                                failure.cancelled = true;
                             }
-                            CurrentAction::SnapshotCreationPending => {
-                                panic!("Error creating snapshot : {:?}", e.to_string());
-                            }
-                            _ => (),
+                        } else {
+                            // timeshift itself failed, we get the exit code and its output
+                            let (code, details) = match e.downcast_ref::<CommandError>() {
+                                Some(error) => (error.code, error.output.clone()),
+                                None => (None, format!("{e:#}")),
+                            };
+                            self.show_failure(title, code, details);
                         }
                     }
                     Err(_) => {
                         // Thread panic
-                        panic!("Thread error while operating snapshot");
+                        self.show_failure(
+                            title,
+                            None,
+                            "Thread error while operating snapshot".to_string(),
+                        );
                     }
                 }
+                if restoring_files {
+                    // The snapshots haven't changed, the browser stays where it was
+                    return;
+                }
+                // Even when it failed, the operation may have changed something
+                self.update_snapshot_list();
+                // Deleting a snapshot changes what the others own, everything is measured again
+                self.snapshot_sizes.clear();
+                self.stop_size_job();
+                self.current_index = 0;
             } else {
                 // Remettre le handle si pas encore terminé
-                self.operation_thread = Some(handle);
+                self.operation = Some(RunningOperation {
+                    thread: handle,
+                    events: operation.events,
+                    cancel: operation.cancel,
+                });
+            }
+        }
+    }
+
+    /// Opens the selected snapshot in the file browser, mounting its device if needed.
+    pub fn open_browser(&mut self) {
+        let Some(snapshot) = self.current_snapshot() else {
+            return;
+        };
+        let snapshot_name = snapshot.name.clone();
+        let tree = match self
+            .timeshift_instance
+            .backend
+            .open_snapshot(&snapshot_name, &self.current_device_name)
+        {
+            Ok(tree) => tree,
+            Err(e) => {
+                self.show_failure("Error opening snapshot", None, format!("{e:#}"));
+                return;
+            }
+        };
+        let mut browser = FileBrowser {
+            tree,
+            snapshot_name,
+            dir: PathBuf::new(),
+            entries: Ok(Vec::new()),
+            preview: None,
+            snapshot_index: self.current_index,
+            marked: BTreeSet::new(),
+            destination_index: 0,
+            invalid_folder: false,
+            restore_plan: None,
+            diff_targets: Vec::new(),
+            diff_index: 0,
+        };
+        browser.load();
+        self.browser = Some(browser);
+        self.current_display_screen = Screen::BrowserScreen;
+        self.current_index = 0;
+    }
+
+    /// Unmounts the snapshot and goes back to the snapshot list where we left it.
+    pub fn close_browser(&mut self) {
+        if let Some(browser) = self.browser.take() {
+            self.current_index = browser.snapshot_index;
+        }
+        self.current_display_screen = Screen::SnapshotScreen;
+    }
+
+    /// The file under the cursor in the browser.
+    pub fn browser_selection(&self) -> Option<&FileEntry> {
+        let browser = self.browser.as_ref()?;
+        browser.entries.as_ref().ok()?.get(self.current_index)
+    }
+
+    /// Goes in the selected folder. Links are never followed.
+    pub fn browser_enter(&mut self) {
+        let Some(entry) = self.browser_selection().filter(|entry| entry.is_dir()) else {
+            return;
+        };
+        let name = entry.name.clone();
+        if let Some(browser) = &mut self.browser {
+            browser.dir.push(name);
+            browser.load();
+        }
+        self.current_index = 0;
+    }
+
+    /// Goes to the parent folder with the one we left selected, or closes the browser from the
+    /// top of the snapshot.
+    pub fn browser_up(&mut self) {
+        let Some(browser) = &mut self.browser else {
+            return;
+        };
+        let Some(left) = browser
+            .dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+        else {
+            self.close_browser();
+            return;
+        };
+        browser.dir.pop();
+        browser.load();
+        self.current_index = browser
+            .entries
+            .as_ref()
+            .ok()
+            .and_then(|entries| entries.iter().position(|entry| entry.name == left))
+            .unwrap_or(0);
+    }
+
+    /// Marks the selected file or folder to restore it, or unmarks it, and moves to the next one.
+    pub fn browser_toggle_mark(&mut self) {
+        let Some(name) = self.browser_selection().map(|entry| entry.name.clone()) else {
+            return;
+        };
+        if let Some(browser) = &mut self.browser {
+            let path = browser.dir.join(name);
+            if !browser.marked.remove(&path) {
+                browser.marked.insert(path);
+            }
+        }
+        self.select_next();
+    }
+
+    /// The marked files, or the selected one when nothing is marked.
+    fn files_to_restore(&self) -> Vec<PathBuf> {
+        let Some(browser) = &self.browser else {
+            return Vec::new();
+        };
+        if !browser.marked.is_empty() {
+            return browser.marked.iter().cloned().collect();
+        }
+        self.browser_selection()
+            .map(|entry| vec![browser.dir.join(&entry.name)])
+            .unwrap_or_default()
+    }
+
+    /// Opens the restore popups, if there is something to restore.
+    pub fn ask_file_restore(&mut self) {
+        if self.files_to_restore().is_empty() {
+            return;
+        }
+        if let Some(browser) = &mut self.browser {
+            browser.destination_index = 0;
+            browser.restore_plan = None;
+            self.current_action = CurrentAction::FileRestoreDestination;
+        }
+    }
+
+    /// Looks at what the files would replace, for the confirmation.
+    pub fn plan_file_restore(&mut self, destination: FileDestination) {
+        let paths = self.files_to_restore();
+        let Some(browser) = &mut self.browser else {
+            return;
+        };
+        let backup_name = Local::now().format(SNAPSHOT_NAME_FORMAT).to_string();
+        match FileRestorePlan::new(&browser.tree, &paths, &destination, &backup_name) {
+            Ok(plan) => {
+                browser.restore_plan = Some(plan);
+                self.current_action = CurrentAction::FileRestoreConfirmation;
+            }
+            Err(error) => self.show_failure("Error restoring files", None, error),
+        }
+    }
+
+    pub fn start_file_restore(&mut self) {
+        let Some(plan) = self
+            .browser
+            .as_ref()
+            .and_then(|browser| browser.restore_plan.clone())
+        else {
+            return;
+        };
+        self.start_operation(
+            CurrentAction::FileRestorePending,
+            move |backend, context| backend.restore_files(&plan, context),
+        );
+    }
+
+    /// Opens the diff popup on the selected file : it can be compared with the running system
+    /// when it is a file of it, and with the other snapshots of the device.
+    pub fn ask_diff(&mut self) {
+        let Some(entry) = self.browser_selection().filter(|entry| !entry.is_dir()) else {
+            return;
+        };
+        let name = entry.name.clone();
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        let mut targets = Vec::new();
+        if original_path(&browser.dir.join(name)).is_some() {
+            targets.push(CompareTarget::Live);
+        }
+        targets.extend(
+            self.timeshift_instance
+                .devices_map_by_name
+                .get(&self.current_device_name)
+                .into_iter()
+                .flatten()
+                .filter(|snapshot| snapshot.name != browser.snapshot_name)
+                .map(|snapshot| CompareTarget::Snapshot(snapshot.name.clone())),
+        );
+        if let Some(browser) = &mut self.browser
+            && !targets.is_empty()
+        {
+            browser.diff_targets = targets;
+            browser.diff_index = 0;
+            self.current_action = CurrentAction::DiffMenu;
+        }
+    }
+
+    /// Compares the selected file with the target chosen in the diff popup. The oldest version
+    /// is always on the left.
+    pub fn open_diff(&mut self) {
+        let Some(name) = self.browser_selection().map(|entry| entry.name.clone()) else {
+            return;
+        };
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        let Some(target) = browser.diff_targets.get(browser.diff_index).cloned() else {
+            return;
+        };
+        let relative = browser.dir.join(name);
+        let snapshot_name = browser.snapshot_name.clone();
+        let snapshot_side = (
+            format!("{snapshot_name}:/{}", relative.display()),
+            browser.tree.path(&relative),
+        );
+        match target {
+            CompareTarget::Live => {
+                let Some(live) = original_path(&relative) else {
+                    return;
+                };
+                self.show_diff(snapshot_side, (live.display().to_string(), live), None);
+            }
+            CompareTarget::Snapshot(other) => {
+                let tree = match self
+                    .timeshift_instance
+                    .backend
+                    .open_snapshot(&other, &self.current_device_name)
+                {
+                    Ok(tree) => tree,
+                    Err(e) => {
+                        self.show_failure("Error comparing files", None, format!("{e:#}"));
+                        return;
+                    }
+                };
+                let other_side = (
+                    format!("{other}:/{}", relative.display()),
+                    tree.path(&relative),
+                );
+                // The names are dates, the smallest one is the oldest
+                if other < snapshot_name {
+                    self.show_diff(other_side, snapshot_side, Some(tree));
+                } else {
+                    self.show_diff(snapshot_side, other_side, Some(tree));
+                }
             }
         }
     }
+
+    /// Diffs two versions of a file, `(label, path)` each, in its own thread. A side that doesn't
+    /// exist is an added or removed file. `tree` is the snapshot we mounted for it, kept until
+    /// the thread is done reading.
+    pub fn show_diff(
+        &mut self,
+        old: (String, PathBuf),
+        new: (String, PathBuf),
+        tree: Option<SnapshotTree>,
+    ) {
+        let label = |(label, path): &(String, PathBuf)| {
+            if path.symlink_metadata().is_ok() {
+                label.clone()
+            } else {
+                format!("{label} (doesn't exist)")
+            }
+        };
+        let (sender, job) = mpsc::channel();
+        let (old_path, new_path) = (old.1.clone(), new.1.clone());
+        thread::spawn(move || {
+            let result = diff::diff_files(&old_path, &new_path).map_err(|e| format!("{e:#}"));
+            drop(tree);
+            // The diff may have been closed already, nothing to do about it
+            let _ = sender.send(result);
+        });
+        self.diff = Some(DiffView {
+            old_label: label(&old),
+            new_label: label(&new),
+            diff: None,
+            job: Some(job),
+            scroll: 0,
+        });
+        self.current_action = CurrentAction::Diff;
+    }
+
+    fn read_diff(&mut self) {
+        let Some(view) = &mut self.diff else {
+            return;
+        };
+        let Some(job) = &view.job else {
+            return;
+        };
+        let result = match job.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                Err("The comparison stopped unexpectedly".to_string())
+            }
+        };
+        view.job = None;
+        match result {
+            Ok(file_diff) => view.diff = Some(file_diff),
+            Err(error) => {
+                self.close_diff();
+                self.show_failure("Error comparing files", None, error);
+            }
+        }
+    }
+
+    /// Opens the compare popup on the selected snapshot.
+    pub fn ask_compare(&mut self) {
+        let Some(snapshot) = self.current_snapshot() else {
+            return;
+        };
+        let mut targets = vec![CompareTarget::Live];
+        targets.extend(
+            self.timeshift_instance
+                .devices_map_by_name
+                .get(&self.current_device_name)
+                .into_iter()
+                .flatten()
+                .filter(|other| other.name != snapshot.name)
+                .map(|other| CompareTarget::Snapshot(other.name.clone())),
+        );
+        self.compare_targets = targets;
+        self.compare_index = 0;
+        self.current_action = CurrentAction::CompareMenu;
+    }
+
+    /// Mounts what has to be, and compares the two sides in a thread. The report screen shows
+    /// the progress until it is done.
+    pub fn start_report(&mut self) {
+        let Some(snapshot_name) = self.current_snapshot().map(|s| s.name.clone()) else {
+            return;
+        };
+        let Some(target) = self.compare_targets.get(self.compare_index).cloned() else {
+            return;
+        };
+        let backend = &self.timeshift_instance.backend;
+        let mut trees = Vec::new();
+        let mut open = |name: &str| {
+            let tree = backend
+                .open_snapshot(name, &self.current_device_name)
+                .map_err(|e| format!("{e:#}"))?;
+            let side = ComparedSystem::snapshot(name.to_string(), &tree);
+            trees.push(tree);
+            Ok::<_, String>(side)
+        };
+        let sides = match target {
+            CompareTarget::Live => open(&snapshot_name).map(|old| {
+                let live = ComparedSystem {
+                    snapshot: None,
+                    root: PathBuf::from("/"),
+                };
+                (old, live)
+            }),
+            // The names are dates, the smallest one is the oldest
+            CompareTarget::Snapshot(other) => open(&snapshot_name).and_then(|this| {
+                let other = open(&other)?;
+                Ok(if other.label() < this.label() {
+                    (other, this)
+                } else {
+                    (this, other)
+                })
+            }),
+        };
+        let (old, new) = match sides {
+            Ok(sides) => sides,
+            Err(error) => {
+                self.show_failure("Error comparing snapshots", None, error);
+                return;
+            }
+        };
+        // Without the settings, only the folders timeshift always leaves out are skipped
+        let filters = backend
+            .read_config()
+            .map(|config| config.filters)
+            .unwrap_or_default();
+        let (sender, events) = mpsc::channel();
+        let cancel = CancelHandle::default();
+        let thread_cancel = cancel.clone();
+        let (old_root, new_root) = (old.root.clone(), new.root.clone());
+        thread::spawn(move || {
+            let progress = sender.clone();
+            let result = compare::compare_trees(
+                &old_root,
+                &new_root,
+                &filters,
+                &thread_cancel,
+                &mut |scanned, path| {
+                    // The app may not want it anymore, nothing to do about it
+                    let _ = progress.send(ReportEvent::Progress(scanned, path.to_path_buf()));
+                },
+            );
+            // When the report was closed, the trees are dropped here, after the last read
+            let _ = sender.send(ReportEvent::Done(
+                result.map_err(|e| format!("{e:#}")),
+                trees,
+            ));
+        });
+        self.report = Some(ChangeReportView {
+            old,
+            new,
+            progress: (0, PathBuf::new()),
+            result: None,
+            job: Some(ReportJob { events, cancel }),
+            trees: Vec::new(),
+            focus_files: false,
+            file_index: 0,
+            snapshot_index: self.current_index,
+        });
+        self.current_action = CurrentAction::Idle;
+        self.current_display_screen = Screen::ReportScreen;
+        self.current_index = 0;
+    }
+
+    fn read_report_events(&mut self) {
+        let Some(report) = &mut self.report else {
+            return;
+        };
+        let Some(job) = &report.job else {
+            return;
+        };
+        loop {
+            match job.events.try_recv() {
+                Ok(ReportEvent::Progress(scanned, path)) => report.progress = (scanned, path),
+                Ok(ReportEvent::Done(result, trees)) => {
+                    report.result = Some(result);
+                    report.trees = trees;
+                    report.job = None;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    report.result = Some(Err("The comparison stopped unexpectedly".to_string()));
+                    report.job = None;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Stops the comparison if it is still running, unmounts and goes back to the snapshot list.
+    pub fn close_report(&mut self) {
+        if let Some(report) = self.report.take() {
+            if let Some(job) = &report.job {
+                job.cancel.cancel();
+            }
+            self.current_index = report.snapshot_index;
+        }
+        self.current_display_screen = Screen::SnapshotScreen;
+    }
+
+    /// Diffs the file selected in the change report.
+    pub fn report_diff(&mut self) {
+        let Some(report) = &self.report else {
+            return;
+        };
+        let Some(change) = report
+            .files(self.current_index)
+            .get(report.file_index)
+            .copied()
+        else {
+            return;
+        };
+        let old = (
+            report.old.file_label(&change.path),
+            report.old.root.join(&change.path),
+        );
+        let new = (
+            report.new.file_label(&change.path),
+            report.new.root.join(&change.path),
+        );
+        self.show_diff(old, new, None);
+    }
+
+    pub fn close_diff(&mut self) {
+        self.diff = None;
+        self.current_action = CurrentAction::Idle;
+    }
+
+    /// Reads the selected file for the preview pane, if it wasn't already.
+    fn load_preview(&mut self) {
+        let Some(entry) = self.browser_selection() else {
+            return;
+        };
+        if entry.kind != FileKind::File {
+            return;
+        }
+        let name = entry.name.clone();
+        let Some(browser) = &mut self.browser else {
+            return;
+        };
+        let path = browser.dir.join(name);
+        if browser.preview.as_ref().is_some_and(|(p, _)| *p == path) {
+            return;
+        }
+        let preview = browse::preview(&browser.tree.path(&path)).map_err(|e| format!("{e:#}"));
+        browser.preview = Some((path, preview));
+    }
+
+    /// Writes every device and snapshot in the current folder, with the sizes measured so far.
+    pub fn export_inventory(&mut self, format: ExportFormat) {
+        let sizes = &self.snapshot_sizes;
+        let inventory = Inventory::new(&self.timeshift_instance.devices_map, |device, snapshot| {
+            let key = (device.device_name.clone(), snapshot.name.clone());
+            sizes.get(&key).and_then(|size| size.as_ref().ok()).copied()
+        });
+        let file_name = format!(
+            "timeshift-inventory-{}.{}",
+            Local::now().format(SNAPSHOT_NAME_FORMAT),
+            format.extension()
+        );
+        let path = env::current_dir()
+            .map(|dir| dir.join(file_name))
+            .and_then(|path| fs::write(&path, inventory.render(format)).map(|()| path));
+        match path {
+            Ok(path) => {
+                self.exported_path = Some(path);
+                self.current_action = CurrentAction::Exported;
+            }
+            Err(e) => self.show_failure("Error exporting the inventory", None, e.to_string()),
+        }
+    }
+
+    fn show_failure(&mut self, title: &str, code: Option<i32>, details: String) {
+        self.operation_failure = Some(OperationFailure {
+            title: title.to_string(),
+            code,
+            details,
+            cancelled: false,
+        });
+        self.current_action = CurrentAction::OperationFailed;
+    }
+
+    pub fn dismiss_failure(&mut self) {
+        self.operation_failure = None;
+        self.current_action = CurrentAction::Idle;
+    }
 }
 
-impl Widget for &App {
+impl<B: Backend> Widget for &App<B> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let title = Line::from(" Timeshift TUI ".bold());
         let block = Block::bordered()
@@ -182,26 +1971,281 @@ impl Widget for &App {
             .border_set(border::THICK);
         block.render(area, buf);
         match self.current_display_screen {
-            Screen::DeviceScreen => self.render_devices(area, buf),
+            Screen::DeviceScreen => {
+                self.render_devices(area, buf);
+                match self.current_action {
+                    CurrentAction::ExportMenu => self.render_export_menu(area, buf),
+                    CurrentAction::Exported => self.render_exported(area, buf),
+                    CurrentAction::OperationFailed => self.render_operation_failure(area, buf),
+                    _ => (),
+                }
+            }
+            Screen::SettingsScreen => self.render_settings(area, buf),
+            Screen::ScheduleScreen => self.render_schedule(area, buf),
+            Screen::ReportScreen => {
+                self.render_report(area, buf);
+                match self.current_action {
+                    CurrentAction::Diff => self.render_diff(area, buf),
+                    CurrentAction::OperationFailed => self.render_operation_failure(area, buf),
+                    _ => (),
+                }
+            }
+            Screen::BrowserScreen => {
+                self.render_browser(area, buf);
+                match self.current_action {
+                    CurrentAction::FileRestoreDestination => {
+                        self.render_file_restore_destination(area, buf);
+                    }
+                    CurrentAction::FileRestoreConfirmation => {
+                        self.render_file_restore_confirmation(area, buf);
+                    }
+                    CurrentAction::FileRestorePending => {
+                        self.render_file_restore_progress(area, buf)
+                    }
+                    CurrentAction::FileRestored => self.render_files_restored(area, buf),
+                    CurrentAction::DiffMenu => self.render_diff_menu(area, buf),
+                    CurrentAction::Diff => self.render_diff(area, buf),
+                    CurrentAction::OperationFailed => self.render_operation_failure(area, buf),
+                    _ => (),
+                }
+            }
             Screen::SnapshotScreen => {
-                self.render_snapshots(area, buf, self.current_device_name.clone());
+                self.render_snapshots(area, buf);
                 match self.current_action {
                     CurrentAction::SnapshotDeletionConfirmation => {
-                        self.render_delete_confirmation(
-                            area,
-                            buf,
-                            self.current_device_name.clone(),
-                        );
+                        self.render_delete_confirmation(area, buf);
                     }
                     CurrentAction::SnapshotDeletion => {
                         self.render_deletion_progress(area, buf);
                     }
+                    CurrentAction::MarkMenu => self.render_mark_menu(area, buf),
+                    CurrentAction::RetentionPolicy => self.render_retention_policy(area, buf),
+                    CurrentAction::BulkDeletionConfirmation => {
+                        self.render_bulk_delete_confirmation(area, buf);
+                    }
+                    CurrentAction::BulkDeletion => self.render_bulk_deletion_progress(area, buf),
+                    CurrentAction::BulkDeletionSummary => {
+                        self.render_bulk_deletion_summary(area, buf);
+                    }
                     CurrentAction::SnapshotCreationPending => {
                         self.render_creation_progress(area, buf);
                     }
+                    CurrentAction::SnapshotCreationCancelConfirmation => {
+                        self.render_creation_progress(area, buf);
+                        self.render_cancel_confirmation(area, buf);
+                    }
+                    CurrentAction::RestoreTarget => self.render_restore_target(area, buf),
+                    CurrentAction::RestoreBootloader => self.render_restore_bootloader(area, buf),
+                    CurrentAction::RestoreSummary => self.render_restore_summary(area, buf),
+                    CurrentAction::RestorePending => self.render_restore_progress(area, buf),
+                    CurrentAction::CompareMenu => self.render_compare_menu(area, buf),
+                    CurrentAction::OperationFailed => self.render_operation_failure(area, buf),
                     _ => (),
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeshift_lib::MockBackend;
+    use crate::timeshift_lib::mock::MockOperation;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+    /// The demo data, with operations that take `delay`.
+    fn app(mock: MockBackend, delay: Duration) -> App<MockBackend> {
+        App::new(Timeshift::new(mock.with_delay(delay)))
+    }
+
+    fn press(app: &mut App<MockBackend>, keys: &str) {
+        for key in keys.chars() {
+            app.handle_key_event(KeyEvent::from(KeyCode::Char(key)));
+        }
+    }
+
+    fn press_code(app: &mut App<MockBackend>, code: KeyCode) {
+        app.handle_key_event(KeyEvent::from(code));
+    }
+
+    /// Runs the main loop without drawing until the operation is over.
+    fn wait_for_operation(app: &mut App<MockBackend>) {
+        for _ in 0..500 {
+            app.update();
+            if app.operation.is_none() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the operation never finished");
+    }
+
+    fn snapshot_names(app: &App<MockBackend>) -> Vec<String> {
+        app.timeshift_instance.devices_map_by_name["/dev/sda2"]
+            .iter()
+            .map(|snapshot| snapshot.name.clone())
+            .collect()
+    }
+
+    /// Opens the snapshot list of the first device.
+    fn open_first_device(app: &mut App<MockBackend>) {
+        press_code(app, KeyCode::Enter);
+        assert_eq!(app.current_device_name, "/dev/sda2");
+        assert!(matches!(app.current_display_screen, Screen::SnapshotScreen));
+    }
+
+    #[test]
+    fn create_adds_the_snapshot() {
+        let mut app = app(MockBackend::demo(), Duration::ZERO);
+        open_first_device(&mut app);
+        press(&mut app, "c");
+        assert!(matches!(
+            app.current_action,
+            CurrentAction::SnapshotCreation
+        ));
+        press(&mut app, "test");
+        press_code(&mut app, KeyCode::Enter);
+        assert!(matches!(
+            app.current_action,
+            CurrentAction::SnapshotCreationPending
+        ));
+        wait_for_operation(&mut app);
+        assert!(matches!(app.current_action, CurrentAction::Idle));
+        let snapshots = &app.timeshift_instance.devices_map_by_name["/dev/sda2"];
+        assert_eq!(snapshots.len(), 4);
+        assert!(snapshots.iter().any(|s| s.description == "test"));
+    }
+
+    #[test]
+    fn delete_removes_the_selected_snapshot() {
+        let mut app = app(MockBackend::demo(), Duration::ZERO);
+        open_first_device(&mut app);
+        let selected = app.current_snapshot().unwrap().name.clone();
+        press(&mut app, "d");
+        assert!(matches!(
+            app.current_action,
+            CurrentAction::SnapshotDeletionConfirmation
+        ));
+        press(&mut app, "y");
+        wait_for_operation(&mut app);
+        assert!(matches!(app.current_action, CurrentAction::Idle));
+        let names = snapshot_names(&app);
+        assert_eq!(names.len(), 2);
+        assert!(!names.contains(&selected));
+    }
+
+    #[test]
+    fn failed_delete_shows_the_error() {
+        let mock = MockBackend::demo().with_failure(MockOperation::Delete, "device is busy");
+        let mut app = app(mock, Duration::ZERO);
+        open_first_device(&mut app);
+        press(&mut app, "dy");
+        wait_for_operation(&mut app);
+        assert!(matches!(app.current_action, CurrentAction::OperationFailed));
+        let failure = app.operation_failure.as_ref().unwrap();
+        assert_eq!(failure.title, "Error deleting snapshot");
+        assert_eq!(failure.code, Some(1));
+        assert_eq!(failure.details, "device is busy");
+        assert!(!failure.cancelled);
+        press_code(&mut app, KeyCode::Esc);
+        assert!(matches!(app.current_action, CurrentAction::Idle));
+        assert_eq!(snapshot_names(&app).len(), 3);
+    }
+
+    #[test]
+    fn cancelled_create_leaves_nothing_behind() {
+        let mut app = app(MockBackend::demo(), Duration::from_secs(5));
+        open_first_device(&mut app);
+        press(&mut app, "c");
+        press_code(&mut app, KeyCode::Enter);
+        press_code(&mut app, KeyCode::Esc);
+        assert!(matches!(
+            app.current_action,
+            CurrentAction::SnapshotCreationCancelConfirmation
+        ));
+        press(&mut app, "y");
+        assert!(app.is_cancelling());
+        wait_for_operation(&mut app);
+        assert!(matches!(app.current_action, CurrentAction::OperationFailed));
+        assert!(app.operation_failure.as_ref().unwrap().cancelled);
+        assert_eq!(snapshot_names(&app).len(), 3);
+    }
+
+    #[test]
+    fn cancelled_prune_gives_the_marks_back() {
+        let mut app = app(MockBackend::demo(), Duration::ZERO);
+        open_first_device(&mut app);
+        // Only the newest snapshot is kept
+        app.retention = RetentionPolicy {
+            keep_daily: 0,
+            keep_weekly: 0,
+            keep_monthly: 0,
+            keep_within_days: 0,
+            keep_scheduled: false,
+            keep_pinned: false,
+            keep_commented: false,
+        };
+        let selected = app.current_snapshot().unwrap().name.clone();
+        press(&mut app, " p");
+        press_code(&mut app, KeyCode::Enter);
+        press(&mut app, "P");
+        assert!(matches!(
+            app.current_action,
+            CurrentAction::BulkDeletionConfirmation
+        ));
+        assert_eq!(app.bulk.marked.len(), 2);
+        press_code(&mut app, KeyCode::Esc);
+        assert!(matches!(app.current_action, CurrentAction::Idle));
+        assert_eq!(app.bulk.marked, BTreeSet::from([selected]));
+    }
+
+    #[test]
+    fn pinned_snapshot_is_not_pruned() {
+        let mut app = app(MockBackend::demo(), Duration::ZERO);
+        open_first_device(&mut app);
+        app.retention = RetentionPolicy {
+            keep_daily: 0,
+            keep_weekly: 0,
+            keep_monthly: 0,
+            keep_within_days: 0,
+            keep_scheduled: false,
+            keep_pinned: true,
+            keep_commented: false,
+        };
+        press_code(&mut app, KeyCode::Down);
+        let selected = app.current_snapshot().unwrap().name.clone();
+        press(&mut app, "*");
+        assert_eq!(
+            app.timeshift_instance.backend.read_pins().unwrap(),
+            BTreeSet::from([selected.clone()])
+        );
+        assert_eq!(app.prune_verdicts()[&selected], KeepReason::Pinned);
+        assert_eq!(app.snapshots_to_prune().len(), 1);
+        press(&mut app, "*");
+        assert!(app.pinned.is_empty());
+        assert_eq!(app.snapshots_to_prune().len(), 2);
+    }
+
+    #[test]
+    fn sizes_are_measured_once_when_asked() {
+        let mut app = app(MockBackend::demo(), Duration::ZERO);
+        open_first_device(&mut app);
+        app.update();
+        assert!(app.size_job.is_none());
+        assert!(app.snapshot_sizes.is_empty());
+        press(&mut app, "u");
+        assert!(app.size_job.is_some());
+        for _ in 0..500 {
+            app.update();
+            if app.size_job.is_none() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(app.size_job.is_none());
+        assert_eq!(app.snapshot_sizes.len(), 3);
+        app.update();
+        assert!(app.size_job.is_none());
+    }
+}
@@ -0,0 +1,540 @@
+use crate::timeshift_lib::{
+    Backend, CancelHandle, Device, ExportFormat, Inventory, OperationContext, OperationEvent,
+    Snapshot, SnapshotInfo, create_snapshot_or_clean_up,
+};
+use anyhow::Context;
+use chrono::TimeDelta;
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+
+pub const USAGE: &str = "\
+Usage: timeshift-tui-rs [--mock] [--stale-days N]            start the TUI
+       timeshift-tui-rs [--mock] [--json] <command>           run one command and exit
+       timeshift-tui-rs --help                                this help
+
+Commands:
+  list-devices                            the devices timeshift can use
+  list --device DEVICE                    the snapshots of a device
+  create --device DEVICE [--comment TEXT] take a snapshot on-demand
+  delete --device DEVICE NAME             delete a snapshot
+  info [--device DEVICE] NAME             what timeshift knows about a snapshot
+  export --format json|csv|markdown       every device and snapshot with their sizes
+         [--output FILE] [--no-sizes]     (measuring the sizes can take minutes)
+
+Exit codes: 0 success, 1 timeshift failed, 2 bad arguments, 3 device or snapshot not found,
+4 not root";
+
+/// The commands of the CLI, for scripts. Without one the TUI starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    ListDevices,
+    List {
+        device: String,
+    },
+    Create {
+        device: String,
+        comment: String,
+    },
+    Delete {
+        device: String,
+        name: String,
+    },
+    Info {
+        device: Option<String>,
+        name: String,
+    },
+    Export {
+        format: ExportFormat,
+        /// Printed on stdout without it
+        output: Option<String>,
+        sizes: bool,
+    },
+}
+
+/// Why a command failed, each one with its exit code.
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    NotFound(String),
+    NotRoot,
+    Failed(anyhow::Error),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Failed(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::NotFound(_) => 3,
+            CliError::NotRoot => 4,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{message}\n\n{USAGE}"),
+            CliError::NotFound(message) => write!(f, "{message}"),
+            CliError::NotRoot => write!(f, "You must run this executable with root permissions"),
+            CliError::Failed(error) => write!(f, "{error:#}"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for CliError {
+    fn from(error: anyhow::Error) -> Self {
+        CliError::Failed(error)
+    }
+}
+
+/// The options of the whole program. The global ones come before the command, the ones of
+/// the command after it.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub help: bool,
+    pub mock: bool,
+    pub json: bool,
+    /// Snapshots older than this are highlighted in the TUI
+    pub stale_after: Option<TimeDelta>,
+    pub command: Option<CliCommand>,
+}
+
+impl CliArgs {
+    /// `args` without the name of the binary. An option the command doesn't take, or given
+    /// with the wrong value, is an error.
+    pub fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut cli = CliArgs::default();
+        let mut args = args.iter();
+        let name = loop {
+            let Some(arg) = args.next() else {
+                if cli.json && !cli.help {
+                    return Err(CliError::Usage("--json needs a command".to_string()));
+                }
+                return Ok(cli);
+            };
+            match arg.as_str() {
+                "--help" | "-h" => cli.help = true,
+                "--mock" => cli.mock = true,
+                "--json" => cli.json = true,
+                "--stale-days" => {
+                    let days = value(arg, args.next())?;
+                    let days: u32 = days.parse().map_err(|_| {
+                        CliError::Usage(format!("--stale-days needs a number of days, not {days}"))
+                    })?;
+                    cli.stale_after = Some(TimeDelta::days(days.into()));
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(CliError::Usage(format!("Unknown option {arg}")));
+                }
+                _ => break arg.as_str(),
+            }
+        };
+        // `--help` is only for the TUI, the command is not run
+        if cli.help {
+            return Ok(cli);
+        }
+        if cli.stale_after.is_some() {
+            return Err(CliError::Usage(
+                "--stale-days is for the TUI, not for a command".to_string(),
+            ));
+        }
+
+        let mut options = Vec::new();
+        let mut device = None;
+        let mut comment = None;
+        let mut format = None;
+        let mut output = None;
+        let mut sizes = true;
+        let mut positionals = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-sizes" => sizes = false,
+                "--device" => device = Some(value(arg, args.next())?.clone()),
+                "--comment" => comment = Some(value(arg, args.next())?.clone()),
+                "--output" => output = Some(value(arg, args.next())?.clone()),
+                "--format" => {
+                    let value = value(arg, args.next())?;
+                    format = Some(
+                        ExportFormat::from_name(value)
+                            .ok_or_else(|| CliError::Usage(format!("Unknown format {value}")))?,
+                    );
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(CliError::Usage(format!("Unknown option {arg} for {name}")));
+                }
+                _ => {
+                    positionals.push(arg.clone());
+                    continue;
+                }
+            }
+            options.push(arg.as_str());
+        }
+
+        let need_device = || {
+            device
+                .clone()
+                .ok_or_else(|| CliError::Usage(format!("{name} needs --device")))
+        };
+        let snapshot_name = || {
+            positionals
+                .first()
+                .cloned()
+                .ok_or_else(|| CliError::Usage(format!("{name} needs a snapshot name")))
+        };
+        let (command, allowed): (CliCommand, &[&str]) = match name {
+            "list-devices" => (CliCommand::ListDevices, &[]),
+            "list" => (
+                CliCommand::List {
+                    device: need_device()?,
+                },
+                &["--device"],
+            ),
+            "create" => (
+                CliCommand::Create {
+                    device: need_device()?,
+                    comment: comment.clone().unwrap_or_default(),
+                },
+                &["--device", "--comment"],
+            ),
+            "delete" => (
+                CliCommand::Delete {
+                    device: need_device()?,
+                    name: snapshot_name()?,
+                },
+                &["--device"],
+            ),
+            "info" => (
+                CliCommand::Info {
+                    device: device.clone(),
+                    name: snapshot_name()?,
+                },
+                &["--device"],
+            ),
+            "export" => (
+                CliCommand::Export {
+                    format: format
+                        .ok_or_else(|| CliError::Usage("export needs --format".to_string()))?,
+                    output: output.clone(),
+                    sizes,
+                },
+                &["--format", "--output", "--no-sizes"],
+            ),
+            other => return Err(CliError::Usage(format!("Unknown command {other}"))),
+        };
+        if let Some(option) = options.iter().find(|option| !allowed.contains(option)) {
+            return Err(CliError::Usage(format!("{name} doesn't take {option}")));
+        }
+        let taken = match command {
+            CliCommand::Delete { .. } | CliCommand::Info { .. } => 1,
+            _ => 0,
+        };
+        if let Some(surplus) = positionals.get(taken) {
+            return Err(CliError::Usage(format!(
+                "Unexpected argument {surplus} for {name}"
+            )));
+        }
+        cli.command = Some(command);
+        Ok(cli)
+    }
+}
+
+/// The value following an option, whatever it looks like.
+fn value<'a>(option: &str, value: Option<&'a String>) -> Result<&'a String, CliError> {
+    value.ok_or_else(|| CliError::Usage(format!("{option} needs a value")))
+}
+
+/// Runs the command and prints the result, as JSON on stdout with `--json`. Gives back the
+/// exit code.
+pub fn run<B: Backend>(backend: &B, command: &CliCommand, json: bool) -> i32 {
+    match run_command(backend, command, json) {
+        Ok(()) => 0,
+        Err(error) => report(&error, json),
+    }
+}
+
+/// Prints the error, as JSON on stdout with `--json` so scripts always get something to parse,
+/// and gives back the exit code.
+pub fn report(error: &CliError, json: bool) -> i32 {
+    if json {
+        println!("{}", json!({ "error": error.to_string() }));
+    } else {
+        eprintln!("{error}");
+    }
+    error.exit_code()
+}
+
+fn run_command<B: Backend>(backend: &B, command: &CliCommand, json: bool) -> Result<(), CliError> {
+    match command {
+        CliCommand::ListDevices => {
+            let devices = backend.get_devices()?.items;
+            print_list(&devices, json);
+        }
+        CliCommand::List { device } => {
+            let device = find_device(backend, device)?;
+            let snapshots = backend.get_snapshots(&device)?.items;
+            print_list(&snapshots, json);
+        }
+        CliCommand::Create { device, comment } => {
+            let device = find_device(backend, device)?;
+            let before = backend.get_snapshots(&device)?.items;
+            run_operation(json, |context| {
+                create_snapshot_or_clean_up(backend, comment, &device, None, context)
+            })?;
+            // Timeshift doesn't say the name of the snapshot, it is the new one in the list
+            let created = backend
+                .get_snapshots(&device)?
+                .items
+                .into_iter()
+                .find(|snapshot| !before.iter().any(|s| s.name == snapshot.name));
+            match (json, created) {
+                (true, created) => println!("{}", json!({ "created": created })),
+                (false, Some(snapshot)) => println!("Created {}", snapshot.name),
+                (false, None) => println!("Created"),
+            }
+        }
+        CliCommand::Delete { device, name } => {
+            let device = find_device(backend, device)?;
+            find_snapshot(backend, &device, name)?;
+            run_operation(json, |context| {
+                backend.delete_snapshot(name, &device.device_name, context)
+            })?;
+            if json {
+                println!("{}", json!({ "deleted": name }));
+            } else {
+                println!("Deleted {name}");
+            }
+        }
+        CliCommand::Info { device, name } => {
+            let device = match device {
+                Some(device) => find_device(backend, device)?,
+                None => device_of_snapshot(backend, name)?,
+            };
+            let snapshot = find_snapshot(backend, &device, name)?;
+            let info = backend.snapshot_info(name, &device.device_name)?;
+            if json {
+                println!(
+                    "{}",
+                    json!({ "device": device.device_name, "snapshot": snapshot, "info": info })
+                );
+            } else {
+                print_info(&device, &snapshot, &info);
+            }
+        }
+        CliCommand::Export {
+            format,
+            output,
+            sizes,
+        } => {
+            let mut devices_map = IndexMap::new();
+            for device in backend.get_devices()?.items {
+                let snapshots = backend.get_snapshots(&device)?.items;
+                devices_map.insert(device, snapshots);
+            }
+            // Like in the TUI, only the rsync snapshots can be measured
+            let btrfs_mode = backend.read_config().is_ok_and(|config| config.btrfs_mode);
+            let mut measured = HashMap::new();
+            if *sizes && !btrfs_mode {
+                for (device, snapshots) in &devices_map {
+                    let names: Vec<String> = snapshots.iter().map(|s| s.name.clone()).collect();
+                    backend.snapshot_sizes(
+                        &device.device_name,
+                        &names,
+                        &CancelHandle::default(),
+                        &mut |name, size| {
+                            if let Ok(size) = size {
+                                measured
+                                    .insert((device.device_name.clone(), name.to_string()), size);
+                            }
+                        },
+                    );
+                }
+            }
+            let inventory = Inventory::new(&devices_map, |device, snapshot| {
+                let key = (device.device_name.clone(), snapshot.name.clone());
+                measured.get(&key).copied()
+            });
+            let content = inventory.render(*format);
+            match output {
+                Some(path) => {
+                    fs::write(path, content).with_context(|| format!("Couldn't write {path}"))?
+                }
+                None => print!("{content}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One line per item like in the TUI, or a JSON array.
+fn print_list<T: Serialize + fmt::Display>(items: &[T], json: bool) {
+    if json {
+        println!("{}", json!(items));
+    } else {
+        for item in items {
+            println!("{item}");
+        }
+    }
+}
+
+fn print_info(device: &Device, snapshot: &Snapshot, info: &SnapshotInfo) {
+    let created = info
+        .created
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let tags: Vec<String> = info.tags.iter().map(|tag| tag.to_string()).collect();
+    println!("Snapshot     : {}", snapshot.name);
+    println!("Device       : {}", device.device_name);
+    println!("Created      : {created}");
+    println!("Distro       : {}", info.sys_distro);
+    println!("System UUID  : {}", info.sys_uuid);
+    println!("Timeshift    : {}", info.app_version);
+    println!("Tags         : {}", tags.join(", "));
+    println!("Comments     : {}", info.comments);
+    for (key, value) in &info.other {
+        println!("{key:<13}: {value}");
+    }
+}
+
+fn find_device<B: Backend>(backend: &B, device_name: &str) -> Result<Device, CliError> {
+    backend
+        .get_devices()?
+        .items
+        .into_iter()
+        .find(|device| device.device_name == device_name)
+        .ok_or_else(|| CliError::NotFound(format!("Device {device_name} not found")))
+}
+
+fn find_snapshot<B: Backend>(
+    backend: &B,
+    device: &Device,
+    name: &str,
+) -> Result<Snapshot, CliError> {
+    backend
+        .get_snapshots(device)?
+        .items
+        .into_iter()
+        .find(|snapshot| snapshot.name == name)
+        .ok_or_else(|| {
+            CliError::NotFound(format!(
+                "Snapshot {name} not found on {}",
+                device.device_name
+            ))
+        })
+}
+
+/// Without `--device`, the first device that has a snapshot with this name.
+fn device_of_snapshot<B: Backend>(backend: &B, name: &str) -> Result<Device, CliError> {
+    for device in backend.get_devices()?.items {
+        if backend
+            .get_snapshots(&device)?
+            .items
+            .iter()
+            .any(|snapshot| snapshot.name == name)
+        {
+            return Ok(device);
+        }
+    }
+    Err(CliError::NotFound(format!("Snapshot {name} not found")))
+}
+
+/// Runs a long operation, with what timeshift prints on stderr so stdout only has the result.
+/// Nothing is printed with `--json`.
+fn run_operation(
+    json: bool,
+    operation: impl FnOnce(&OperationContext) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let (sender, events) = mpsc::channel();
+    let printer = thread::spawn(move || {
+        for event in events {
+            if let OperationEvent::Line(line) = event
+                && !json
+            {
+                eprintln!("{line}");
+            }
+        }
+    });
+    let result = operation(&OperationContext::new(sender));
+    // The context was dropped with the operation, the printer stops once it read everything
+    let _ = printer.join();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        CliArgs::parse(&args)
+    }
+
+    #[test]
+    fn no_command_starts_the_tui() {
+        let args = parse(&["--mock", "--stale-days", "7"]).unwrap();
+        assert_eq!(args.command, None);
+        assert!(args.mock);
+        assert_eq!(args.stale_after, Some(TimeDelta::days(7)));
+        assert!(parse(&[]).unwrap().command.is_none());
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn commands() {
+        let args = parse(&[
+            "--json",
+            "--mock",
+            "delete",
+            "--device",
+            "/dev/sda2",
+            "2025-01-01_10-00-00",
+        ])
+        .unwrap();
+        assert!(args.json);
+        assert!(args.mock);
+        assert_eq!(
+            args.command,
+            Some(CliCommand::Delete {
+                device: "/dev/sda2".to_string(),
+                name: "2025-01-01_10-00-00".to_string(),
+            })
+        );
+        // A value is taken as it is, even when it looks like an option
+        assert_eq!(
+            parse(&["create", "--device", "/dev/sda2", "--comment", "--help"])
+                .unwrap()
+                .command,
+            Some(CliCommand::Create {
+                device: "/dev/sda2".to_string(),
+                comment: "--help".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn bad_arguments_are_usage_errors() {
+        let usage = |args: &[&str]| matches!(parse(args), Err(CliError::Usage(_)));
+        assert!(usage(&["--foo"]));
+        assert!(usage(&["--json"]));
+        assert!(usage(&["--stale-days", "soon"]));
+        assert!(usage(&["--stale-days", "-3"]));
+        assert!(usage(&["--stale-days"]));
+        assert!(usage(&["--stale-days", "7", "list-devices"]));
+        assert!(usage(&["list-devices", "--foo"]));
+        assert!(usage(&["list-devices", "--json"]));
+        assert!(usage(&["list-devices", "--device", "/dev/sda2"]));
+        assert!(usage(&["list-devices", "extra"]));
+        assert!(usage(&["list", "--device", "/dev/sda2", "--no-sizes"]));
+        assert!(usage(&["delete", "--device", "/dev/sda2", "one", "two"]));
+        assert!(usage(&["create", "--comment"]));
+        assert!(usage(&["list"]));
+        assert!(usage(&["export", "--format", "xml"]));
+        assert!(usage(&["frobnicate"]));
+    }
+}
@@ -1,14 +1,28 @@
 use crate::app::App;
 use crate::app::CurrentAction;
+use crate::app::DeviceColumn;
+use crate::app::FILE_RESTORE_CHOICES;
 use crate::app::InputMode;
+use crate::app::MarkChoice;
+use crate::app::RestoreWizard;
 use crate::app::Screen;
-use crate::timeshift_lib::Timeshift;
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crate::app::{SettingsRow, SettingsStatus};
+use crate::timeshift_lib::{
+    Backend, Bootloader, ExportFormat, FileDestination, Filter, MAX_COUNT, RetentionField,
+    ScheduleLevel, Snapshot, create_snapshot_or_clean_up,
+};
+use chrono::TimeDelta;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::BTreeSet;
 use std::io;
-use std::thread;
+use std::path::PathBuf;
+use tui_input::Input;
 use tui_input::backend::crossterm::EventHandler;
 
-impl App {
+/// Lines scrolled at once with PageUp/PageDown in the diff.
+const DIFF_PAGE: usize = 20;
+
+impl<B: Backend> App<B> {
     pub fn handle_events(&mut self) -> io::Result<()> {
         match event::read()? {
             // it's important to check that the event is a key press event as
@@ -21,12 +35,115 @@ impl App {
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key_event: KeyEvent) {
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) {
         // Si on est en mode création, on gère l'input différemment (c'est moche)
         match self.current_action {
             CurrentAction::SnapshotCreation => {
                 self.handle_creation_key_event(key_event);
             }
+            CurrentAction::SnapshotCreationPending => {
+                let ctrl_c = key_event.code == KeyCode::Char('c')
+                    && key_event.modifiers.contains(KeyModifiers::CONTROL);
+                if (key_event.code == KeyCode::Esc || ctrl_c) && !self.is_cancelling() {
+                    self.current_action = CurrentAction::SnapshotCreationCancelConfirmation;
+                }
+            }
+            CurrentAction::SnapshotCreationCancelConfirmation => match key_event.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    self.cancel_operation();
+                    self.current_action = CurrentAction::SnapshotCreationPending;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.current_action = CurrentAction::SnapshotCreationPending;
+                }
+                _ => {}
+            },
+            CurrentAction::OperationFailed => match key_event.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => self.dismiss_failure(),
+                _ => {}
+            },
+            CurrentAction::RestoreTarget
+            | CurrentAction::RestoreBootloader
+            | CurrentAction::RestoreSummary
+            | CurrentAction::RestoreConfirmation => {
+                self.handle_restore_key_event(key_event);
+            }
+            CurrentAction::SettingsEditing => self.handle_settings_input(key_event),
+            CurrentAction::RetentionPolicy => self.handle_retention_key_event(key_event),
+            CurrentAction::Search => self.handle_search_key_event(key_event),
+            CurrentAction::ExportMenu => match key_event.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    self.export_index = (self.export_index + 1).min(ExportFormat::ALL.len() - 1);
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.export_index = self.export_index.saturating_sub(1);
+                }
+                KeyCode::Enter => self.export_inventory(ExportFormat::ALL[self.export_index]),
+                KeyCode::Esc | KeyCode::Char('q') => self.current_action = CurrentAction::Idle,
+                _ => {}
+            },
+            CurrentAction::Exported => match key_event.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    self.current_action = CurrentAction::Idle;
+                }
+                _ => {}
+            },
+            CurrentAction::FileRestoreDestination
+            | CurrentAction::FileRestoreFolder
+            | CurrentAction::FileRestoreConfirmation
+            | CurrentAction::FileRestored => self.handle_file_restore_key_event(key_event),
+            CurrentAction::DiffMenu | CurrentAction::Diff => self.handle_diff_key_event(key_event),
+            CurrentAction::CompareMenu => match key_event.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    self.compare_index =
+                        (self.compare_index + 1).min(self.compare_targets.len().saturating_sub(1));
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.compare_index = self.compare_index.saturating_sub(1);
+                }
+                KeyCode::Enter => self.start_report(),
+                KeyCode::Esc | KeyCode::Char('q') => self.current_action = CurrentAction::Idle,
+                _ => {}
+            },
+            CurrentAction::FileRestorePending => {
+                let ctrl_c = key_event.code == KeyCode::Char('c')
+                    && key_event.modifiers.contains(KeyModifiers::CONTROL);
+                if key_event.code == KeyCode::Esc || ctrl_c {
+                    self.cancel_operation();
+                }
+            }
+            CurrentAction::MarkMenu | CurrentAction::MarkOlderThan => {
+                self.handle_mark_key_event(key_event);
+            }
+            CurrentAction::BulkDeletion => {
+                let ctrl_c = key_event.code == KeyCode::Char('c')
+                    && key_event.modifiers.contains(KeyModifiers::CONTROL);
+                if key_event.code == KeyCode::Esc || ctrl_c {
+                    // The snapshot being deleted is left alone, the next ones are skipped
+                    self.bulk.stopping = true;
+                }
+            }
+            CurrentAction::BulkDeletionSummary => match key_event.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    self.bulk.queue.clear();
+                    self.bulk.results.clear();
+                    self.bulk.stopping = false;
+                    self.current_action = CurrentAction::Idle;
+                }
+                _ => {}
+            },
+            _ if matches!(self.current_display_screen, Screen::SettingsScreen) => {
+                self.handle_settings_key_event(key_event);
+            }
+            _ if matches!(self.current_display_screen, Screen::ScheduleScreen) => {
+                self.handle_schedule_key_event(key_event);
+            }
+            _ if matches!(self.current_display_screen, Screen::BrowserScreen) => {
+                self.handle_browser_key_event(key_event);
+            }
+            _ if matches!(self.current_display_screen, Screen::ReportScreen) => {
+                self.handle_report_key_event(key_event);
+            }
             _ => match key_event.code {
                 KeyCode::Char('q') => self.back_or_exit(),
                 KeyCode::Char('j') | KeyCode::Down => self.select_next(),
@@ -35,25 +152,188 @@ impl App {
                 KeyCode::Char('G') | KeyCode::End => self.select_last(),
                 KeyCode::Char('c') => {
                     if let Screen::SnapshotScreen = self.current_display_screen {
+                        // Starts from what timeshift would do by itself
+                        self.create_include_home = self.timeshift_instance.btrfs_mode
+                            && self
+                                .timeshift_instance
+                                .backend
+                                .read_config()
+                                .is_ok_and(|config| config.include_btrfs_home_for_backup);
                         self.current_action = CurrentAction::SnapshotCreation;
                         self.input_mode = crate::app::InputMode::Editing;
                     }
                 }
                 KeyCode::Char('d') | KeyCode::Delete => {
                     if let Screen::SnapshotScreen = self.current_display_screen {
-                        self.current_action = CurrentAction::SnapshotDeletionConfirmation
+                        if !self.marked_snapshots().is_empty() {
+                            self.current_action = CurrentAction::BulkDeletionConfirmation
+                        } else if self.current_snapshot().is_some() {
+                            self.current_action = CurrentAction::SnapshotDeletionConfirmation
+                        }
                     }
                 }
-                KeyCode::Char('y') | KeyCode::Char('Y') => {
-                    if let CurrentAction::SnapshotDeletionConfirmation = self.current_action {
-                        self.delete_current_snapshot();
+                KeyCode::Char(' ') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                        && let Some(snapshot) = self.current_snapshot()
+                    {
+                        let name = snapshot.name.clone();
+                        if !self.bulk.marked.remove(&name) {
+                            self.bulk.marked.insert(name);
+                        }
+                        self.select_next();
                     }
                 }
-                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                    if let CurrentAction::SnapshotDeletionConfirmation = self.current_action {
-                        self.current_action = CurrentAction::Idle;
+                KeyCode::Char('v') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                    {
+                        self.mark_range();
+                    }
+                }
+                KeyCode::Char('p') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                    {
+                        self.prune_preview = true;
+                        self.current_action = CurrentAction::RetentionPolicy;
+                    }
+                }
+                KeyCode::Char('P') => {
+                    // The prune goes through the same confirmation and queue as the marks
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                        && self.prune_preview
+                    {
+                        let names: BTreeSet<String> = self
+                            .snapshots_to_prune()
+                            .into_iter()
+                            .map(|snapshot| snapshot.name.clone())
+                            .collect();
+                        if !names.is_empty() {
+                            let marks = std::mem::replace(&mut self.bulk.marked, names);
+                            self.bulk.saved_marks = Some(marks);
+                            self.bulk.range_start = None;
+                            self.prune_preview = false;
+                            self.current_action = CurrentAction::BulkDeletionConfirmation;
+                        }
+                    }
+                }
+                KeyCode::Char('*') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                    {
+                        self.toggle_pin();
                     }
                 }
+                KeyCode::Char('m') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                    {
+                        self.bulk.menu_index = 0;
+                        self.current_action = CurrentAction::MarkMenu;
+                    }
+                }
+                KeyCode::Char('r') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                        && let Some(snapshot) = self.current_snapshot()
+                    {
+                        self.restore_wizard = RestoreWizard {
+                            snapshot_name: snapshot.name.clone(),
+                            ..Default::default()
+                        };
+                        self.current_action = CurrentAction::RestoreTarget;
+                    }
+                }
+                KeyCode::Char('e') => {
+                    if let Screen::DeviceScreen = self.current_display_screen {
+                        self.current_action = CurrentAction::ExportMenu;
+                    }
+                }
+                KeyCode::Char('o') => {
+                    if let Screen::DeviceScreen = self.current_display_screen {
+                        self.open_settings(Screen::SettingsScreen);
+                    }
+                }
+                KeyCode::Char(digit @ '1'..='9') => {
+                    if let CurrentAction::Idle = self.current_action {
+                        self.sort_by_column(digit as usize - '1' as usize);
+                    }
+                }
+                KeyCode::Char('/') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                    {
+                        self.input_mode = InputMode::Editing;
+                        self.current_action = CurrentAction::Search;
+                    }
+                }
+                KeyCode::Char('t') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen {
+                        self.cycle_tag_filter();
+                        self.bulk.range_start = None;
+                    }
+                }
+                KeyCode::Char('i') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen {
+                        self.show_details = !self.show_details;
+                    }
+                }
+                KeyCode::Char('x') => {
+                    if let Screen::SnapshotScreen = self.current_display_screen {
+                        self.ask_compare();
+                    }
+                }
+                KeyCode::Char('u') => {
+                    if let Screen::SnapshotScreen | Screen::DeviceScreen =
+                        self.current_display_screen
+                        && let CurrentAction::Idle = self.current_action
+                    {
+                        self.start_size_job();
+                    }
+                }
+                KeyCode::Char('s') => match self.current_display_screen {
+                    Screen::SnapshotScreen => {
+                        self.snapshot_sort.order = self.snapshot_sort.order.toggle();
+                        self.current_index = 0;
+                        self.bulk.range_start = None;
+                    }
+                    Screen::DeviceScreen => self.open_settings(Screen::ScheduleScreen),
+                    _ => {}
+                },
+                KeyCode::Char('y') | KeyCode::Char('Y') => match self.current_action {
+                    CurrentAction::SnapshotDeletionConfirmation => self.delete_current_snapshot(),
+                    CurrentAction::BulkDeletionConfirmation => self.start_bulk_deletion(),
+                    _ => {}
+                },
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => match self.current_action
+                {
+                    CurrentAction::SnapshotDeletionConfirmation
+                    | CurrentAction::BulkDeletionConfirmation => {
+                        if let Some(saved) = self.bulk.saved_marks.take() {
+                            self.bulk.marked = saved;
+                        }
+                        self.current_action = CurrentAction::Idle;
+                    }
+                    // Esc drops the range being marked first, then the search, then the prune
+                    // preview, then the marks
+                    CurrentAction::Idle
+                        if key_event.code == KeyCode::Esc
+                            && self.bulk.range_start.take().is_none() =>
+                    {
+                        if !self.search.value().is_empty() {
+                            let previous = self.current_snapshot().map(|s| s.name.clone());
+                            self.search.reset();
+                            self.update_search(previous);
+                        } else if self.prune_preview {
+                            self.prune_preview = false;
+                        } else {
+                            self.bulk.marked.clear();
+                        }
+                    }
+                    _ => {}
+                },
                 KeyCode::Enter => self.choose(),
                 _ => {}
             },
@@ -67,14 +347,11 @@ impl App {
                     self.input_mode = InputMode::Normal;
                     self.current_action = CurrentAction::Idle;
                 }
+                KeyCode::Tab if self.timeshift_instance.btrfs_mode => {
+                    self.create_include_home = !self.create_include_home;
+                }
                 KeyCode::Enter => {
-                    // TODO: Improve lifetime thingy.
-                    self.current_action = CurrentAction::SnapshotCreationPending;
-                    let comment = self.input.value_and_reset();
-                    let device_name = self.current_device_name.clone();
-                    self.operation_thread = Some(thread::spawn(move || {
-                        Timeshift::create_snapshot(comment, &device_name).map_err(|e| e.to_string())
-                    }));
+                    self.start_creation();
                 }
                 _ => {
                     self.input.handle_event(&Event::Key(key_event));
@@ -83,13 +360,7 @@ impl App {
             InputMode::Normal => match key_event.code {
                 // doesnt happend for now
                 KeyCode::Enter => {
-                    // TODO: Improve lifetime thingy.
-                    self.current_action = CurrentAction::SnapshotCreationPending;
-                    let comment = self.input.value_and_reset();
-                    let device_name = self.current_device_name.clone();
-                    self.operation_thread = Some(thread::spawn(move || {
-                        Timeshift::create_snapshot(comment, &device_name).map_err(|e| e.to_string())
-                    }));
+                    self.start_creation();
                 }
                 KeyCode::Esc => {
                     self.current_action = CurrentAction::Idle;
@@ -100,41 +371,716 @@ impl App {
         }
     }
 
+    /// Esc always goes back one step of the wizard, and cancels it from the first one.
+    fn handle_restore_key_event(&mut self, key_event: KeyEvent) {
+        match self.current_action {
+            CurrentAction::RestoreTarget => match key_event.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    let max = self.restore_targets().len().saturating_sub(1);
+                    let index = &mut self.restore_wizard.target_index;
+                    *index = (*index + 1).min(max);
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    let index = &mut self.restore_wizard.target_index;
+                    *index = index.saturating_sub(1);
+                }
+                KeyCode::Enter => {
+                    // By default, GRUB goes on the disk of the device we restore on
+                    let target = self
+                        .timeshift_instance
+                        .devices_map
+                        .keys()
+                        .nth(self.restore_wizard.target_index);
+                    if let Some(target) = target {
+                        let disk = Bootloader::Reinstall(target.disk());
+                        self.restore_wizard.bootloader_index = self
+                            .bootloader_choices()
+                            .iter()
+                            .position(|choice| *choice == disk)
+                            .unwrap_or(0);
+                        self.current_action = CurrentAction::RestoreBootloader;
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.current_action = CurrentAction::Idle,
+                _ => {}
+            },
+            CurrentAction::RestoreBootloader => match key_event.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    let max = self.bootloader_choices().len().saturating_sub(1);
+                    let index = &mut self.restore_wizard.bootloader_index;
+                    *index = (*index + 1).min(max);
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    let index = &mut self.restore_wizard.bootloader_index;
+                    *index = index.saturating_sub(1);
+                }
+                KeyCode::Enter => self.current_action = CurrentAction::RestoreSummary,
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.current_action = CurrentAction::RestoreTarget
+                }
+                _ => {}
+            },
+            CurrentAction::RestoreSummary => match key_event.code {
+                KeyCode::Enter => {
+                    self.input.reset();
+                    self.input_mode = InputMode::Editing;
+                    self.restore_wizard.confirmation_mismatch = false;
+                    self.current_action = CurrentAction::RestoreConfirmation;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.current_action = CurrentAction::RestoreBootloader
+                }
+                _ => {}
+            },
+            CurrentAction::RestoreConfirmation => match key_event.code {
+                KeyCode::Esc => {
+                    self.input.reset();
+                    self.input_mode = InputMode::Normal;
+                    self.current_action = CurrentAction::RestoreSummary;
+                }
+                KeyCode::Enter => {
+                    if self.input.value() == self.restore_wizard.snapshot_name {
+                        self.start_restore();
+                    } else {
+                        self.restore_wizard.confirmation_mismatch = true;
+                    }
+                }
+                _ => {
+                    self.restore_wizard.confirmation_mismatch = false;
+                    self.input.handle_event(&Event::Key(key_event));
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// The list narrows at each key, the arrows still move in it. Enter keeps the search to act
+    /// on what it found, Esc drops it.
+    fn handle_search_key_event(&mut self, key_event: KeyEvent) {
+        let previous = self.current_snapshot().map(|s| s.name.clone());
+        match key_event.code {
+            KeyCode::Down => self.select_next(),
+            KeyCode::Up => self.select_previous(),
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                self.current_action = CurrentAction::Idle;
+            }
+            KeyCode::Esc => {
+                self.search.reset();
+                self.input_mode = InputMode::Normal;
+                self.current_action = CurrentAction::Idle;
+                self.update_search(previous);
+            }
+            _ => {
+                self.search.handle_event(&Event::Key(key_event));
+                self.update_search(previous);
+            }
+        }
+    }
+
+    /// The snapshot list previews the policy while it is edited, and keeps doing it once the
+    /// popup is closed.
+    fn handle_retention_key_event(&mut self, key_event: KeyEvent) {
+        let field = RetentionField::ALL[self.retention_index];
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.retention_index =
+                    (self.retention_index + 1).min(RetentionField::ALL.len() - 1);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.retention_index = self.retention_index.saturating_sub(1);
+            }
+            KeyCode::Char('+') | KeyCode::Char('l') | KeyCode::Right | KeyCode::Char(' ') => {
+                self.retention.adjust(field, true);
+            }
+            KeyCode::Char('-') | KeyCode::Char('h') | KeyCode::Left => {
+                self.retention.adjust(field, false);
+            }
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                self.current_action = CurrentAction::Idle;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_mark_key_event(&mut self, key_event: KeyEvent) {
+        if let CurrentAction::MarkOlderThan = self.current_action {
+            match key_event.code {
+                KeyCode::Esc => {
+                    self.input.reset();
+                    self.input_mode = InputMode::Normal;
+                    self.current_action = CurrentAction::MarkMenu;
+                }
+                KeyCode::Enter => match self.input.value().trim().parse::<i64>() {
+                    Ok(days) if days >= 0 => {
+                        self.input.reset();
+                        self.input_mode = InputMode::Normal;
+                        let older_than = TimeDelta::days(days);
+                        self.mark_where(|snapshot| snapshot.age() > older_than);
+                    }
+                    _ => self.bulk.invalid_days = true,
+                },
+                _ => {
+                    self.bulk.invalid_days = false;
+                    self.input.handle_event(&Event::Key(key_event));
+                }
+            }
+            return;
+        }
+        let choices = MarkChoice::all();
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.bulk.menu_index = (self.bulk.menu_index + 1).min(choices.len() - 1);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.bulk.menu_index = self.bulk.menu_index.saturating_sub(1);
+            }
+            KeyCode::Enter => match choices[self.bulk.menu_index] {
+                MarkChoice::Tag(tag) => self.mark_where(|snapshot| snapshot.tags.contains(&tag)),
+                MarkChoice::OlderThan => {
+                    self.input.reset();
+                    self.input_mode = InputMode::Editing;
+                    self.bulk.invalid_days = false;
+                    self.current_action = CurrentAction::MarkOlderThan;
+                }
+                MarkChoice::Unmark => {
+                    self.bulk.marked.clear();
+                    self.current_action = CurrentAction::Idle;
+                }
+            },
+            KeyCode::Esc | KeyCode::Char('q') => self.current_action = CurrentAction::Idle,
+            _ => {}
+        }
+    }
+
+    /// Marks the displayed snapshots matching `predicate`, on top of the ones already marked.
+    fn mark_where(&mut self, predicate: impl Fn(&Snapshot) -> bool) {
+        let names: Vec<String> = self
+            .visible_snapshots()
+            .into_iter()
+            .filter(|snapshot| predicate(snapshot))
+            .map(|snapshot| snapshot.name.clone())
+            .collect();
+        self.bulk.marked.extend(names);
+        self.current_action = CurrentAction::Idle;
+    }
+
+    /// The first `v` remembers where the range starts, the second one marks everything from
+    /// there to the selected snapshot.
+    fn mark_range(&mut self) {
+        let Some(start) = self.bulk.range_start.take() else {
+            self.bulk.range_start = Some(self.current_index);
+            return;
+        };
+        let (from, to) = if start <= self.current_index {
+            (start, self.current_index)
+        } else {
+            (self.current_index, start)
+        };
+        let names: Vec<String> = self
+            .visible_snapshots()
+            .into_iter()
+            .skip(from)
+            .take(to - from + 1)
+            .map(|snapshot| snapshot.name.clone())
+            .collect();
+        self.bulk.marked.extend(names);
+    }
+
+    fn start_bulk_deletion(&mut self) {
+        self.bulk.queue = self
+            .marked_snapshots()
+            .into_iter()
+            .map(|snapshot| snapshot.name.clone())
+            .collect();
+        self.bulk.results.clear();
+        self.bulk.stopping = false;
+        self.delete_next_marked();
+    }
+
+    fn handle_settings_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => self.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.select_last(),
+            KeyCode::Enter => self.edit_setting(),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(SettingsRow::Filter(i)) = self.settings_rows().get(self.current_index)
+                    && let Some(config) = &mut self.settings.config
+                {
+                    config.filters.remove(*i);
+                    self.settings.status = None;
+                }
+            }
+            KeyCode::Char('w') => self.save_settings(),
+            KeyCode::Char('q') | KeyCode::Esc => self.leave_settings(),
+            _ => {}
+        }
+    }
+
+    /// Same config as the Settings screen, but only the schedule : toggle a level, change how
+    /// many snapshots of it are kept.
+    fn handle_schedule_key_event(&mut self, key_event: KeyEvent) {
+        let level = ScheduleLevel::ALL.get(self.current_index).copied();
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => self.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.select_last(),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some(level) = level
+                    && let Some(config) = &mut self.settings.config
+                    && let Some(flag) = config.flag_mut(level.schedule_field())
+                {
+                    *flag = !*flag;
+                    self.settings.status = None;
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Char('l') | KeyCode::Right => {
+                if let Some(level) = level
+                    && let Some(config) = &mut self.settings.config
+                    && let Some(count) = config.count_mut(level.count_field())
+                {
+                    *count = (*count + 1).min(MAX_COUNT);
+                    self.settings.status = None;
+                }
+            }
+            KeyCode::Char('-') | KeyCode::Char('h') | KeyCode::Left => {
+                if let Some(level) = level
+                    && let Some(config) = &mut self.settings.config
+                    && let Some(count) = config.count_mut(level.count_field())
+                {
+                    *count = count.saturating_sub(1);
+                    self.settings.status = None;
+                }
+            }
+            KeyCode::Char('w') => self.save_settings(),
+            KeyCode::Char('q') | KeyCode::Esc => self.leave_settings(),
+            _ => {}
+        }
+    }
+
+    /// Leaving with unsaved changes needs a second press.
+    fn leave_settings(&mut self) {
+        if self.settings.is_modified()
+            && !matches!(self.settings.status, Some(SettingsStatus::UnsavedChanges))
+        {
+            self.settings.status = Some(SettingsStatus::UnsavedChanges);
+        } else {
+            self.back_or_exit();
+        }
+    }
+
+    fn handle_settings_input(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.input.reset();
+                self.input_mode = InputMode::Normal;
+                self.current_action = CurrentAction::Idle;
+            }
+            KeyCode::Enter => self.apply_setting(),
+            _ => {
+                self.input.handle_event(&Event::Key(key_event));
+            }
+        }
+    }
+
+    /// Reads timeshift.json for the Settings or the Schedule screen.
+    fn open_settings(&mut self, screen: Screen) {
+        let config = self.timeshift_instance.backend.read_config();
+        self.settings.status = match &config {
+            Ok(_) => None,
+            Err(e) => Some(SettingsStatus::Errors(vec![format!("{e:#}")])),
+        };
+        self.settings.config = config.ok();
+        self.settings.saved = self.settings.config.clone();
+        self.settings.cron_jobs = self.timeshift_instance.backend.cron_jobs();
+        self.current_display_screen = screen;
+        self.current_index = 0;
+    }
+
+    /// Flags are toggled right away, the other values are typed in an input.
+    fn edit_setting(&mut self) {
+        let Some(row) = self.settings_rows().get(self.current_index).copied() else {
+            return;
+        };
+        let Some(config) = &mut self.settings.config else {
+            return;
+        };
+        self.settings.status = None;
+        let value = match row {
+            SettingsRow::Field(field) => {
+                if let Some(flag) = config.flag_mut(field) {
+                    *flag = !*flag;
+                    return;
+                }
+                config.value(field)
+            }
+            SettingsRow::Filter(i) => config.filters[i].to_string(),
+            SettingsRow::AddFilter => String::new(),
+        };
+        self.input = Input::new(value);
+        self.input_mode = InputMode::Editing;
+        self.current_action = CurrentAction::SettingsEditing;
+    }
+
+    fn apply_setting(&mut self) {
+        let Some(row) = self.settings_rows().get(self.current_index).copied() else {
+            return;
+        };
+        let Some(config) = &mut self.settings.config else {
+            return;
+        };
+        let value = self.input.value().trim().to_string();
+        match row {
+            SettingsRow::Field(field) => {
+                if let Err(error) = config.set(field, &value) {
+                    // The input stays open so the user can fix the value
+                    self.settings.status = Some(SettingsStatus::Errors(vec![error]));
+                    return;
+                }
+            }
+            SettingsRow::Filter(i) => config.filters[i] = Filter::parse(&value),
+            SettingsRow::AddFilter => {
+                if !value.is_empty() {
+                    config.filters.push(Filter::parse(&value));
+                }
+            }
+        }
+        self.settings.status = None;
+        self.input.reset();
+        self.input_mode = InputMode::Normal;
+        self.current_action = CurrentAction::Idle;
+    }
+
+    fn save_settings(&mut self) {
+        let Some(config) = &self.settings.config else {
+            return;
+        };
+        let errors = config.validate();
+        if !errors.is_empty() {
+            self.settings.status = Some(SettingsStatus::Errors(errors));
+            return;
+        }
+        match self.timeshift_instance.backend.write_config(config) {
+            Ok(()) => {
+                self.settings.saved = Some(config.clone());
+                self.settings.status = Some(SettingsStatus::Saved);
+            }
+            Err(e) => {
+                self.settings.status = Some(SettingsStatus::Errors(vec![format!("{e:#}")]));
+            }
+        }
+    }
+
+    fn start_creation(&mut self) {
+        let comment = self.input.value_and_reset();
+        let Some(device) = self
+            .timeshift_instance
+            .devices_map
+            .keys()
+            .find(|device| device.device_name == self.current_device_name)
+            .cloned()
+        else {
+            return;
+        };
+        let include_home = self
+            .timeshift_instance
+            .btrfs_mode
+            .then_some(self.create_include_home);
+        self.start_operation(
+            CurrentAction::SnapshotCreationPending,
+            move |backend, context| {
+                create_snapshot_or_clean_up(&backend, &comment, &device, include_home, context)
+            },
+        );
+    }
+
+    fn start_restore(&mut self) {
+        let Some(options) = self.restore_options() else {
+            return;
+        };
+        self.input.reset();
+        self.input_mode = InputMode::Normal;
+        self.start_operation(CurrentAction::RestorePending, move |backend, context| {
+            backend.restore_snapshot(&options, context)
+        });
+    }
+
     fn back_or_exit(&mut self) {
         match self.current_display_screen {
             Screen::DeviceScreen => self.exit = true,
             Screen::SnapshotScreen => {
                 self.input_mode = crate::app::InputMode::Normal;
+                // The marks only make sense on the device they were made on
+                self.bulk = Default::default();
+                self.prune_preview = false;
+                self.search.reset();
                 self.current_display_screen = Screen::DeviceScreen;
                 self.current_index = 0; // Reset pour les snapshots
             }
+            Screen::BrowserScreen => self.close_browser(),
+            Screen::ReportScreen => self.close_report(),
+            Screen::SettingsScreen | Screen::ScheduleScreen => {
+                self.settings = Default::default();
+                self.current_display_screen = Screen::DeviceScreen;
+                self.current_index = 0;
+            }
         }
     }
 
     fn choose(&mut self) {
         if let Screen::DeviceScreen = self.current_display_screen {
             // Récupère la clé à l'index actuel
-            let device_name = self.timeshift_instance.devices_map.keys()[self.current_index]
-                .device_name
-                .clone();
+            let Some(device) = self.selected_device() else {
+                return;
+            };
+            let device_name = device.device_name.clone();
             self.current_device_name = device_name.clone();
             self.current_display_screen = Screen::SnapshotScreen;
             self.current_index = 0; // Reset pour les snapshots
+        } else if let Screen::SnapshotScreen = self.current_display_screen
+            && let CurrentAction::Idle = self.current_action
+        {
+            self.open_browser();
         }
     }
 
-    pub fn select_next(&mut self) {
-        let max = match self.current_display_screen {
-            Screen::DeviceScreen => self.timeshift_instance.devices_map_by_name.keys().len() - 1,
-            Screen::SnapshotScreen => {
-                self.timeshift_instance.devices_map_by_name[&self.current_device_name].len() - 1
+    /// Same keys as the lists, with Enter to go in a folder and `q` to go back up.
+    fn handle_browser_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => self.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.select_last(),
+            KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => self.browser_enter(),
+            KeyCode::Char('q') | KeyCode::Backspace | KeyCode::Char('h') | KeyCode::Left => {
+                self.browser_up()
             }
+            KeyCode::Char(' ') => self.browser_toggle_mark(),
+            KeyCode::Char('r') => self.ask_file_restore(),
+            KeyCode::Char('d') => self.ask_diff(),
+            KeyCode::Esc => self.close_browser(),
+            _ => {}
+        }
+    }
+
+    /// The folders on the left, the files of the selected one on the right. `Enter` on a file
+    /// opens its diff.
+    fn handle_report_key_event(&mut self, key_event: KeyEvent) {
+        let Some(report) = &mut self.report else {
+            return;
         };
+        if report.job.is_some() {
+            if let KeyCode::Esc | KeyCode::Char('q') = key_event.code {
+                self.close_report();
+            }
+            return;
+        }
+        if !report.focus_files {
+            match key_event.code {
+                KeyCode::Enter | KeyCode::Tab | KeyCode::Char('l') | KeyCode::Right
+                    if !report.files(self.current_index).is_empty() =>
+                {
+                    report.focus_files = true;
+                    report.file_index = 0;
+                }
+                KeyCode::Char('j') | KeyCode::Down => self.select_next(),
+                KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+                KeyCode::Char('g') | KeyCode::Home => self.select_first(),
+                KeyCode::Char('G') | KeyCode::End => self.select_last(),
+                KeyCode::Esc | KeyCode::Char('q') => self.close_report(),
+                _ => {}
+            }
+            return;
+        }
+        let last = report.files(self.current_index).len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Enter => self.report_diff(),
+            KeyCode::Char('j') | KeyCode::Down => {
+                report.file_index = (report.file_index + 1).min(last);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                report.file_index = report.file_index.saturating_sub(1);
+            }
+            KeyCode::Char('g') | KeyCode::Home => report.file_index = 0,
+            KeyCode::Char('G') | KeyCode::End => report.file_index = last,
+            KeyCode::Esc
+            | KeyCode::Char('q')
+            | KeyCode::Tab
+            | KeyCode::Char('h')
+            | KeyCode::Left => report.focus_files = false,
+            _ => {}
+        }
+    }
+
+    fn handle_diff_key_event(&mut self, key_event: KeyEvent) {
+        if let CurrentAction::DiffMenu = self.current_action {
+            let Some(browser) = &mut self.browser else {
+                self.current_action = CurrentAction::Idle;
+                return;
+            };
+            match key_event.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    browser.diff_index =
+                        (browser.diff_index + 1).min(browser.diff_targets.len().saturating_sub(1));
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    browser.diff_index = browser.diff_index.saturating_sub(1);
+                }
+                KeyCode::Enter => self.open_diff(),
+                KeyCode::Esc | KeyCode::Char('q') => self.current_action = CurrentAction::Idle,
+                _ => {}
+            }
+            return;
+        }
+        let Some(view) = &mut self.diff else {
+            self.current_action = CurrentAction::Idle;
+            return;
+        };
+        let last = view.line_count().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => view.scroll = (view.scroll + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                view.scroll = (view.scroll + DIFF_PAGE).min(last);
+            }
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(DIFF_PAGE),
+            KeyCode::Char('g') | KeyCode::Home => view.scroll = 0,
+            KeyCode::Char('G') | KeyCode::End => view.scroll = last,
+            KeyCode::Esc | KeyCode::Char('q') => self.close_diff(),
+            _ => {}
+        }
+    }
+
+    fn handle_file_restore_key_event(&mut self, key_event: KeyEvent) {
+        let Some(browser) = &mut self.browser else {
+            self.current_action = CurrentAction::Idle;
+            return;
+        };
+        match self.current_action {
+            CurrentAction::FileRestoreDestination => match key_event.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    browser.destination_index =
+                        (browser.destination_index + 1).min(FILE_RESTORE_CHOICES.len() - 1);
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    browser.destination_index = browser.destination_index.saturating_sub(1);
+                }
+                KeyCode::Enter if browser.destination_index == 0 => {
+                    self.plan_file_restore(FileDestination::Original);
+                }
+                KeyCode::Enter => {
+                    browser.invalid_folder = false;
+                    self.input.reset();
+                    self.input_mode = InputMode::Editing;
+                    self.current_action = CurrentAction::FileRestoreFolder;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.current_action = CurrentAction::Idle,
+                _ => {}
+            },
+            CurrentAction::FileRestoreFolder => match key_event.code {
+                KeyCode::Esc => {
+                    self.input.reset();
+                    self.input_mode = InputMode::Normal;
+                    self.current_action = CurrentAction::FileRestoreDestination;
+                }
+                KeyCode::Enter => {
+                    let folder = PathBuf::from(self.input.value().trim());
+                    if folder.is_absolute() {
+                        self.input.reset();
+                        self.input_mode = InputMode::Normal;
+                        self.plan_file_restore(FileDestination::Folder(folder));
+                    } else {
+                        browser.invalid_folder = true;
+                    }
+                }
+                _ => {
+                    browser.invalid_folder = false;
+                    self.input.handle_event(&Event::Key(key_event));
+                }
+            },
+            CurrentAction::FileRestoreConfirmation => match key_event.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.start_file_restore(),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    browser.restore_plan = None;
+                    self.current_action = CurrentAction::Idle;
+                }
+                _ => {}
+            },
+            CurrentAction::FileRestored => match key_event.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    browser.restore_plan = None;
+                    self.current_action = CurrentAction::Idle;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// The number keys sort the table by the column with that number, again to reverse it.
+    /// The selection follows the item it was on.
+    fn sort_by_column(&mut self, index: usize) {
+        match self.current_display_screen {
+            Screen::DeviceScreen => {
+                let Some(column) = DeviceColumn::ALL.get(index).copied() else {
+                    return;
+                };
+                let selected = self.selected_device().map(|d| d.device_name.clone());
+                self.device_sort.by(column);
+                self.current_index = self
+                    .visible_devices()
+                    .iter()
+                    .position(|d| Some(&d.device_name) == selected.as_ref())
+                    .unwrap_or(0);
+            }
+            Screen::SnapshotScreen => {
+                let Some(column) = self.snapshot_columns().get(index).copied() else {
+                    return;
+                };
+                let selected = self.current_snapshot().map(|s| s.name.clone());
+                self.snapshot_sort.by(column);
+                self.bulk.range_start = None;
+                self.current_index = self
+                    .visible_snapshots()
+                    .iter()
+                    .position(|s| Some(&s.name) == selected.as_ref())
+                    .unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        let max = self.list_len().saturating_sub(1);
         if self.current_index < max {
             self.current_index += 1;
         }
     }
 
+    /// Number of items in the list currently displayed.
+    fn list_len(&self) -> usize {
+        match self.current_display_screen {
+            Screen::DeviceScreen => self.timeshift_instance.devices_map.len(),
+            Screen::SnapshotScreen => self.visible_snapshots().len(),
+            Screen::SettingsScreen => self.settings_rows().len(),
+            Screen::ScheduleScreen => ScheduleLevel::ALL.len(),
+            Screen::ReportScreen => self
+                .report
+                .as_ref()
+                .and_then(|report| report.result.as_ref())
+                .and_then(|result| result.as_ref().ok())
+                .map_or(0, |report| report.groups.len()),
+            Screen::BrowserScreen => self
+                .browser
+                .as_ref()
+                .and_then(|browser| browser.entries.as_ref().ok())
+                .map_or(0, |entries| entries.len()),
+        }
+    }
+
     pub fn select_previous(&mut self) {
         if self.current_index > 0 {
             self.current_index -= 1;
@@ -146,30 +1092,23 @@ impl App {
     }
 
     pub fn select_last(&mut self) {
-        let max = match self.current_display_screen {
-            Screen::DeviceScreen => self.timeshift_instance.devices_map_by_name.keys().len() - 1,
-            Screen::SnapshotScreen => {
-                self.timeshift_instance.devices_map_by_name[&self.current_device_name].len() - 1
-            }
-        };
+        let max = self.list_len().saturating_sub(1);
         self.current_index = max;
     }
 
     fn delete_current_snapshot(&mut self) {
-        if let Screen::SnapshotScreen = self.current_display_screen {
-            let snapshot_to_delete = &self.timeshift_instance.devices_map_by_name
-                [&self.current_device_name.clone()][self.current_index];
+        if let Screen::SnapshotScreen = self.current_display_screen
+            && let Some(snapshot_to_delete) = self.current_snapshot()
+        {
             // On créé un thread pour delete le snapshot, et on attend la fin du tread.
             // Pour faire ça, comme la closure capture self, on clone les valeurs utilisé par
             // timeshift (c'est pas le plus opti, mais on est pas à ça près lol), et on les move
             // dans la closure
-            let snapshot_name = snapshot_to_delete.clone();
+            let snapshot_name = snapshot_to_delete.name.clone();
             let current_device = self.current_device_name.clone();
-            self.current_action = CurrentAction::SnapshotDeletion;
-            self.operation_thread = Some(thread::spawn(move || {
-                Timeshift::delete_snapshot(&snapshot_name.name, &current_device)
-                    .map_err(|e| e.to_string())
-            }));
+            self.start_operation(CurrentAction::SnapshotDeletion, move |backend, context| {
+                backend.delete_snapshot(&snapshot_name, &current_device, context)
+            });
         }
     }
 }
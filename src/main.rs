@@ -1,18 +1,59 @@
 mod app;
+mod cli;
 mod handlers;
 mod timeshift_lib;
 mod ui;
 
 use app::App;
+use chrono::TimeDelta;
+use cli::{CliArgs, CliError};
 use is_root::is_root;
+use std::env;
 use std::io;
-use timeshift_lib::Timeshift;
+use std::process;
+use timeshift_lib::{Backend, MockBackend, ProcessBackend, Timeshift};
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli = CliArgs::parse(&args).unwrap_or_else(|error| {
+        // Only the global options before the command say how to report it
+        let json = args
+            .iter()
+            .take_while(|arg| arg.starts_with('-'))
+            .any(|arg| arg == "--json");
+        process::exit(cli::report(&error, json))
+    });
+    if cli.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    // With a subcommand, we run it and exit without starting the TUI
+    if let Some(command) = &cli.command {
+        let code = if cli.mock {
+            cli::run(&MockBackend::demo().configure_from_env(), command, cli.json)
+        } else if !is_root() {
+            cli::report(&CliError::NotRoot, cli.json)
+        } else {
+            cli::run(&ProcessBackend, command, cli.json)
+        };
+        process::exit(code);
+    }
+
+    // --mock runs the TUI on fake data, no need for root or timeshift for that
+    if cli.mock {
+        let timeshift = Timeshift::new(MockBackend::demo().configure_from_env());
+        return run(App::new(timeshift), cli.stale_after);
+    }
     if !is_root() {
-        panic!("You must run this executable with root permissions");
+        process::exit(cli::report(&CliError::NotRoot, false));
+    }
+    run(App::new(Timeshift::new(ProcessBackend)), cli.stale_after)
+}
+
+fn run<B: Backend>(mut app: App<B>, stale_after: Option<TimeDelta>) -> io::Result<()> {
+    if let Some(stale_after) = stale_after {
+        app = app.with_stale_after(stale_after);
     }
-    let timeshift = Timeshift::new();
-    let mut app = App::new(timeshift);
     let mut terminal = ratatui::init();
     let app_result = app.run(&mut terminal);
     ratatui::restore();
@@ -1,28 +1,79 @@
-use anyhow::{Context, Result};
+pub mod backend;
+pub mod browse;
+pub mod btrfs;
+pub mod compare;
+pub mod config;
+pub mod diff;
+pub mod export;
+pub mod file_restore;
+pub mod info;
+pub mod mock;
+pub mod mount;
+pub mod operation;
+pub mod packages;
+pub mod parser;
+pub mod retention;
+pub mod schedule;
+pub mod search;
+pub mod space;
+pub mod usage;
+
+pub use backend::{Backend, CommandError, ProcessBackend, create_snapshot_or_clean_up};
+pub use browse::{FileEntry, FileKind, Preview, SnapshotTree};
+pub use btrfs::Subvolume;
+pub use compare::{Change, ChangeKind, ChangeReport};
+pub use config::{ConfigField, Filter, MAX_COUNT, TimeshiftConfig};
+pub use diff::{DiffLine, FileDiff};
+pub use export::{ExportFormat, Inventory};
+pub use file_restore::{FileDestination, FileRestorePlan, original_path};
+pub use info::SnapshotInfo;
+pub use mock::MockBackend;
+pub use operation::{
+    CancelHandle, Cancelled, OPERATION_LOG_LINES, OperationContext, OperationEvent, Progress,
+};
+pub use packages::{InstalledPackages, PackageChange, PackageChangeKind};
+pub use parser::{ParseError, Parsed};
+pub use retention::{KeepReason, RetentionField, RetentionPolicy};
+pub use schedule::{CronJobs, ScheduleLevel};
+pub use search::search_score;
+pub use space::{DeviceSpace, GROWTH_SAMPLE};
+pub use usage::{SnapshotSize, format_bytes};
+
+use anyhow::Context;
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta};
 use indexmap::IndexMap;
-use std::collections::HashMap;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
-use std::process::Command;
-use std::str;
+use std::path::PathBuf;
 
-#[derive(Debug, Default, Clone, Eq, Hash, PartialEq)]
+/// Timeshift names the snapshots after the time they were taken, with this format.
+pub const SNAPSHOT_NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+#[derive(Debug, Default, Clone, Eq, Hash, PartialEq, Serialize)]
 pub struct Device {
-    num: u8,
+    pub num: u32,
     pub device_name: String,
-    size: String,
-    device_type: String, //should be an enum, will do it later
-    label: String, // I legit don't know what that is, mine is always left blank on my system, and I
-                   // do not find the documentation (tell me if you know what that is)
+    pub size: String,
+    pub device_type: DeviceType,
+    /// Read from the mounted device, `None` until the backend was asked
+    #[serde(skip)]
+    pub space: Option<Result<DeviceSpace, String>>,
+    /// Where the device is mounted, `None` when it isn't
+    #[serde(skip)]
+    pub mount_point: Option<PathBuf>,
+    pub label: String, // I legit don't know what that is, mine is always left blank on my system, and I
+                       // do not find the documentation (tell me if you know what that is)
 }
 
 impl Device {
     pub fn new(
-        num: u8,
+        num: u32,
         device_name: String,
         size: String, // I use the size in String because its a float, and float cannot use the Eq
         // trait (maybe there is a workaround but I don't really need the size in f32
         // anyway)
-        device_type: String,
+        device_type: DeviceType,
         label: String,
     ) -> Self {
         Device {
@@ -31,9 +82,45 @@ impl Device {
             size,
             device_type,
             label,
+            space: None,
+            mount_point: None,
+        }
+    }
+}
+impl Device {
+    pub fn is_btrfs(&self) -> bool {
+        self.device_type == DeviceType::Btrfs
+    }
+
+    /// The size timeshift prints, like `500.0 GB`. Timeshift counts in powers of 1000.
+    pub fn size_bytes(&self) -> Option<u64> {
+        let (number, unit) = self.size.trim().split_once(' ')?;
+        let number: f64 = number.parse().ok()?;
+        let unit = match unit {
+            "B" => 1.0,
+            "KB" => 1e3,
+            "MB" => 1e6,
+            "GB" => 1e9,
+            "TB" => 1e12,
+            _ => return None,
+        };
+        Some((number * unit) as u64)
+    }
+
+    /// The disk the partition is on, which is where GRUB gets installed : `/dev/sda2` gives
+    /// `/dev/sda` and `/dev/nvme0n1p2` gives `/dev/nvme0n1`.
+    pub fn disk(&self) -> String {
+        let name = self
+            .device_name
+            .trim_end_matches(|c: char| c.is_ascii_digit());
+        if (name.contains("nvme") || name.contains("mmcblk")) && name.ends_with('p') {
+            name[..name.len() - 1].to_string()
+        } else {
+            name.to_string()
         }
     }
 }
+
 impl fmt::Display for Device {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -44,209 +131,273 @@ impl fmt::Display for Device {
     }
 }
 
-#[derive(Debug, Default, Clone)] // We cannot use the copy trait because there is a String in our
+/// The filesystem of a device, as printed by `timeshift --list-devices`. Only btrfs changes
+/// how timeshift works, the other ones are kept as they are printed.
+#[derive(Debug, Default, Clone, Eq, Hash, PartialEq)]
+pub enum DeviceType {
+    #[default]
+    Ext4,
+    Btrfs,
+    Other(String),
+}
+
+impl DeviceType {
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "ext4" => DeviceType::Ext4,
+            "btrfs" => DeviceType::Btrfs,
+            other => DeviceType::Other(other.to_string()),
+        }
+    }
+}
+
+/// Written as timeshift prints it, like `ext4`.
+impl Serialize for DeviceType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceType::Ext4 => write!(f, "ext4"),
+            DeviceType::Btrfs => write!(f, "btrfs"),
+            DeviceType::Other(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Why a snapshot was taken. Timeshift prints them as one letter each in the tags column, and a
+/// snapshot can have several of them (`OD` for example).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SnapshotTag {
+    Ondemand,
+    Boot,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl SnapshotTag {
+    pub const ALL: [SnapshotTag; 6] = [
+        SnapshotTag::Ondemand,
+        SnapshotTag::Boot,
+        SnapshotTag::Hourly,
+        SnapshotTag::Daily,
+        SnapshotTag::Weekly,
+        SnapshotTag::Monthly,
+    ];
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        SnapshotTag::ALL
+            .into_iter()
+            .find(|tag| tag.letter() == letter)
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            SnapshotTag::Ondemand => 'O',
+            SnapshotTag::Boot => 'B',
+            SnapshotTag::Hourly => 'H',
+            SnapshotTag::Daily => 'D',
+            SnapshotTag::Weekly => 'W',
+            SnapshotTag::Monthly => 'M',
+        }
+    }
+
+    /// How the tag is written in the `info.json` of the snapshots.
+    pub fn word(self) -> &'static str {
+        match self {
+            SnapshotTag::Ondemand => "ondemand",
+            SnapshotTag::Boot => "boot",
+            SnapshotTag::Hourly => "hourly",
+            SnapshotTag::Daily => "daily",
+            SnapshotTag::Weekly => "weekly",
+            SnapshotTag::Monthly => "monthly",
+        }
+    }
+
+    pub fn from_word(word: &str) -> Option<Self> {
+        SnapshotTag::ALL.into_iter().find(|tag| tag.word() == word)
+    }
+
+    /// Parses a whole tags column, `None` if one of the letters is not a tag.
+    pub fn parse_set(letters: &str) -> Option<BTreeSet<SnapshotTag>> {
+        letters.chars().map(SnapshotTag::from_letter).collect()
+    }
+}
+
+/// Written like in the `info.json` of the snapshots, like `ondemand`.
+impl Serialize for SnapshotTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.word())
+    }
+}
+
+impl fmt::Display for SnapshotTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SnapshotTag::Ondemand => "On-demand",
+            SnapshotTag::Boot => "Boot",
+            SnapshotTag::Hourly => "Hourly",
+            SnapshotTag::Daily => "Daily",
+            SnapshotTag::Weekly => "Weekly",
+            SnapshotTag::Monthly => "Monthly",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)] // We cannot use the copy trait because there is a String in our
 // struct (:/)
 pub struct Snapshot {
-    num: u8,
+    pub num: u32,
 
     pub name: String,
+    /// When the snapshot was taken, read from its name.
+    pub date: DateTime<Local>,
 
-    tags: char,
-    description: String,
+    pub tags: BTreeSet<SnapshotTag>,
+    pub description: String,
 }
 
 impl Snapshot {
-    pub fn new(num: u8, name: String, tags: char, description: String) -> Self {
+    pub fn new(
+        num: u32,
+        name: String,
+        date: DateTime<Local>,
+        tags: BTreeSet<SnapshotTag>,
+        description: String,
+    ) -> Self {
         Snapshot {
             num,
             name,
+            date,
             tags,
             description,
         }
     }
+
+    /// Reads the date out of a snapshot name like `2025-10-01_12-00-01`. Timeshift uses the
+    /// local time of the machine for the names.
+    pub fn date_from_name(name: &str) -> Option<DateTime<Local>> {
+        NaiveDateTime::parse_from_str(name, SNAPSHOT_NAME_FORMAT)
+            .ok()?
+            .and_local_timezone(Local)
+            .earliest()
+    }
+
+    /// The tags as timeshift prints them, like `OD`.
+    pub fn tag_letters(&self) -> String {
+        self.tags.iter().map(|tag| tag.letter()).collect()
+    }
+
+    pub fn age(&self) -> TimeDelta {
+        Local::now().signed_duration_since(self.date)
+    }
 }
 impl fmt::Display for Snapshot {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "{} | {} | {} | {}",
-            self.num, self.name, self.tags, self.description
+            self.num,
+            self.name,
+            self.tag_letters(),
+            self.description
         )
     }
 }
 
+/// What to do with the bootloader when restoring a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bootloader {
+    /// Reinstall GRUB on this device (`--grub-device`)
+    Reinstall(String),
+    /// Leave the bootloader alone (`--skip-grub`)
+    Skip,
+}
+
+/// Everything timeshift needs to know to restore a snapshot.
+#[derive(Debug, Clone)]
+pub struct RestoreOptions {
+    pub snapshot_name: String,
+    pub snapshot_device: String,
+    pub target_device: String,
+    pub bootloader: Bootloader,
+}
+
+/// Holds everything we know about the devices and their snapshots. The actual calls to timeshift
+/// are done by the backend `B`, so the same struct works with the real binary or with the mock.
 #[derive(Debug, Default)]
-pub struct Timeshift {
+pub struct Timeshift<B> {
+    pub backend: B,
     //why did i create such a monster
     pub devices_map: IndexMap<Device, Vec<Snapshot>>,
     pub devices_map_by_name: HashMap<String, Vec<Snapshot>>,
+    pub parse_errors: Vec<ParseError>,
+    /// Timeshift takes btrfs snapshots instead of rsync ones
+    pub btrfs_mode: bool,
+    /// Why the last refresh failed, the lists are then the ones from before
+    pub list_error: Option<String>,
 }
 
-pub enum DeviceOrSnapshot {
-    Device(Device),
-    Snapshot(Snapshot),
-}
-
-impl Timeshift {
-    pub fn new() -> Self {
-        let (devices_map, devices_map_by_name) = Self::fetch_info();
-        Timeshift {
-            devices_map,
-            devices_map_by_name,
-        }
+impl<B: Backend> Timeshift<B> {
+    pub fn new(backend: B) -> Self {
+        let mut timeshift = Timeshift {
+            backend,
+            devices_map: IndexMap::new(),
+            devices_map_by_name: HashMap::new(),
+            parse_errors: Vec::new(),
+            btrfs_mode: false,
+            list_error: None,
+        };
+        // Kept in `list_error`, the device screen tells about it
+        let _ = timeshift.update();
+        timeshift
     }
 
-    pub fn fetch_info() -> (
-        IndexMap<Device, Vec<Snapshot>>,
-        HashMap<String, Vec<Snapshot>>,
-    ) {
-        let mut devices_map: IndexMap<Device, Vec<Snapshot>> = IndexMap::new();
-        let mut devices_map_by_name: HashMap<String, Vec<Snapshot>> = HashMap::new();
-        let devices: Vec<Device> = Self::get_devices();
-        for device in devices {
-            devices_map.insert(device.clone(), Self::get_snapshots(device.clone()));
-            devices_map_by_name.insert(device.clone().device_name, Self::get_snapshots(device));
-        }
-        (devices_map, devices_map_by_name)
-    }
-
-    pub fn update(&mut self) {
-        (self.devices_map, self.devices_map_by_name) = Self::fetch_info();
-    }
-
-    pub fn get_snapshots(device: Device) -> Vec<Snapshot> {
-        let output = Command::new("timeshift")
-            .arg("--list")
-            .arg("--snapshot-device")
-            .arg(device.device_name)
-            .output()
-            .expect("Couldn't get snapshots list");
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let result: Vec<Snapshot> = Self::parse_output(stdout.to_string(), "Snapshot")
-            .into_iter()
-            .map(|item| match item {
-                DeviceOrSnapshot::Snapshot(snapshot) => snapshot,
-                DeviceOrSnapshot::Device(_) => panic!("Expected Snapshot, got Device"),
-            })
-            .collect();
-        result
-    }
-
-    pub fn get_devices() -> Vec<Device> {
-        let output = Command::new("timeshift")
-            .arg("--list-devices")
-            .output()
-            .expect("Couldn't get device list");
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let result: Vec<Device> = Self::parse_output(stdout.to_string(), "Device")
-            .into_iter()
-            .map(|item| match item {
-                DeviceOrSnapshot::Device(device) => device,
-                DeviceOrSnapshot::Snapshot(_) => panic!("Expected Device, got snapshot"),
-            })
-            .collect();
-
-        if result.is_empty() {
-            panic!("No devices found");
-        }
-        result
-    }
-
-    // Le but de cette fonction est de généraliser le parsing des output de timeshift
-    // I found out that the timeshift command always return dashes, so I exploit that.
-    // I don't know if generalizing the output with an enum is good practice, but why not try ? We
-    // have only one life after all :)
-    pub fn parse_output(s: String, t: &str) -> Vec<DeviceOrSnapshot> {
-        let mut result: Vec<DeviceOrSnapshot> = Vec::new();
-        let lines_after_separator = s
-            .split_once("----")
-            .map(|(_, after)| after.lines().skip(1)) // skip(1) pour ignorer la fin de la ligne des dashes
-            .into_iter()
-            .flatten()
-            .filter(|line| !line.trim().is_empty()); // Filtre les lignes vides !
-        for line in lines_after_separator {
-            if t == "Device" {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                let num = parts[0].parse::<u8>().expect("Could not parse num");
-                let device_name = parts[2].to_string();
-                let size = parts[3].to_string();
-                let device_type = parts[4].to_string();
-                let label = "".to_string();
-                // On récupère tout le
-                // reste (heuresement que
-                // le truc est à la fin)
-
-                result.push(DeviceOrSnapshot::Device(Device::new(
-                    num,
-                    device_name,
-                    size,
-                    device_type,
-                    label,
-                )));
-            } else if t == "Snapshot" {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() < 5 {
-                    // panic!("Invalid snapshot format");
-                }
-                let num = parts[0].parse::<u8>().expect("Could not parse num");
-                let name = String::from(parts[2]);
-                let tags = parts[3].parse::<char>().expect("could not parse Tags");
-                let description = parts[4..].join(" ").to_string();
-                // On récupère tout le
-                // reste (heuresement que
-                // le truc est à la fin)
-                result.push(DeviceOrSnapshot::Snapshot(Snapshot::new(
-                    num,
-                    name,
-                    tags,
-                    description,
-                )));
-            }
-        }
+    /// Asks the backend for the devices and their snapshots again. The rows of the outputs that
+    /// could not be read are kept in `parse_errors` so the UI can tell about them. When timeshift
+    /// fails, the last lists we got are kept and the error goes in `list_error` too.
+    pub fn update(&mut self) -> anyhow::Result<()> {
+        let result = self.read_lists();
+        self.list_error = result.as_ref().err().map(|e| format!("{e:#}"));
         result
     }
 
-    pub fn delete_snapshot(snapshot_name: &str, snapshot_device: &str) -> Result<()> {
-        let output = Command::new("timeshift")
-            .arg("--delete")
-            .arg("--snapshot")
-            .arg(snapshot_name)
-            .arg("--snapshot-device")
-            .arg(snapshot_device)
-            .output()
-            .context("Failed to execute timeshift command")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stdout);
-            anyhow::bail!(
-                "Timeshift delete failed with exit code {:?}: {}",
-                output.status.code(),
-                stderr
+    fn read_lists(&mut self) -> anyhow::Result<()> {
+        let devices = self
+            .backend
+            .get_devices()
+            .context("Couldn't get the device list")?;
+        let mut parse_errors = devices.errors;
+        let mut devices_map = IndexMap::new();
+        let mut devices_map_by_name = HashMap::new();
+        for mut device in devices.items {
+            let snapshots = self.backend.get_snapshots(&device).with_context(|| {
+                format!("Couldn't get the snapshot list of {}", device.device_name)
+            })?;
+            device.space = Some(
+                self.backend
+                    .device_space(&device)
+                    .map_err(|e| format!("{e:#}")),
             );
+            device.mount_point = self.backend.mount_point(&device);
+            parse_errors.extend(snapshots.errors);
+            devices_map.insert(device.clone(), snapshots.items.clone());
+            devices_map_by_name.insert(device.device_name, snapshots.items);
         }
-
-        Ok(())
-    }
-
-    pub fn create_snapshot(comment: String, snapshot_device: &str) -> Result<()> {
-        let output = Command::new("timeshift")
-            .arg("--create")
-            .arg("--comment")
-            .arg(comment)
-            .arg("--snapshot-device")
-            .arg(snapshot_device)
-            .output()
-            .context("Failed to execute timeshift command")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stdout);
-            anyhow::bail!(
-                "Timeshift delete failed with exit code {:?}: {}",
-                output.status.code(),
-                stderr
-            );
-        }
-
+        // Without the config we can't know, rsync is what timeshift does by default
+        self.btrfs_mode = self
+            .backend
+            .read_config()
+            .is_ok_and(|config| config.btrfs_mode);
+        self.devices_map = devices_map;
+        self.devices_map_by_name = devices_map_by_name;
+        self.parse_errors = parse_errors;
         Ok(())
     }
 }
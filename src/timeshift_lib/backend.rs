@@ -0,0 +1,524 @@
+use super::browse::SnapshotTree;
+use super::btrfs::{self, Subvolume};
+use super::config::{self, TimeshiftConfig};
+use super::file_restore::FileRestorePlan;
+use super::info::SnapshotInfo;
+use super::mount::{self, Mount};
+use super::operation::{self, CancelHandle, Cancelled, OperationContext};
+use super::packages::{self, InstalledPackages};
+use super::parser::{self, Parsed};
+use super::retention;
+use super::schedule::CronJobs;
+use super::space::DeviceSpace;
+use super::usage::{self, SnapshotSize};
+use super::{Bootloader, Device, RestoreOptions, Snapshot, SnapshotTag};
+use anyhow::{Context, Result};
+use chrono::{Local, TimeDelta};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long timeshift gets to stop by itself after SIGINT, before we kill it.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Everything the TUI needs from timeshift. `ProcessBackend` calls the real binary, and
+/// `MockBackend` keeps everything in memory so the app can run without root or timeshift.
+/// The backend is cloned into the threads doing the long operations, hence the bounds. Those
+/// operations send what timeshift prints to the context while they run, and stop when it is
+/// cancelled.
+pub trait Backend: Clone + Send + 'static {
+    fn get_devices(&self) -> Result<Parsed<Device>>;
+    fn get_snapshots(&self, device: &Device) -> Result<Parsed<Snapshot>>;
+    /// `include_home` is only given in btrfs mode : whether `@home` goes in this snapshot,
+    /// whatever the settings say.
+    fn create_snapshot(
+        &self,
+        comment: &str,
+        snapshot_device: &str,
+        include_home: Option<bool>,
+        context: &OperationContext,
+    ) -> Result<()>;
+    fn delete_snapshot(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+        context: &OperationContext,
+    ) -> Result<()>;
+    fn restore_snapshot(&self, options: &RestoreOptions, context: &OperationContext) -> Result<()>;
+    /// The metadata timeshift keeps next to the snapshot, in its `info.json`.
+    fn snapshot_info(&self, snapshot_name: &str, snapshot_device: &str) -> Result<SnapshotInfo>;
+    /// The subvolumes saved in a snapshot taken in btrfs mode.
+    fn snapshot_subvolumes(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+    ) -> Result<Vec<Subvolume>>;
+    /// The folder of the snapshot, with its device mounted read-only if it wasn't mounted.
+    fn open_snapshot(&self, snapshot_name: &str, snapshot_device: &str) -> Result<SnapshotTree>;
+    /// The packages installed in the system saved by a snapshot.
+    fn snapshot_packages(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+    ) -> Result<InstalledPackages>;
+    /// The packages installed on the running system.
+    fn installed_packages(&self) -> Result<InstalledPackages>;
+    /// Copies files of an opened snapshot back, moving what they replace in the backup folder
+    /// of the plan.
+    fn restore_files(&self, plan: &FileRestorePlan, context: &OperationContext) -> Result<()>;
+    /// Measures the rsync snapshots one after the other, `on_size` gets the result of each one as
+    /// soon as it is known. It can take minutes on big snapshots, so it stops early once `cancel`
+    /// is cancelled.
+    fn snapshot_sizes(
+        &self,
+        snapshot_device: &str,
+        snapshot_names: &[String],
+        cancel: &CancelHandle,
+        on_size: &mut dyn FnMut(&str, Result<SnapshotSize>),
+    );
+    /// Total, used and free bytes of the device the snapshots are on.
+    fn device_space(&self, device: &Device) -> Result<DeviceSpace>;
+    /// Where the device is mounted, without mounting it.
+    fn mount_point(&self, device: &Device) -> Option<PathBuf>;
+    /// The settings of timeshift, from `timeshift.json`.
+    fn read_config(&self) -> Result<TimeshiftConfig>;
+    fn write_config(&self, config: &TimeshiftConfig) -> Result<()>;
+    /// The names of the snapshots pinned for the retention policy.
+    fn read_pins(&self) -> Result<BTreeSet<String>>;
+    fn write_pins(&self, pins: &BTreeSet<String>) -> Result<()>;
+    /// The cron jobs that run the scheduled snapshots.
+    fn cron_jobs(&self) -> CronJobs;
+}
+
+/// Timeshift ran, but exited with an error. We keep the exit code and what it printed so the UI
+/// can show them to the user.
+#[derive(Debug, Clone)]
+pub struct CommandError {
+    pub code: Option<i32>,
+    pub output: String,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "timeshift exited with code {code}: {}", self.output),
+            None => write!(f, "timeshift was killed by a signal: {}", self.output),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// The real thing : every call spawns the `timeshift` command.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessBackend;
+
+impl Backend for ProcessBackend {
+    fn get_devices(&self) -> Result<Parsed<Device>> {
+        let output = Command::new("timeshift")
+            .arg("--list-devices")
+            .output()
+            .context("Couldn't get device list")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // A failed timeshift prints no table, that is not the same as no device
+        check_status(
+            output.status,
+            &stdout,
+            &String::from_utf8_lossy(&output.stderr),
+        )?;
+        Ok(parser::parse_devices(&stdout))
+    }
+
+    fn get_snapshots(&self, device: &Device) -> Result<Parsed<Snapshot>> {
+        let output = Command::new("timeshift")
+            .arg("--list")
+            .arg("--snapshot-device")
+            .arg(&device.device_name)
+            .output()
+            .context("Couldn't get snapshots list")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        check_status(
+            output.status,
+            &stdout,
+            &String::from_utf8_lossy(&output.stderr),
+        )?;
+        Ok(parser::parse_snapshots(&stdout))
+    }
+
+    fn create_snapshot(
+        &self,
+        comment: &str,
+        snapshot_device: &str,
+        include_home: Option<bool>,
+        context: &OperationContext,
+    ) -> Result<()> {
+        // Only when it isn't what the settings already say
+        let mut config_override = None;
+        if let Some(include_home) = include_home {
+            let mut config = self.read_config()?;
+            if config.include_btrfs_home_for_backup != include_home {
+                config.include_btrfs_home_for_backup = include_home;
+                config_override = Some(ConfigOverride::new(&config)?);
+            }
+        }
+        let mut command = match &config_override {
+            Some(config) => config.command("timeshift"),
+            None => Command::new("timeshift"),
+        };
+        command
+            .arg("--create")
+            .arg("--comment")
+            .arg(comment)
+            .arg("--snapshot-device")
+            .arg(snapshot_device);
+        run_streaming(&mut command, context).context("Timeshift create failed")
+    }
+
+    fn delete_snapshot(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+        context: &OperationContext,
+    ) -> Result<()> {
+        let mut command = Command::new("timeshift");
+        command
+            .arg("--delete")
+            .arg("--snapshot")
+            .arg(snapshot_name)
+            .arg("--snapshot-device")
+            .arg(snapshot_device);
+        run_streaming(&mut command, context).context("Timeshift delete failed")
+    }
+
+    fn restore_snapshot(&self, options: &RestoreOptions, context: &OperationContext) -> Result<()> {
+        let mut command = Command::new("timeshift");
+        command
+            .arg("--restore")
+            .arg("--snapshot")
+            .arg(&options.snapshot_name)
+            .arg("--snapshot-device")
+            .arg(&options.snapshot_device)
+            .arg("--target-device")
+            .arg(&options.target_device);
+        match &options.bootloader {
+            Bootloader::Reinstall(grub_device) => command.arg("--grub-device").arg(grub_device),
+            Bootloader::Skip => command.arg("--skip-grub"),
+        };
+        // Everything has already been confirmed in the TUI, timeshift must not ask again
+        command.arg("--yes");
+        run_streaming(&mut command, context).context("Timeshift restore failed")
+    }
+
+    fn snapshot_info(&self, snapshot_name: &str, snapshot_device: &str) -> Result<SnapshotInfo> {
+        let mount = Mount::device(snapshot_device)?;
+        let path = mount.snapshot_dir(snapshot_name)?.join("info.json");
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        SnapshotInfo::parse(&json)
+    }
+
+    fn snapshot_subvolumes(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+    ) -> Result<Vec<Subvolume>> {
+        let mount = Mount::device(snapshot_device)?;
+        btrfs::subvolumes(&mount.snapshot_dir(snapshot_name)?)
+    }
+
+    fn open_snapshot(&self, snapshot_name: &str, snapshot_device: &str) -> Result<SnapshotTree> {
+        let mount = Mount::device(snapshot_device)?;
+        let root = mount.snapshot_dir(snapshot_name)?;
+        Ok(SnapshotTree::new(root, Some(mount)))
+    }
+
+    fn snapshot_packages(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+    ) -> Result<InstalledPackages> {
+        let tree = self.open_snapshot(snapshot_name, snapshot_device)?;
+        packages::installed_packages(&tree.system_root())
+    }
+
+    fn installed_packages(&self) -> Result<InstalledPackages> {
+        packages::installed_packages(Path::new("/"))
+    }
+
+    fn restore_files(&self, plan: &FileRestorePlan, context: &OperationContext) -> Result<()> {
+        for (i, item) in plan.items.iter().enumerate() {
+            if context.is_cancelled() {
+                return Err(Cancelled {
+                    details: format!(
+                        "{i} of {} restored, {} left as it was",
+                        plan.items.len(),
+                        item.destination.display()
+                    ),
+                }
+                .into());
+            }
+            if let Some(parent) = item.destination.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Couldn't create {}", parent.display()))?;
+            }
+            context.send_line(&format!("Restoring {}", item.destination.display()));
+            let mut command = Command::new("rsync");
+            command.args(item.rsync_args(&plan.backup_dir));
+            run_streaming(&mut command, context)
+                .with_context(|| format!("Couldn't restore {}", item.destination.display()))?;
+        }
+        Ok(())
+    }
+
+    fn snapshot_sizes(
+        &self,
+        snapshot_device: &str,
+        snapshot_names: &[String],
+        cancel: &CancelHandle,
+        on_size: &mut dyn FnMut(&str, Result<SnapshotSize>),
+    ) {
+        // Mounted once for all the snapshots
+        let mount = match Mount::device(snapshot_device) {
+            Ok(mount) => mount,
+            Err(e) => {
+                let error = format!("{e:#}");
+                for name in snapshot_names {
+                    on_size(name, Err(anyhow::anyhow!(error.clone())));
+                }
+                return;
+            }
+        };
+        for name in snapshot_names {
+            if cancel.is_cancelled() {
+                return;
+            }
+            on_size(
+                name,
+                mount
+                    .snapshot_dir(name)
+                    .and_then(|dir| usage::measure(&dir, cancel)),
+            );
+        }
+    }
+
+    fn device_space(&self, device: &Device) -> Result<DeviceSpace> {
+        let mount = Mount::device(&device.device_name)?;
+        DeviceSpace::read(&mount.path)
+    }
+
+    fn mount_point(&self, device: &Device) -> Option<PathBuf> {
+        mount::mount_point(&device.device_name)
+    }
+
+    fn read_config(&self) -> Result<TimeshiftConfig> {
+        let json = fs::read_to_string(config::CONFIG_PATH)
+            .with_context(|| format!("Couldn't read {}", config::CONFIG_PATH))?;
+        TimeshiftConfig::parse(&json)
+    }
+
+    fn write_config(&self, config: &TimeshiftConfig) -> Result<()> {
+        config.save(Path::new(config::CONFIG_PATH))
+    }
+
+    fn read_pins(&self) -> Result<BTreeSet<String>> {
+        retention::read_pins(Path::new(retention::PINS_PATH))
+    }
+
+    fn write_pins(&self, pins: &BTreeSet<String>) -> Result<()> {
+        retention::write_pins(Path::new(retention::PINS_PATH), pins)
+    }
+
+    fn cron_jobs(&self) -> CronJobs {
+        CronJobs::from_system()
+    }
+}
+
+/// Creates a snapshot, and when the user cancels it, deletes what timeshift had the time to
+/// create so no partial snapshot is left behind. Timeshift names the snapshot after the time
+/// it started, so ours is the first new one named between the start and the cancellation.
+/// The ones with a schedule tag were taken by the cron job meanwhile, they stay.
+pub fn create_snapshot_or_clean_up<B: Backend>(
+    backend: &B,
+    comment: &str,
+    device: &Device,
+    include_home: Option<bool>,
+    context: &OperationContext,
+) -> Result<()> {
+    let snapshots = |backend: &B| -> Vec<Snapshot> {
+        backend
+            .get_snapshots(device)
+            .map(|snapshots| snapshots.items)
+            .unwrap_or_default()
+    };
+    let before: Vec<String> = snapshots(backend).into_iter().map(|s| s.name).collect();
+    // The names are to the second
+    let started = Local::now() - TimeDelta::seconds(1);
+    let result = backend.create_snapshot(comment, &device.device_name, include_home, context);
+    let Some(cancelled) = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<Cancelled>())
+    else {
+        return result;
+    };
+
+    let stopped = Local::now();
+    let ours = snapshots(backend)
+        .into_iter()
+        .filter(|snapshot| !before.contains(&snapshot.name))
+        .filter(|snapshot| {
+            snapshot
+                .tags
+                .iter()
+                .all(|tag| *tag == SnapshotTag::Ondemand)
+        })
+        .filter_map(|snapshot| {
+            let date = Snapshot::date_from_name(&snapshot.name)?;
+            (started..=stopped)
+                .contains(&date)
+                .then_some((date, snapshot.name))
+        })
+        .min();
+    let mut details = cancelled.details.clone();
+    if let Some((_, name)) = ours {
+        // The context is cancelled, the deletion needs a fresh one
+        let cleanup_context = OperationContext::new(context.events.clone());
+        context.send_line(&format!("Removing partial snapshot {name}"));
+        match backend.delete_snapshot(&name, &device.device_name, &cleanup_context) {
+            Ok(()) => details.push_str(&format!("\nRemoved partial snapshot {name}")),
+            Err(e) => details.push_str(&format!(
+                "\nCould not remove partial snapshot {name}: {e:#}"
+            )),
+        }
+    }
+    Err(Cancelled { details }.into())
+}
+
+/// Timeshift has no option for it on the command line : `@home` goes in the btrfs snapshots
+/// when `include_btrfs_home_for_backup` is on. Instead of changing the real settings (the cron
+/// job of timeshift could read them meanwhile, and they would stay changed if we die), the
+/// command runs in its own mount namespace where another config is mounted over the real one.
+/// Nobody else sees it, and it is gone with the command.
+struct ConfigOverride {
+    path: PathBuf,
+}
+
+impl ConfigOverride {
+    fn new(config: &TimeshiftConfig) -> Result<Self> {
+        let dir = Path::new(mount::MOUNT_ROOT);
+        fs::create_dir_all(dir).with_context(|| format!("Couldn't create {}", dir.display()))?;
+        let path = dir.join(format!("timeshift-{}.json", std::process::id()));
+        fs::write(&path, config.to_json())
+            .with_context(|| format!("Couldn't write {}", path.display()))?;
+        Ok(ConfigOverride { path })
+    }
+
+    /// `program` run by `unshare` once the config is bind mounted. It is exec'd, so it gets the
+    /// signals sent to the child when the operation is cancelled.
+    fn command(&self, program: &str) -> Command {
+        let mut command = Command::new("unshare");
+        command
+            .args(["--mount", "--propagation", "private", "--", "sh", "-c"])
+            .arg(r#"mount --bind "$1" "$2" && shift 2 && exec "$@""#)
+            .arg("sh")
+            .arg(&self.path)
+            .arg(config::CONFIG_PATH)
+            .arg(program);
+        command
+    }
+}
+
+impl Drop for ConfigOverride {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Runs the command while sending everything it prints to the context, instead of waiting for
+/// `.output()`, so the UI can show what is going on during a long rsync.
+fn run_streaming(command: &mut Command, context: &OperationContext) -> Result<()> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute {program} command"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| operation::forward_lines(out, context.events.clone()));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| operation::forward_lines(err, context.events.clone()));
+    let status = wait_or_cancel(&mut child, context)?;
+    let stdout = stdout.and_then(|t| t.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+    check_status(status, &stdout, &stderr)
+}
+
+/// Waits for timeshift, stopping it if the user cancels : SIGINT first so timeshift can clean up
+/// after itself, then SIGKILL if it is still running after `CANCEL_TIMEOUT`.
+fn wait_or_cancel(child: &mut Child, context: &OperationContext) -> Result<ExitStatus> {
+    let poll = Duration::from_millis(100);
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for timeshift")? {
+            return Ok(status);
+        }
+        if context.is_cancelled() {
+            break;
+        }
+        thread::sleep(poll);
+    }
+
+    context.send_line("Cancelling : sending SIGINT to timeshift");
+    // SAFETY: kill only sends a signal, and the pid is the one of our child which has not been
+    // reaped yet, so it can't be reused by another process
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+    let deadline = Instant::now() + CANCEL_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Some(status) = child.try_wait().context("Failed to wait for timeshift")? {
+            return Err(Cancelled {
+                details: format!("timeshift stopped after SIGINT ({status})"),
+            }
+            .into());
+        }
+        thread::sleep(poll);
+    }
+
+    context.send_line("Timeshift is still running, sending SIGKILL");
+    child.kill().context("Failed to kill timeshift")?;
+    child.wait().context("Failed to wait for timeshift")?;
+    Err(Cancelled {
+        details: format!(
+            "timeshift did not stop {} seconds after SIGINT, it was killed",
+            CANCEL_TIMEOUT.as_secs()
+        ),
+    }
+    .into())
+}
+
+/// Turns a failed run of timeshift into a `CommandError`. Timeshift prints most of its errors on
+/// stdout, so we take stdout when stderr is empty.
+fn check_status(status: ExitStatus, stdout: &str, stderr: &str) -> Result<()> {
+    if status.success() {
+        return Ok(());
+    }
+    let text = if stderr.trim().is_empty() {
+        stdout
+    } else {
+        stderr
+    };
+    Err(CommandError {
+        code: status.code(),
+        output: text.trim().to_string(),
+    }
+    .into())
+}
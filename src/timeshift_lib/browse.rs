@@ -0,0 +1,159 @@
+use super::mount::Mount;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::fs::{self, File, Metadata};
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Files bigger than this are not previewed.
+pub const PREVIEW_LIMIT: u64 = 64 * 1024;
+
+/// The folder of a snapshot, kept mounted as long as it is browsed.
+#[derive(Debug)]
+pub struct SnapshotTree {
+    pub root: PathBuf,
+    /// Unmounted on drop when we mounted it ourselves
+    _mount: Option<Mount>,
+}
+
+impl SnapshotTree {
+    pub fn new(root: PathBuf, mount: Option<Mount>) -> Self {
+        SnapshotTree {
+            root,
+            _mount: mount,
+        }
+    }
+
+    /// `relative` is a path inside the snapshot, `..` can't get out of it.
+    pub fn path(&self, relative: &Path) -> PathBuf {
+        let mut path = self.root.clone();
+        for component in relative.components() {
+            if let std::path::Component::Normal(name) = component {
+                path.push(name);
+            }
+        }
+        path
+    }
+
+    /// Where the `/` of the saved system is : `localhost` for rsync snapshots, the `@`
+    /// subvolume in btrfs mode.
+    pub fn system_root(&self) -> PathBuf {
+        let localhost = self.path(Path::new("localhost"));
+        if localhost.is_dir() {
+            localhost
+        } else {
+            self.path(Path::new("@"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileKind {
+    Directory,
+    File,
+    /// Never followed : an absolute link would point in the running system, not in the snapshot
+    Symlink(PathBuf),
+    /// Devices, sockets, fifos...
+    Other,
+}
+
+/// A line of the file browser.
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub name: String,
+    pub kind: FileKind,
+    pub size: u64,
+    pub modified: Option<DateTime<Local>>,
+    pub mode: u32,
+}
+
+impl FileEntry {
+    fn new(path: &Path, metadata: &Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            FileKind::Symlink(fs::read_link(path).unwrap_or_default())
+        } else if file_type.is_dir() {
+            FileKind::Directory
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        };
+        FileEntry {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            kind,
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(DateTime::<Local>::from),
+            mode: metadata.permissions().mode(),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Directory
+    }
+
+    /// Like `ls -l` : `drwxr-xr-x`.
+    pub fn permissions(&self) -> String {
+        let kind = match self.kind {
+            FileKind::Directory => 'd',
+            FileKind::Symlink(_) => 'l',
+            FileKind::File => '-',
+            FileKind::Other => '?',
+        };
+        let mut permissions = String::from(kind);
+        for shift in [6, 3, 0] {
+            let bits = (self.mode >> shift) & 0o7;
+            permissions.push(if bits & 4 != 0 { 'r' } else { '-' });
+            permissions.push(if bits & 2 != 0 { 'w' } else { '-' });
+            permissions.push(if bits & 1 != 0 { 'x' } else { '-' });
+        }
+        permissions
+    }
+}
+
+/// The content of a folder, folders first and then by name. The entries that can't be read are
+/// skipped.
+pub fn read_dir(dir: &Path) -> Result<Vec<FileEntry>> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Couldn't read {}", dir.display()))?;
+    let mut files: Vec<FileEntry> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = fs::symlink_metadata(entry.path()).ok()?;
+            Some(FileEntry::new(&entry.path(), &metadata))
+        })
+        .collect();
+    files.sort_by(|a, b| b.is_dir().cmp(&a.is_dir()).then(a.name.cmp(&b.name)));
+    Ok(files)
+}
+
+/// What the preview pane shows for a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preview {
+    Text(String),
+    Binary,
+    TooBig(u64),
+}
+
+/// Reads the file if it is small and looks like text.
+pub fn preview(path: &Path) -> Result<Preview> {
+    let metadata =
+        fs::symlink_metadata(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    if metadata.len() > PREVIEW_LIMIT {
+        return Ok(Preview::TooBig(metadata.len()));
+    }
+    let mut content = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(PREVIEW_LIMIT).read_to_end(&mut content))
+        .with_context(|| format!("Couldn't read {}", path.display()))?;
+    if content.contains(&0) {
+        return Ok(Preview::Binary);
+    }
+    match String::from_utf8(content) {
+        Ok(text) => Ok(Preview::Text(text)),
+        Err(_) => Ok(Preview::Binary),
+    }
+}
@@ -0,0 +1,60 @@
+use super::parser;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// A subvolume saved in a btrfs snapshot (`@`, `@home`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subvolume {
+    pub name: String,
+    /// Only known when quotas are enabled on the filesystem
+    pub usage: Option<QgroupUsage>,
+}
+
+/// The sizes btrfs keeps for a subvolume when quotas are on. `exclusive` is what deleting the
+/// subvolume would free, `referenced` everything it can see, shared data included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QgroupUsage {
+    pub referenced: u64,
+    pub exclusive: u64,
+}
+
+/// The subvolumes in the folder of a btrfs snapshot : timeshift puts `@` in there, and `@home`
+/// when it is included.
+pub fn subvolumes(snapshot_dir: &Path) -> Result<Vec<Subvolume>> {
+    let entries = fs::read_dir(snapshot_dir)
+        .with_context(|| format!("Couldn't read {}", snapshot_dir.display()))?;
+    let mut subvolumes: Vec<Subvolume> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with('@').then(|| Subvolume {
+                usage: qgroup_usage(&entry.path()),
+                name,
+            })
+        })
+        .collect();
+    subvolumes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(subvolumes)
+}
+
+/// `btrfs qgroup show` fails when quotas are disabled, we simply have no sizes then.
+fn qgroup_usage(subvolume: &Path) -> Option<QgroupUsage> {
+    let output = Command::new("btrfs")
+        .arg("qgroup")
+        .arg("show")
+        .arg("--raw")
+        .arg("-f")
+        .arg(subvolume)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parser::parse_qgroups(&String::from_utf8_lossy(&output.stdout))
+        .items
+        .into_iter()
+        .next()
+}
@@ -0,0 +1,370 @@
+use super::config::Filter;
+use super::operation::{CancelHandle, Cancelled};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// What timeshift always leaves out of the rsync snapshots, on top of the exclude list of the
+/// settings. Comparing them with the running system would only be noise.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "/dev/*",
+    "/proc/*",
+    "/sys/*",
+    "/media/*",
+    "/mnt/*",
+    "/tmp/*",
+    "/run/*",
+    "/var/run/*",
+    "/var/lock/*",
+    "/var/lib/dhcpcd/*",
+    "/var/lib/docker/*",
+    "/var/lib/schroot/*",
+    "/lost+found",
+    "/timeshift/*",
+    "/timeshift-btrfs/*",
+    "/swapfile",
+    "/snap/*",
+    "/root/.cache",
+    "/root/.thumbnails",
+    "/root/.local/share/Trash",
+    "/home/*/.cache",
+    "/home/*/.thumbnails",
+    "/home/*/.local/share/Trash",
+];
+
+/// The progress is sent every this many files.
+const PROGRESS_EVERY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A file that isn't the same on both sides. Folders are not listed, only what they contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// From the root of the system, without the leading `/`
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+impl Change {
+    pub fn delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+
+    /// The top-level folder the change is counted in, `/` for the files at the root.
+    pub fn group(&self) -> String {
+        let mut components = self.path.components();
+        let first = components.next();
+        match first {
+            Some(first) if components.next().is_some() => {
+                format!("/{}", first.as_os_str().to_string_lossy())
+            }
+            _ => "/".to_string(),
+        }
+    }
+}
+
+/// The changes of a top-level folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeGroup {
+    pub name: String,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    /// Bytes gained (or lost) from the old side to the new one
+    pub delta: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChangeReport {
+    /// Sorted by path
+    pub changes: Vec<Change>,
+    /// Sorted by name
+    pub groups: Vec<ChangeGroup>,
+    /// Files looked at on both sides
+    pub scanned: usize,
+    /// Folders that couldn't be read, their content isn't compared
+    pub unreadable: Vec<PathBuf>,
+}
+
+impl ChangeReport {
+    pub fn changes_in<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a Change> + 'a {
+        self.changes
+            .iter()
+            .filter(move |change| change.group() == group)
+    }
+
+    pub fn total(&self) -> ChangeGroup {
+        self.groups
+            .iter()
+            .fold(ChangeGroup::default(), |total, group| ChangeGroup {
+                name: String::new(),
+                added: total.added + group.added,
+                removed: total.removed + group.removed,
+                modified: total.modified + group.modified,
+                delta: total.delta + group.delta,
+            })
+    }
+}
+
+/// Walks two systems side by side, `old` being the snapshot and `new` the other snapshot or the
+/// running system. Files are modified when their size, date or link target changed, like the
+/// quick check of rsync. `on_progress` gets the number of files scanned and where we are.
+pub fn compare_trees(
+    old: &Path,
+    new: &Path,
+    filters: &[Filter],
+    cancel: &CancelHandle,
+    on_progress: &mut dyn FnMut(usize, &Path),
+) -> Result<ChangeReport> {
+    let mut walk = Walk {
+        filters,
+        cancel,
+        on_progress,
+        report: ChangeReport::default(),
+    };
+    walk.dir(Some(old), Some(new), Path::new(""))?;
+    let mut report = walk.report;
+    report.changes.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut groups: BTreeMap<String, ChangeGroup> = BTreeMap::new();
+    for change in &report.changes {
+        let name = change.group();
+        let group = groups.entry(name.clone()).or_insert_with(|| ChangeGroup {
+            name,
+            ..Default::default()
+        });
+        match change.kind {
+            ChangeKind::Added => group.added += 1,
+            ChangeKind::Removed => group.removed += 1,
+            ChangeKind::Modified => group.modified += 1,
+        }
+        group.delta += change.delta();
+    }
+    report.groups = groups.into_values().collect();
+    Ok(report)
+}
+
+struct Walk<'a> {
+    filters: &'a [Filter],
+    cancel: &'a CancelHandle,
+    on_progress: &'a mut dyn FnMut(usize, &Path),
+    report: ChangeReport,
+}
+
+impl Walk<'_> {
+    /// Compares a folder that exists on one side at least.
+    fn dir(&mut self, old: Option<&Path>, new: Option<&Path>, relative: &Path) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(Cancelled {
+                details: format!("stopped after {} files", self.report.scanned),
+            }
+            .into());
+        }
+        let old_entries = old
+            .map(|dir| self.entries(dir, relative))
+            .unwrap_or_default();
+        let new_entries = new
+            .map(|dir| self.entries(dir, relative))
+            .unwrap_or_default();
+        let names: BTreeSet<&OsString> = old_entries.keys().chain(new_entries.keys()).collect();
+        for name in names {
+            let path = relative.join(name);
+            let old_metadata = old_entries.get(name);
+            let new_metadata = new_entries.get(name);
+            let is_dir = old_metadata.or(new_metadata).is_some_and(|m| m.is_dir());
+            if self.excluded(&path, is_dir) {
+                continue;
+            }
+            self.report.scanned += 1;
+            if self.report.scanned.is_multiple_of(PROGRESS_EVERY) {
+                (self.on_progress)(self.report.scanned, &path);
+            }
+            let old_path = old.map(|dir| dir.join(name));
+            let new_path = new.map(|dir| dir.join(name));
+            match (old_metadata, new_metadata) {
+                (Some(o), Some(n)) if o.is_dir() && n.is_dir() => {
+                    self.dir(old_path.as_deref(), new_path.as_deref(), &path)?;
+                }
+                (Some(o), Some(n)) if !o.is_dir() && !n.is_dir() => {
+                    if !same_file(o, n, old_path.as_deref(), new_path.as_deref()) {
+                        self.push(path, ChangeKind::Modified, o.len(), n.len());
+                    }
+                }
+                _ => {
+                    // A folder replaced by a file or the other way round is both removed and
+                    // added
+                    if let Some(o) = old_metadata {
+                        self.removed(o, old_path.as_deref(), &path)?;
+                    }
+                    if let Some(n) = new_metadata {
+                        self.added(n, new_path.as_deref(), &path)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn removed(&mut self, metadata: &Metadata, path: Option<&Path>, relative: &Path) -> Result<()> {
+        if metadata.is_dir() {
+            self.dir(path, None, relative)
+        } else {
+            self.push(
+                relative.to_path_buf(),
+                ChangeKind::Removed,
+                metadata.len(),
+                0,
+            );
+            Ok(())
+        }
+    }
+
+    fn added(&mut self, metadata: &Metadata, path: Option<&Path>, relative: &Path) -> Result<()> {
+        if metadata.is_dir() {
+            self.dir(None, path, relative)
+        } else {
+            self.push(relative.to_path_buf(), ChangeKind::Added, 0, metadata.len());
+            Ok(())
+        }
+    }
+
+    fn push(&mut self, path: PathBuf, kind: ChangeKind, old_size: u64, new_size: u64) {
+        self.report.changes.push(Change {
+            path,
+            kind,
+            old_size,
+            new_size,
+        });
+    }
+
+    /// The content of a folder, links not followed. A folder we can't read is noted and
+    /// compared as empty.
+    fn entries(&mut self, dir: &Path, relative: &Path) -> BTreeMap<OsString, Metadata> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            self.report.unreadable.push(relative.to_path_buf());
+            return BTreeMap::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Some((entry.file_name(), entry.metadata().ok()?)))
+            .collect()
+    }
+
+    /// The first rule that matches decides, like rsync.
+    fn excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = format!("/{}", relative.display());
+        let rules = self.filters.iter().map(|filter| match filter {
+            Filter::Include(pattern) => (pattern.as_str(), false),
+            Filter::Exclude(pattern) => (pattern.as_str(), true),
+        });
+        let defaults = DEFAULT_EXCLUDES.iter().map(|pattern| (*pattern, true));
+        rules
+            .chain(defaults)
+            .find(|(pattern, _)| matches(pattern, &path, is_dir))
+            .is_some_and(|(_, exclude)| exclude)
+    }
+}
+
+/// The quick check of rsync : same type, size and date, to the second. Links are the same when
+/// they point to the same place.
+pub fn same_file(
+    old: &Metadata,
+    new: &Metadata,
+    old_path: Option<&Path>,
+    new_path: Option<&Path>,
+) -> bool {
+    if old.file_type().is_symlink() || new.file_type().is_symlink() {
+        let target = |path: Option<&Path>| path.and_then(|path| fs::read_link(path).ok());
+        return old.file_type().is_symlink()
+            && new.file_type().is_symlink()
+            && target(old_path) == target(new_path);
+    }
+    let seconds = |metadata: &Metadata| {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|date| date.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+    };
+    old.file_type() == new.file_type() && old.len() == new.len() && seconds(old) == seconds(new)
+}
+
+/// Matches a path like rsync matches its filter rules : `*` stays in a folder, `**` doesn't, a
+/// pattern starting with `/` starts at the root and the others match the end of the path. A
+/// trailing `/` only matches folders.
+fn matches(pattern: &str, path: &str, is_dir: bool) -> bool {
+    let (pattern, dir_only) = match pattern.strip_suffix('/') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    if dir_only && !is_dir {
+        return false;
+    }
+    let path = path.trim_start_matches('/');
+    if let Some(anchored) = pattern.strip_prefix('/') {
+        return glob(anchored.as_bytes(), path.as_bytes());
+    }
+    std::iter::once(0)
+        .chain(path.match_indices('/').map(|(i, _)| i + 1))
+        .any(|start| glob(pattern.as_bytes(), &path.as_bytes()[start..]))
+}
+
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob(rest, &text[i..])),
+        [b'?', rest @ ..] => matches!(text, [c, tail @ ..] if *c != b'/' && glob(rest, tail)),
+        [c, rest @ ..] => matches!(text, [t, tail @ ..] if t == c && glob(rest, tail)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_stays_in_a_folder() {
+        assert!(glob(b"*.conf", b"pacman.conf"));
+        assert!(!glob(b"*.conf", b"pacman.d/mirrors.conf"));
+        assert!(glob(b"root/**", b"root/.cache/thing"));
+        assert!(glob(b"home/*/**", b"home/user/file"));
+        assert!(!glob(b"home/*/**", b"home/user"));
+        assert!(glob(b"fil?", b"file"));
+        assert!(!glob(b"a?b", b"a/b"));
+        assert!(!glob(b"file", b"files"));
+    }
+
+    #[test]
+    fn patterns_match_like_rsync() {
+        // Anchored at the root
+        assert!(matches("/root/**", "/root/.bashrc", false));
+        assert!(!matches("/root/**", "/home/root/.bashrc", false));
+        // The others match the end of the path
+        assert!(matches("*.log", "/var/log/pacman.log", false));
+        assert!(matches("cache", "/home/user/cache", true));
+        assert!(!matches("cache", "/home/user/mycache", true));
+        // A trailing slash only matches folders
+        assert!(matches(
+            "node_modules/",
+            "/home/user/app/node_modules",
+            true
+        ));
+        assert!(!matches(
+            "node_modules/",
+            "/home/user/app/node_modules",
+            false
+        ));
+    }
+}
@@ -0,0 +1,503 @@
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Where timeshift keeps its settings.
+pub const CONFIG_PATH: &str = "/etc/timeshift/timeshift.json";
+
+/// Timeshift doesn't keep more snapshots than this for a level, neither does the GTK app.
+pub const MAX_COUNT: u32 = 999;
+
+/// A line of the `exclude` list of timeshift. The paths to include are in the same list, with a
+/// `+ ` in front of them, and the order matters (the first rule matching a path wins).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Include(String),
+    Exclude(String),
+}
+
+impl Filter {
+    /// Reads a rule written like in `timeshift.json`.
+    pub fn parse(rule: &str) -> Self {
+        match rule.strip_prefix("+ ") {
+            Some(pattern) => Filter::Include(pattern.trim().to_string()),
+            None => Filter::Exclude(rule.trim().to_string()),
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        match self {
+            Filter::Include(pattern) | Filter::Exclude(pattern) => pattern,
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Include(pattern) => write!(f, "+ {pattern}"),
+            Filter::Exclude(pattern) => write!(f, "{pattern}"),
+        }
+    }
+}
+
+/// The content of `timeshift.json`. Timeshift writes every value as a string (`"true"`, `"5"`),
+/// we give them their real type here. The keys we don't know about are kept in `raw` and written
+/// back as they were.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeshiftConfig {
+    pub backup_device_uuid: String,
+    pub parent_device_uuid: String,
+    pub btrfs_mode: bool,
+    pub include_btrfs_home_for_backup: bool,
+    pub include_btrfs_home_for_restore: bool,
+    pub stop_cron_emails: bool,
+    pub schedule_monthly: bool,
+    pub schedule_weekly: bool,
+    pub schedule_daily: bool,
+    pub schedule_hourly: bool,
+    pub schedule_boot: bool,
+    pub count_monthly: u32,
+    pub count_weekly: u32,
+    pub count_daily: u32,
+    pub count_hourly: u32,
+    pub count_boot: u32,
+    pub filters: Vec<Filter>,
+    raw: Map<String, Value>,
+}
+
+/// The settings that can be edited from the Settings screen, in the order they are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigField {
+    BackupDeviceUuid,
+    BtrfsMode,
+    IncludeBtrfsHomeForBackup,
+    IncludeBtrfsHomeForRestore,
+    StopCronEmails,
+    ScheduleMonthly,
+    ScheduleWeekly,
+    ScheduleDaily,
+    ScheduleHourly,
+    ScheduleBoot,
+    CountMonthly,
+    CountWeekly,
+    CountDaily,
+    CountHourly,
+    CountBoot,
+}
+
+impl ConfigField {
+    pub const ALL: [ConfigField; 15] = [
+        ConfigField::BackupDeviceUuid,
+        ConfigField::BtrfsMode,
+        ConfigField::IncludeBtrfsHomeForBackup,
+        ConfigField::IncludeBtrfsHomeForRestore,
+        ConfigField::StopCronEmails,
+        ConfigField::ScheduleMonthly,
+        ConfigField::ScheduleWeekly,
+        ConfigField::ScheduleDaily,
+        ConfigField::ScheduleHourly,
+        ConfigField::ScheduleBoot,
+        ConfigField::CountMonthly,
+        ConfigField::CountWeekly,
+        ConfigField::CountDaily,
+        ConfigField::CountHourly,
+        ConfigField::CountBoot,
+    ];
+
+    /// The key of the field in `timeshift.json`.
+    pub fn key(self) -> &'static str {
+        match self {
+            ConfigField::BackupDeviceUuid => "backup_device_uuid",
+            ConfigField::BtrfsMode => "btrfs_mode",
+            ConfigField::IncludeBtrfsHomeForBackup => "include_btrfs_home_for_backup",
+            ConfigField::IncludeBtrfsHomeForRestore => "include_btrfs_home_for_restore",
+            ConfigField::StopCronEmails => "stop_cron_emails",
+            ConfigField::ScheduleMonthly => "schedule_monthly",
+            ConfigField::ScheduleWeekly => "schedule_weekly",
+            ConfigField::ScheduleDaily => "schedule_daily",
+            ConfigField::ScheduleHourly => "schedule_hourly",
+            ConfigField::ScheduleBoot => "schedule_boot",
+            ConfigField::CountMonthly => "count_monthly",
+            ConfigField::CountWeekly => "count_weekly",
+            ConfigField::CountDaily => "count_daily",
+            ConfigField::CountHourly => "count_hourly",
+            ConfigField::CountBoot => "count_boot",
+        }
+    }
+}
+
+impl TimeshiftConfig {
+    pub fn parse(json: &str) -> Result<Self> {
+        let raw: Map<String, Value> =
+            serde_json::from_str(json).context("Invalid timeshift.json")?;
+        let mut config = TimeshiftConfig {
+            backup_device_uuid: text(&raw, "backup_device_uuid")?,
+            parent_device_uuid: text(&raw, "parent_device_uuid")?,
+            filters: Vec::new(),
+            ..Default::default()
+        };
+        for field in ConfigField::ALL {
+            if let Some(flag) = config.flag_mut(field) {
+                *flag = flag_value(&raw, field.key())?;
+            } else if let Some(count) = config.count_mut(field) {
+                *count = count_value(&raw, field.key())?;
+            }
+        }
+        if let Some(rules) = raw.get("exclude") {
+            let Some(rules) = rules.as_array() else {
+                bail!("exclude should be a list");
+            };
+            config.filters = rules
+                .iter()
+                .filter_map(Value::as_str)
+                .map(Filter::parse)
+                .collect();
+        }
+        config.raw = raw;
+        Ok(config)
+    }
+
+    /// Back to the format of timeshift, strings everywhere.
+    pub fn to_json(&self) -> String {
+        let mut raw = self.raw.clone();
+        raw.insert(
+            "backup_device_uuid".to_string(),
+            Value::String(self.backup_device_uuid.clone()),
+        );
+        raw.insert(
+            "parent_device_uuid".to_string(),
+            Value::String(self.parent_device_uuid.clone()),
+        );
+        for field in ConfigField::ALL {
+            raw.insert(field.key().to_string(), Value::String(self.value(field)));
+        }
+        let rules = self
+            .filters
+            .iter()
+            .map(|filter| Value::String(filter.to_string()))
+            .collect();
+        raw.insert("exclude".to_string(), Value::Array(rules));
+        // to_string_pretty cannot fail on a map of strings
+        serde_json::to_string_pretty(&raw).unwrap_or_default()
+    }
+
+    pub fn flag_mut(&mut self, field: ConfigField) -> Option<&mut bool> {
+        match field {
+            ConfigField::BtrfsMode => Some(&mut self.btrfs_mode),
+            ConfigField::IncludeBtrfsHomeForBackup => Some(&mut self.include_btrfs_home_for_backup),
+            ConfigField::IncludeBtrfsHomeForRestore => {
+                Some(&mut self.include_btrfs_home_for_restore)
+            }
+            ConfigField::StopCronEmails => Some(&mut self.stop_cron_emails),
+            ConfigField::ScheduleMonthly => Some(&mut self.schedule_monthly),
+            ConfigField::ScheduleWeekly => Some(&mut self.schedule_weekly),
+            ConfigField::ScheduleDaily => Some(&mut self.schedule_daily),
+            ConfigField::ScheduleHourly => Some(&mut self.schedule_hourly),
+            ConfigField::ScheduleBoot => Some(&mut self.schedule_boot),
+            _ => None,
+        }
+    }
+
+    pub fn count_mut(&mut self, field: ConfigField) -> Option<&mut u32> {
+        match field {
+            ConfigField::CountMonthly => Some(&mut self.count_monthly),
+            ConfigField::CountWeekly => Some(&mut self.count_weekly),
+            ConfigField::CountDaily => Some(&mut self.count_daily),
+            ConfigField::CountHourly => Some(&mut self.count_hourly),
+            ConfigField::CountBoot => Some(&mut self.count_boot),
+            _ => None,
+        }
+    }
+
+    pub fn flag(&self, field: ConfigField) -> Option<bool> {
+        match field {
+            ConfigField::BtrfsMode => Some(self.btrfs_mode),
+            ConfigField::IncludeBtrfsHomeForBackup => Some(self.include_btrfs_home_for_backup),
+            ConfigField::IncludeBtrfsHomeForRestore => Some(self.include_btrfs_home_for_restore),
+            ConfigField::StopCronEmails => Some(self.stop_cron_emails),
+            ConfigField::ScheduleMonthly => Some(self.schedule_monthly),
+            ConfigField::ScheduleWeekly => Some(self.schedule_weekly),
+            ConfigField::ScheduleDaily => Some(self.schedule_daily),
+            ConfigField::ScheduleHourly => Some(self.schedule_hourly),
+            ConfigField::ScheduleBoot => Some(self.schedule_boot),
+            _ => None,
+        }
+    }
+
+    pub fn count(&self, field: ConfigField) -> Option<u32> {
+        match field {
+            ConfigField::CountMonthly => Some(self.count_monthly),
+            ConfigField::CountWeekly => Some(self.count_weekly),
+            ConfigField::CountDaily => Some(self.count_daily),
+            ConfigField::CountHourly => Some(self.count_hourly),
+            ConfigField::CountBoot => Some(self.count_boot),
+            _ => None,
+        }
+    }
+
+    /// The value of the field as it is written in the file.
+    pub fn value(&self, field: ConfigField) -> String {
+        if let ConfigField::BackupDeviceUuid = field {
+            self.backup_device_uuid.clone()
+        } else if let Some(flag) = self.flag(field) {
+            flag.to_string()
+        } else {
+            self.count(field).unwrap_or_default().to_string()
+        }
+    }
+
+    /// Sets a field from what the user typed, refusing values timeshift wouldn't understand.
+    pub fn set(&mut self, field: ConfigField, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        if let ConfigField::BackupDeviceUuid = field {
+            check_uuid(value)?;
+            self.backup_device_uuid = value.to_string();
+        } else if let Some(flag) = self.flag_mut(field) {
+            *flag = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("{} must be true or false", field.key())),
+            };
+        } else if let Some(count) = self.count_mut(field) {
+            *count = value
+                .parse::<u32>()
+                .ok()
+                .filter(|count| *count <= MAX_COUNT)
+                .ok_or_else(|| format!("{} must be a number up to {MAX_COUNT}", field.key()))?;
+        }
+        Ok(())
+    }
+
+    /// Everything that would make timeshift unhappy, empty when the config can be saved.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.backup_device_uuid.is_empty() {
+            errors.push("backup_device_uuid is empty, timeshift needs a backup device".to_string());
+        } else if let Err(error) = check_uuid(&self.backup_device_uuid) {
+            errors.push(error);
+        }
+        let levels = [
+            (self.schedule_monthly, self.count_monthly, "monthly"),
+            (self.schedule_weekly, self.count_weekly, "weekly"),
+            (self.schedule_daily, self.count_daily, "daily"),
+            (self.schedule_hourly, self.count_hourly, "hourly"),
+            (self.schedule_boot, self.count_boot, "boot"),
+        ];
+        for (enabled, count, level) in levels {
+            if enabled && count == 0 {
+                errors.push(format!(
+                    "count_{level} must be at least 1 when schedule_{level} is on"
+                ));
+            }
+            if count > MAX_COUNT {
+                errors.push(format!("count_{level} must be at most {MAX_COUNT}"));
+            }
+        }
+        for (i, filter) in self.filters.iter().enumerate() {
+            if filter.pattern().is_empty() {
+                errors.push(format!("filter {} is empty", i + 1));
+            }
+        }
+        errors
+    }
+
+    /// Writes the config to `path` without ever leaving a half written file : the new content
+    /// goes to a temporary file next to it, with the permissions of the old one, the previous
+    /// file is copied to `<path>.bak`, and the temporary file is renamed over the old one.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let errors = self.validate();
+        if !errors.is_empty() {
+            bail!("Invalid settings: {}", errors.join(", "));
+        }
+        let temporary = path.with_extension("json.tmp");
+        let mut file = File::create(&temporary)
+            .with_context(|| format!("Couldn't create {}", temporary.display()))?;
+        file.write_all(self.to_json().as_bytes())
+            .and_then(|()| file.sync_all())
+            .with_context(|| format!("Couldn't write {}", temporary.display()))?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temporary, metadata.permissions()).with_context(|| {
+                format!("Couldn't set the permissions of {}", temporary.display())
+            })?;
+        }
+        if path.exists() {
+            let backup = path.with_extension("json.bak");
+            fs::copy(path, &backup).with_context(|| {
+                format!("Couldn't back up the settings to {}", backup.display())
+            })?;
+        }
+        fs::rename(&temporary, path).with_context(|| format!("Couldn't replace {}", path.display()))
+    }
+}
+
+fn text(raw: &Map<String, Value>, key: &str) -> Result<String> {
+    match raw.get(key) {
+        None => Ok(String::new()),
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(value) => bail!("{key} should be a string, not {value}"),
+    }
+}
+
+fn flag_value(raw: &Map<String, Value>, key: &str) -> Result<bool> {
+    match raw.get(key) {
+        None => Ok(false),
+        Some(Value::Bool(value)) => Ok(*value),
+        Some(Value::String(value)) if value == "true" => Ok(true),
+        Some(Value::String(value)) if value == "false" => Ok(false),
+        Some(value) => bail!("{key} should be true or false, not {value}"),
+    }
+}
+
+fn count_value(raw: &Map<String, Value>, key: &str) -> Result<u32> {
+    let count = match raw.get(key) {
+        None => return Ok(0),
+        Some(Value::String(value)) => value.parse::<u32>().ok(),
+        Some(Value::Number(value)) => value.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Some(_) => None,
+    };
+    count.with_context(|| format!("{key} should be a number"))
+}
+
+/// UUIDs of filesystems are hexadecimal with dashes, for ext4 as for FAT (`ABCD-1234`).
+/// The UUIDs of the Linux file systems, like `6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5e`.
+fn check_uuid(uuid: &str) -> Result<(), String> {
+    let groups: Vec<&str> = uuid.split('-').collect();
+    let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+    if lengths == [8, 4, 4, 4, 12]
+        && groups
+            .iter()
+            .all(|group| group.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        Ok(())
+    } else {
+        Err(format!("{uuid:?} is not a valid UUID"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+  "backup_device_uuid" : "6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5e",
+  "parent_device_uuid" : "",
+  "do_first_run" : "false",
+  "btrfs_mode" : "false",
+  "include_btrfs_home_for_backup" : "false",
+  "include_btrfs_home_for_restore" : "false",
+  "stop_cron_emails" : "true",
+  "schedule_monthly" : "false",
+  "schedule_weekly" : "true",
+  "schedule_daily" : "true",
+  "schedule_hourly" : "false",
+  "schedule_boot" : "false",
+  "count_monthly" : "2",
+  "count_weekly" : "3",
+  "count_daily" : "5",
+  "count_hourly" : "6",
+  "count_boot" : "5",
+  "date_format" : "%Y-%m-%d %H:%M:%S",
+  "exclude" : [
+    "+ /root/.bashrc",
+    "/root/**"
+  ],
+  "exclude-apps" : []
+}"#;
+
+    #[test]
+    fn parse_gives_real_types() {
+        let config = TimeshiftConfig::parse(CONFIG).unwrap();
+        assert!(config.schedule_weekly && config.schedule_daily && !config.schedule_boot);
+        assert!(config.stop_cron_emails);
+        assert_eq!(config.count_daily, 5);
+        assert_eq!(
+            config.filters,
+            vec![
+                Filter::Include("/root/.bashrc".to_string()),
+                Filter::Exclude("/root/**".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip_keeps_unknown_keys_and_strings() {
+        let mut config = TimeshiftConfig::parse(CONFIG).unwrap();
+        config.count_daily = 7;
+        config.schedule_hourly = true;
+        let json = config.to_json();
+        let raw: Map<String, Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(raw["count_daily"], "7");
+        assert_eq!(raw["schedule_hourly"], "true");
+        assert_eq!(raw["date_format"], "%Y-%m-%d %H:%M:%S");
+        assert_eq!(raw["exclude-apps"], Value::Array(Vec::new()));
+        assert_eq!(TimeshiftConfig::parse(&json).unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn unchanged_config_is_written_back_as_it_was() {
+        let config = TimeshiftConfig::parse(CONFIG).unwrap();
+        let before: Value = serde_json::from_str(CONFIG).unwrap();
+        let after: Value = serde_json::from_str(&config.to_json()).unwrap();
+        assert_eq!(before, after);
+        // Timeshift's order, not the alphabetical one
+        let mut changed = config.clone();
+        changed.count_daily = 9;
+        let json = changed.to_json();
+        let positions: Vec<usize> = ["backup_device_uuid", "parent_device_uuid", "do_first_run"]
+            .iter()
+            .map(|key| json.find(&format!("\"{key}\"")).unwrap())
+            .collect();
+        assert!(positions.is_sorted(), "{json}");
+    }
+
+    #[test]
+    fn uuid_must_look_like_one() {
+        let mut config = TimeshiftConfig::parse(CONFIG).unwrap();
+        for uuid in [
+            "",
+            "6f1d2c3b",
+            "----",
+            "6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5",
+            "6f1d2c3b8a4e-4b5f-9d7c-2e1a0b3c4d5e",
+            "6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5g",
+        ] {
+            assert!(
+                config.set(ConfigField::BackupDeviceUuid, uuid).is_err(),
+                "{uuid}"
+            );
+        }
+        let uuid = "A1B2C3D4-0000-4000-8000-000000000001";
+        assert_eq!(config.set(ConfigField::BackupDeviceUuid, uuid), Ok(()));
+        assert_eq!(config.backup_device_uuid, uuid);
+    }
+
+    #[test]
+    fn save_keeps_the_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("timeshift-tui-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timeshift.json");
+        fs::write(&path, CONFIG).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let mut config = TimeshiftConfig::parse(CONFIG).unwrap();
+        config.count_daily = 9;
+        config.save(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(
+            TimeshiftConfig::parse(&fs::read_to_string(&path).unwrap())
+                .unwrap()
+                .count_daily,
+            9
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("timeshift.json.bak")).unwrap(),
+            CONFIG
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::Duration;
+
+/// Files bigger than this are compared by size and hash only.
+pub const DIFF_LIMIT: u64 = 2 * 1024 * 1024;
+
+/// Lines kept around each change, like `diff -u`.
+const CONTEXT_LINES: usize = 3;
+
+/// The diff runs on the UI thread. Two files that have nothing in common can take the diff
+/// algorithm a long time, after this it gives a bigger diff than needed instead.
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// What is known about a file that can't be diffed line by line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDigest {
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl FileDigest {
+    fn of_bytes(bytes: &[u8]) -> Self {
+        FileDigest {
+            size: bytes.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(bytes)),
+        }
+    }
+
+    /// Reads the file by chunks, it can be big.
+    fn of_file(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+        let mut hasher = Sha256::new();
+        let size = io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        Ok(FileDigest {
+            size,
+            sha256: format!("{:x}", hasher.finalize()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// `@@ -1,4 +1,5 @@`
+    Hunk(String),
+    Context(String),
+    Added(String),
+    Removed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileDiff {
+    Identical,
+    /// A unified diff, without the `---`/`+++` header
+    Text(Vec<DiffLine>),
+    /// `None` on the side where the file doesn't exist
+    Binary {
+        old: Option<FileDigest>,
+        new: Option<FileDigest>,
+    },
+}
+
+impl FileDiff {
+    /// Lines added and removed.
+    pub fn counts(&self) -> (usize, usize) {
+        let FileDiff::Text(lines) = self else {
+            return (0, 0);
+        };
+        let added = lines
+            .iter()
+            .filter(|line| matches!(line, DiffLine::Added(_)))
+            .count();
+        let removed = lines
+            .iter()
+            .filter(|line| matches!(line, DiffLine::Removed(_)))
+            .count();
+        (added, removed)
+    }
+}
+
+/// A side of the comparison.
+enum Content {
+    Missing,
+    Text(String),
+    Binary(FileDigest),
+}
+
+impl Content {
+    fn digest(&self) -> Option<FileDigest> {
+        match self {
+            Content::Missing => None,
+            Content::Text(text) => Some(FileDigest::of_bytes(text.as_bytes())),
+            Content::Binary(digest) => Some(digest.clone()),
+        }
+    }
+}
+
+/// Compares two versions of a file. One of them may not exist, the file was then added or
+/// removed. Links are compared by where they point to, they are never followed.
+pub fn diff_files(old: &Path, new: &Path) -> Result<FileDiff> {
+    let diff = match (read(old)?, read(new)?) {
+        (Content::Missing, Content::Missing) => {
+            bail!("{} doesn't exist on either side", new.display())
+        }
+        (Content::Text(old), Content::Text(new)) => unified(&old, &new),
+        (Content::Text(old), Content::Missing) => unified(&old, ""),
+        (Content::Missing, Content::Text(new)) => unified("", &new),
+        (old, new) => {
+            let (old, new) = (old.digest(), new.digest());
+            if old == new {
+                FileDiff::Identical
+            } else {
+                FileDiff::Binary { old, new }
+            }
+        }
+    };
+    Ok(diff)
+}
+
+fn read(path: &Path) -> Result<Content> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Content::Missing),
+        Err(e) => return Err(e).with_context(|| format!("Couldn't read {}", path.display())),
+    };
+    if metadata.is_dir() {
+        bail!("{} is a folder", path.display());
+    }
+    if metadata.is_symlink() {
+        let target =
+            fs::read_link(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+        return Ok(Content::Text(format!("link to {}\n", target.display())));
+    }
+    if !metadata.is_file() {
+        bail!("{} is not a regular file", path.display());
+    }
+    if metadata.len() > DIFF_LIMIT {
+        return FileDigest::of_file(path).map(Content::Binary);
+    }
+    let bytes = fs::read(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    if bytes.contains(&0) {
+        return Ok(Content::Binary(FileDigest::of_bytes(&bytes)));
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(Content::Text(text)),
+        Err(e) => Ok(Content::Binary(FileDigest::of_bytes(e.as_bytes()))),
+    }
+}
+
+fn unified(old: &str, new: &str) -> FileDiff {
+    unified_within(old, new, DIFF_TIMEOUT)
+}
+
+fn unified_within(old: &str, new: &str, timeout: Duration) -> FileDiff {
+    if old == new {
+        return FileDiff::Identical;
+    }
+    let diff = TextDiff::configure().timeout(timeout).diff_lines(old, new);
+    let mut lines = Vec::new();
+    for hunk in diff
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .iter_hunks()
+    {
+        lines.push(DiffLine::Hunk(hunk.header().to_string()));
+        for change in hunk.iter_changes() {
+            let text = change.value().trim_end_matches(['\n', '\r']).to_string();
+            lines.push(match change.tag() {
+                ChangeTag::Equal => DiffLine::Context(text),
+                ChangeTag::Insert => DiffLine::Added(text),
+                ChangeTag::Delete => DiffLine::Removed(text),
+            });
+        }
+    }
+    FileDiff::Text(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_line_in_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\n";
+        let FileDiff::Text(lines) = unified(old, new) else {
+            panic!("expected a text diff");
+        };
+        assert_eq!(lines[0], DiffLine::Hunk("@@ -2,7 +2,7 @@".to_string()));
+        assert!(lines.contains(&DiffLine::Removed("e".to_string())));
+        assert!(lines.contains(&DiffLine::Added("E".to_string())));
+        assert_eq!(unified(old, new).counts(), (1, 1));
+        assert_eq!(unified(old, old), FileDiff::Identical);
+    }
+
+    #[test]
+    fn out_of_time_diff_is_bigger_but_right() {
+        let old: String = (0..2_000).map(|i| format!("line {i}\n")).collect();
+        let new: String = (0..2_000)
+            .map(|i| match i % 100 {
+                0 => format!("changed {i}\n"),
+                _ => format!("line {i}\n"),
+            })
+            .collect();
+        assert_eq!(unified(&old, &new).counts(), (20, 20));
+        // Without time, everything between the first and the last change is replaced, which is
+        // still a right diff
+        let late = unified_within(&old, &new, Duration::ZERO);
+        assert_eq!(late.counts(), (1901, 1901));
+        let FileDiff::Text(lines) = late else {
+            panic!("expected a text diff");
+        };
+        assert!(lines.contains(&DiffLine::Removed("line 1900".to_string())));
+        assert!(lines.contains(&DiffLine::Added("changed 1900".to_string())));
+        assert!(lines.contains(&DiffLine::Context("line 1901".to_string())));
+    }
+}
@@ -0,0 +1,170 @@
+use super::usage::{SnapshotSize, format_bytes};
+use super::{Device, Snapshot};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fmt;
+
+/// The formats the inventory can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Json,
+        ExportFormat::Csv,
+        ExportFormat::Markdown,
+    ];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    /// The name given on the command line, the extension also works.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "markdown" | "md" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Json => write!(f, "JSON"),
+            ExportFormat::Csv => write!(f, "CSV"),
+            ExportFormat::Markdown => write!(f, "Markdown"),
+        }
+    }
+}
+
+/// Every device with its snapshots, like `Timeshift::devices_map`, and the size of the
+/// snapshots that were measured.
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Inventory<'a> {
+    devices: Vec<DeviceInventory<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct DeviceInventory<'a> {
+    device: &'a Device,
+    snapshots: Vec<SnapshotEntry<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotEntry<'a> {
+    #[serde(flatten)]
+    snapshot: &'a Snapshot,
+    /// `None` when it wasn't measured (btrfs snapshots, or not done yet)
+    size: Option<SnapshotSize>,
+}
+
+const COLUMNS: [&str; 7] = [
+    "device",
+    "snapshot",
+    "date",
+    "tags",
+    "comment",
+    "size",
+    "unique size",
+];
+
+impl<'a> Inventory<'a> {
+    pub fn new(
+        devices_map: &'a IndexMap<Device, Vec<Snapshot>>,
+        size_of: impl Fn(&Device, &Snapshot) -> Option<SnapshotSize>,
+    ) -> Self {
+        let devices = devices_map
+            .iter()
+            .map(|(device, snapshots)| DeviceInventory {
+                device,
+                snapshots: snapshots
+                    .iter()
+                    .map(|snapshot| SnapshotEntry {
+                        snapshot,
+                        size: size_of(device, snapshot),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Inventory { devices }
+    }
+
+    pub fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Json => {
+                serde_json::to_string_pretty(self).expect("The inventory is always valid JSON")
+            }
+            // In bytes, for the spreadsheets
+            ExportFormat::Csv => {
+                let mut csv = COLUMNS.join(",") + "\n";
+                for row in self.rows(|bytes| bytes.to_string()) {
+                    let row: Vec<String> = row.iter().map(|cell| csv_cell(cell)).collect();
+                    csv += &row.join(",");
+                    csv += "\n";
+                }
+                csv
+            }
+            // For humans, the tickets are read not computed
+            ExportFormat::Markdown => {
+                let mut markdown = format!("| {} |\n", COLUMNS.join(" | "));
+                markdown += &format!("|{}\n", "---|".repeat(COLUMNS.len()));
+                for row in self.rows(format_bytes) {
+                    let row: Vec<String> = row
+                        .iter()
+                        .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+                        .collect();
+                    markdown += &format!("| {} |\n", row.join(" | "));
+                }
+                markdown
+            }
+        }
+    }
+
+    /// One row per snapshot for the flat formats, the sizes written with `bytes`.
+    fn rows(&self, bytes: impl Fn(u64) -> String) -> Vec<[String; 7]> {
+        let mut rows = Vec::new();
+        for device in &self.devices {
+            for entry in &device.snapshots {
+                let snapshot = entry.snapshot;
+                let tags: Vec<&str> = snapshot.tags.iter().map(|tag| tag.word()).collect();
+                rows.push([
+                    device.device.device_name.clone(),
+                    snapshot.name.clone(),
+                    snapshot.date.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    tags.join(" "),
+                    snapshot.description.clone(),
+                    entry
+                        .size
+                        .map(|size| bytes(size.apparent))
+                        .unwrap_or_default(),
+                    entry
+                        .size
+                        .map(|size| bytes(size.unique))
+                        .unwrap_or_default(),
+                ]);
+            }
+        }
+        rows
+    }
+}
+
+/// Quoted when needed, like spreadsheets expect.
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
@@ -0,0 +1,268 @@
+use super::browse::SnapshotTree;
+use super::compare::same_file;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the files replaced by a restore are moved, one folder per restore.
+pub const BACKUP_ROOT: &str = "/var/backups/timeshift-tui";
+
+/// Copying files over these would replace the running system, that is the job of a real
+/// restore, which excludes what must not be touched and reinstalls the bootloader.
+const SYSTEM_DESTINATIONS: [&str; 2] = ["/", "/boot"];
+
+/// The preview stops looking for overwritten files after this many files, a big folder would
+/// take too long.
+const PREVIEW_LIMIT: usize = 20_000;
+
+/// Where the restored files go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileDestination {
+    /// Back where they were on the system
+    Original,
+    /// Under another folder, keeping their path : `/etc/fstab` goes in `<folder>/etc/fstab`
+    Folder(PathBuf),
+}
+
+/// A file or folder to copy back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRestoreItem {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub is_dir: bool,
+    /// Something is already there. A file is replaced, a folder is merged.
+    pub exists: bool,
+    /// The live files rsync will overwrite, the ones that differ from the snapshot
+    pub overwritten: Vec<PathBuf>,
+}
+
+/// Everything a file restore will do, shown before it is confirmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRestorePlan {
+    pub items: Vec<FileRestoreItem>,
+    pub backup_dir: PathBuf,
+    /// The folders were too big to look for every overwritten file, there may be more
+    pub preview_incomplete: bool,
+}
+
+/// Where a file of the snapshot was on the system. Timeshift keeps the system in `localhost`
+/// for rsync snapshots, and in the `@` and `@home` subvolumes for btrfs ones. The other files
+/// (`info.json`...) belong to timeshift, they have no original path.
+pub fn original_path(relative: &Path) -> Option<PathBuf> {
+    let mut components = relative.components();
+    let root = match components.next()?.as_os_str().to_str()? {
+        "localhost" | "@" => PathBuf::from("/"),
+        "@home" => PathBuf::from("/home"),
+        _ => return None,
+    };
+    Some(root.join(components.as_path()))
+}
+
+impl FileRestorePlan {
+    /// `paths` are the files chosen in the snapshot, relative to it. `backup_name` names the
+    /// backup folder of this restore.
+    pub fn new(
+        tree: &SnapshotTree,
+        paths: &[PathBuf],
+        destination: &FileDestination,
+        backup_name: &str,
+    ) -> Result<Self, String> {
+        let mut items = Vec::new();
+        for path in paths {
+            let original = original_path(path);
+            let destination = match (destination, original) {
+                (FileDestination::Original, Some(original)) => original,
+                (FileDestination::Original, None) => {
+                    return Err(format!(
+                        "{} isn't a file of the system, it can only be restored in another folder",
+                        path.display()
+                    ));
+                }
+                (FileDestination::Folder(folder), original) => {
+                    let inside = original.unwrap_or_else(|| path.clone());
+                    folder.join(inside.strip_prefix("/").unwrap_or(&inside))
+                }
+            };
+            if SYSTEM_DESTINATIONS
+                .iter()
+                .any(|system| destination == Path::new(system))
+            {
+                return Err(format!(
+                    "Restoring {} would overwrite {} on the running system. Use the restore of \
+                     the whole snapshot (R on the snapshot list) for that.",
+                    path.display(),
+                    destination.display()
+                ));
+            }
+            let source = tree.path(path);
+            let is_dir = source.symlink_metadata().is_ok_and(|m| m.is_dir());
+            items.push(FileRestoreItem {
+                source,
+                exists: destination.symlink_metadata().is_ok(),
+                destination,
+                is_dir,
+                overwritten: Vec::new(),
+            });
+        }
+        let mut preview = Preview {
+            scanned: 0,
+            incomplete: false,
+        };
+        for item in &mut items {
+            item.overwritten = preview.overwritten(&item.source, &item.destination);
+        }
+        Ok(FileRestorePlan {
+            items,
+            backup_dir: Path::new(BACKUP_ROOT).join(backup_name),
+            preview_incomplete: preview.incomplete,
+        })
+    }
+
+    /// The live files that will be overwritten, and kept in the backup folder.
+    pub fn replaced(&self) -> usize {
+        self.items.iter().map(|item| item.overwritten.len()).sum()
+    }
+}
+
+impl FileRestoreItem {
+    /// The rsync arguments that copy this item : owners, permissions, ACLs, xattrs and hard
+    /// links are kept, and what gets replaced is moved in the backup folder, under its path.
+    pub fn rsync_args(&self, backup_dir: &Path) -> Vec<OsString> {
+        let relative = self
+            .destination
+            .strip_prefix("/")
+            .unwrap_or(&self.destination);
+        let mut args: Vec<OsString> = vec!["-aAXH".into(), "--backup".into()];
+        let (source, destination, backup) = if self.is_dir {
+            // The trailing slashes copy the content of the folder into the folder
+            (
+                slash(&self.source),
+                slash(&self.destination),
+                backup_dir.join(relative),
+            )
+        } else {
+            (
+                self.source.clone().into_os_string(),
+                self.destination.clone().into_os_string(),
+                backup_dir.join(relative.parent().unwrap_or(Path::new(""))),
+            )
+        };
+        let mut backup_arg = OsString::from("--backup-dir=");
+        backup_arg.push(backup);
+        args.push(backup_arg);
+        args.push("--".into());
+        args.push(source);
+        args.push(destination);
+        args
+    }
+}
+
+/// Looks for what rsync will replace : the files on both sides that its quick check finds
+/// different. What only exists in the snapshot is added, what only exists on the system stays.
+struct Preview {
+    scanned: usize,
+    incomplete: bool,
+}
+
+impl Preview {
+    fn overwritten(&mut self, source: &Path, destination: &Path) -> Vec<PathBuf> {
+        let mut overwritten = Vec::new();
+        self.walk(source, destination, &mut overwritten);
+        overwritten
+    }
+
+    fn walk(&mut self, source: &Path, destination: &Path, overwritten: &mut Vec<PathBuf>) {
+        if self.scanned >= PREVIEW_LIMIT {
+            self.incomplete = true;
+            return;
+        }
+        self.scanned += 1;
+        let (Ok(old), Ok(new)) = (destination.symlink_metadata(), source.symlink_metadata()) else {
+            return;
+        };
+        if old.is_dir() && new.is_dir() {
+            let Ok(entries) = fs::read_dir(source) else {
+                return;
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name();
+                self.walk(&source.join(&name), &destination.join(&name), overwritten);
+            }
+        } else if !same_file(&old, &new, Some(destination), Some(source)) {
+            overwritten.push(destination.to_path_buf());
+        }
+    }
+}
+
+fn slash(path: &Path) -> OsString {
+    let mut path = path.as_os_str().to_owned();
+    path.push("/");
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(source: &str, destination: &str, is_dir: bool) -> FileRestoreItem {
+        FileRestoreItem {
+            source: PathBuf::from(source),
+            destination: PathBuf::from(destination),
+            is_dir,
+            exists: true,
+            overwritten: Vec::new(),
+        }
+    }
+
+    fn args(item: &FileRestoreItem) -> Vec<String> {
+        item.rsync_args(Path::new("/var/backups/timeshift-tui/restore"))
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn file_is_backed_up_in_its_folder() {
+        let file = item("/snapshot/localhost/etc/fstab", "/etc/fstab", false);
+        assert_eq!(
+            args(&file),
+            [
+                "-aAXH",
+                "--backup",
+                "--backup-dir=/var/backups/timeshift-tui/restore/etc",
+                "--",
+                "/snapshot/localhost/etc/fstab",
+                "/etc/fstab",
+            ]
+        );
+    }
+
+    #[test]
+    fn folder_content_is_copied_into_the_folder() {
+        let folder = item("/snapshot/localhost/etc/pacman.d", "/etc/pacman.d", true);
+        assert_eq!(
+            args(&folder),
+            [
+                "-aAXH",
+                "--backup",
+                "--backup-dir=/var/backups/timeshift-tui/restore/etc/pacman.d",
+                "--",
+                "/snapshot/localhost/etc/pacman.d/",
+                "/etc/pacman.d/",
+            ]
+        );
+    }
+
+    #[test]
+    fn original_paths() {
+        assert_eq!(
+            original_path(Path::new("localhost/etc/fstab")),
+            Some(PathBuf::from("/etc/fstab"))
+        );
+        assert_eq!(
+            original_path(Path::new("@home/user/.bashrc")),
+            Some(PathBuf::from("/home/user/.bashrc"))
+        );
+        assert_eq!(original_path(Path::new("info.json")), None);
+    }
+}
@@ -0,0 +1,150 @@
+use super::SnapshotTag;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What timeshift writes in the `info.json` of every snapshot. `--list` only gives us the name,
+/// the tags and the comment, this file also tells which system the snapshot comes from.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotInfo {
+    pub created: Option<DateTime<Local>>,
+    pub sys_uuid: String,
+    pub sys_distro: String,
+    pub app_version: String,
+    pub tags: BTreeSet<SnapshotTag>,
+    pub comments: String,
+    /// Every other key of the file, as written by timeshift
+    pub other: BTreeMap<String, String>,
+}
+
+// Timeshift writes every value as a string, even the numbers
+#[derive(Deserialize)]
+struct RawInfo {
+    #[serde(default)]
+    created: String,
+    #[serde(default, rename = "sys-uuid")]
+    sys_uuid: String,
+    #[serde(default, rename = "sys-distro")]
+    sys_distro: String,
+    #[serde(default, rename = "app-version")]
+    app_version: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    comments: String,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+impl SnapshotInfo {
+    /// Reads the content of an `info.json`. The tags are separated by spaces in there
+    /// (`"ondemand daily"`), unknown ones are skipped.
+    pub fn parse(json: &str) -> Result<Self> {
+        let raw: RawInfo = serde_json::from_str(json).context("Invalid info.json")?;
+        let created = raw
+            .created
+            .parse::<i64>()
+            .ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .map(|date| date.with_timezone(&Local));
+        let tags = raw
+            .tags
+            .split_whitespace()
+            .filter_map(SnapshotTag::from_word)
+            .collect();
+        let other = raw
+            .other
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(text) => (key, text),
+                value => (key, value.to_string()),
+            })
+            .collect();
+        Ok(SnapshotInfo {
+            created,
+            sys_uuid: raw.sys_uuid,
+            sys_distro: raw.sys_distro,
+            app_version: raw.app_version,
+            tags,
+            comments: raw.comments,
+            other,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_written_by_timeshift() {
+        // What timeshift writes, plus a nested value : those end up in `other` as JSON text
+        let json = r#"{
+  "created" : "1736676001",
+  "sys-uuid" : "6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5e",
+  "sys-distro" : "Ubuntu 24.04.1 LTS",
+  "app-version" : "24.01.1",
+  "tags" : "ondemand daily",
+  "comments" : "before kernel upgrade",
+  "subvolumes" : {
+    "@" : "a1b2c3d4-0000-4000-8000-000000000001"
+  }
+}"#;
+        let info = SnapshotInfo::parse(json).unwrap();
+        assert_eq!(
+            info.created,
+            DateTime::from_timestamp(1736676001, 0).map(|d| d.with_timezone(&Local))
+        );
+        assert_eq!(info.sys_uuid, "6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5e");
+        assert_eq!(info.sys_distro, "Ubuntu 24.04.1 LTS");
+        assert_eq!(info.app_version, "24.01.1");
+        assert_eq!(
+            info.tags,
+            BTreeSet::from([SnapshotTag::Ondemand, SnapshotTag::Daily])
+        );
+        assert_eq!(info.comments, "before kernel upgrade");
+        assert_eq!(
+            info.other,
+            BTreeMap::from([(
+                "subvolumes".to_string(),
+                r#"{"@":"a1b2c3d4-0000-4000-8000-000000000001"}"#.to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn missing_and_extra_fields() {
+        // Older versions of timeshift don't write everything
+        let info = SnapshotInfo::parse(
+            r#"{ "tags" : "boot weekly hourly", "name" : "2025-01-12_10-00-01" }"#,
+        )
+        .unwrap();
+        assert_eq!(info.created, None);
+        assert_eq!(info.sys_uuid, "");
+        assert_eq!(info.comments, "");
+        assert_eq!(
+            info.tags,
+            BTreeSet::from([SnapshotTag::Boot, SnapshotTag::Weekly, SnapshotTag::Hourly])
+        );
+        assert_eq!(info.other["name"], "2025-01-12_10-00-01");
+
+        // A date we can't read and a tag we don't know are left out, not an error
+        let info =
+            SnapshotInfo::parse(r#"{ "created" : "yesterday", "tags" : "ondemand yearly" }"#)
+                .unwrap();
+        assert_eq!(info.created, None);
+        assert_eq!(info.tags, BTreeSet::from([SnapshotTag::Ondemand]));
+        assert!(SnapshotInfo::parse("{}").unwrap().other.is_empty());
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        assert!(SnapshotInfo::parse("").is_err());
+        assert!(SnapshotInfo::parse(r#"{ "created" : "1736676001", "#).is_err());
+        assert!(SnapshotInfo::parse(r#"["ondemand"]"#).is_err());
+        // The values are strings, a number where timeshift writes text is not its file
+        let error = SnapshotInfo::parse(r#"{ "comments" : 12 }"#).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid info.json");
+    }
+}
@@ -0,0 +1,666 @@
+use super::browse::SnapshotTree;
+use super::btrfs::{QgroupUsage, Subvolume};
+use super::config::TimeshiftConfig;
+use super::file_restore::FileRestorePlan;
+use super::info::SnapshotInfo;
+use super::operation::{CancelHandle, Cancelled, OperationContext};
+use super::packages::{self, InstalledPackages, PackageManager};
+use super::schedule::CronJobs;
+use super::space::DeviceSpace;
+use super::usage::SnapshotSize;
+use super::{
+    Backend, CommandError, Device, DeviceType, Parsed, RestoreOptions, SNAPSHOT_NAME_FORMAT,
+    Snapshot, SnapshotTag,
+};
+use anyhow::{Context, Result};
+use chrono::Local;
+use indexmap::IndexMap;
+use std::collections::BTreeSet;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// The operations of the backend that can be scripted to fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockOperation {
+    GetDevices,
+    GetSnapshots,
+    Create,
+    Delete,
+    Restore,
+    Info,
+    Config,
+    Sizes,
+    Space,
+    Browse,
+    RestoreFiles,
+    Packages,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    devices: IndexMap<String, (Device, Vec<Snapshot>)>,
+    failures: HashMap<MockOperation, String>,
+    delay: Duration,
+    config: TimeshiftConfig,
+    cron_jobs: CronJobs,
+    pins: BTreeSet<String>,
+    /// The snapshots that include `@home`, in btrfs mode
+    home_subvolumes: HashSet<String>,
+}
+
+/// What a fresh install of timeshift writes, with the daily snapshots turned on.
+const DEMO_CONFIG: &str = r#"{
+  "backup_device_uuid" : "6f1d2c3b-8a4e-4b5f-9d7c-2e1a0b3c4d5e",
+  "parent_device_uuid" : "",
+  "do_first_run" : "false",
+  "btrfs_mode" : "false",
+  "include_btrfs_home_for_backup" : "false",
+  "include_btrfs_home_for_restore" : "false",
+  "stop_cron_emails" : "true",
+  "schedule_monthly" : "false",
+  "schedule_weekly" : "false",
+  "schedule_daily" : "true",
+  "schedule_hourly" : "false",
+  "schedule_boot" : "true",
+  "count_monthly" : "2",
+  "count_weekly" : "3",
+  "count_daily" : "5",
+  "count_hourly" : "6",
+  "count_boot" : "5",
+  "date_format" : "%Y-%m-%d %H:%M:%S",
+  "exclude" : [
+    "+ /root/.bashrc",
+    "/root/**",
+    "/home/*/**"
+  ],
+  "exclude-apps" : []
+}"#;
+
+/// The packages of the demo system, `generation` goes up with each snapshot. Some are
+/// upgraded every time, some added or removed along the way, and openssl goes back down.
+fn demo_packages(generation: u32) -> Vec<(&'static str, String)> {
+    let mut packages = vec![
+        ("pacman", "7.0.0-1".to_string()),
+        ("glibc", "2.40+r16-1".to_string()),
+        ("linux", format!("6.10.{}.arch1-1", generation + 1)),
+        ("firefox", format!("{}.0-1", 128 + generation)),
+        ("mesa", format!("1:24.{}.0-1", generation / 2)),
+        (
+            "openssl",
+            format!("3.{}.0-1", 5u32.saturating_sub(generation)),
+        ),
+    ];
+    if generation >= 2 {
+        packages.push(("python-requests", "2.32.3-1".to_string()));
+    }
+    if generation < 3 {
+        packages.push(("neofetch", "7.1.0-5".to_string()));
+    }
+    if generation >= 4 {
+        packages.push(("nvidia", "560.35.03-1".to_string()));
+    }
+    packages
+}
+
+/// In-memory backend, so the whole TUI can be used without root and without timeshift (on CI
+/// for example). The state is shared between the clones, so a snapshot created from the
+/// operation thread shows up in the list of the app.
+#[derive(Debug, Default, Clone)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A couple of devices with some snapshots on them, used by `--mock`.
+    pub fn demo() -> Self {
+        Self::new()
+            .with_device(Device::new(
+                0,
+                "/dev/sda2".to_string(),
+                "500.0 GB".to_string(),
+                DeviceType::Ext4,
+                "".to_string(),
+            ))
+            .with_device(Device::new(
+                1,
+                "/dev/sdb1".to_string(),
+                "1.0 TB".to_string(),
+                DeviceType::Ext4,
+                "".to_string(),
+            ))
+            .with_snapshot(
+                "/dev/sda2",
+                "2025-09-01_10-00-01",
+                "O",
+                "before kernel upgrade",
+            )
+            .with_snapshot("/dev/sda2", "2025-10-01_12-00-01", "D", "")
+            .with_snapshot("/dev/sda2", "2025-10-02_12-00-01", "BD", "")
+            .with_snapshot("/dev/sdb1", "2025-10-03_09-30-00", "W", "weekly")
+            .with_delay(Duration::from_secs(2))
+            .with_config(DEMO_CONFIG)
+            // The boot snapshots are on in the config, but their cron job is missing
+            .with_cron_jobs(CronJobs {
+                hourly: true,
+                boot: false,
+            })
+    }
+
+    /// Turns the mock into a btrfs setup : btrfs devices, `btrfs_mode` on, and `@home` in the
+    /// first snapshot of every device.
+    pub fn with_btrfs(self) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.config.btrfs_mode = true;
+            let mut home_subvolumes = HashSet::new();
+            for (device, snapshots) in state.devices.values_mut() {
+                device.device_type = DeviceType::Btrfs;
+                home_subvolumes.extend(snapshots.first().map(|s| s.name.clone()));
+            }
+            state.home_subvolumes = home_subvolumes;
+        }
+        self
+    }
+
+    /// Reads `TIMESHIFT_TUI_MOCK_DELAY_MS` and `TIMESHIFT_TUI_MOCK_FAIL` (a comma separated list
+    /// of `devices`, `snapshots`, `create`, `delete`, `restore`, `info`, `config`, `sizes` and `space`) to script
+    /// the mock from outside. `TIMESHIFT_TUI_MOCK_BTRFS=1` switches it to btrfs mode.
+    pub fn configure_from_env(mut self) -> Self {
+        if let Some(delay) = env::var("TIMESHIFT_TUI_MOCK_DELAY_MS")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+        {
+            self = self.with_delay(Duration::from_millis(delay));
+        }
+        if env::var("TIMESHIFT_TUI_MOCK_BTRFS").is_ok_and(|value| value == "1") {
+            self = self.with_btrfs();
+        }
+        if let Ok(failures) = env::var("TIMESHIFT_TUI_MOCK_FAIL") {
+            for name in failures.split(',').map(str::trim) {
+                let operation = match name {
+                    "devices" => MockOperation::GetDevices,
+                    "snapshots" => MockOperation::GetSnapshots,
+                    "create" => MockOperation::Create,
+                    "delete" => MockOperation::Delete,
+                    "restore" => MockOperation::Restore,
+                    "info" => MockOperation::Info,
+                    "config" => MockOperation::Config,
+                    "sizes" => MockOperation::Sizes,
+                    "space" => MockOperation::Space,
+                    "browse" => MockOperation::Browse,
+                    "files" => MockOperation::RestoreFiles,
+                    "packages" => MockOperation::Packages,
+                    _ => continue,
+                };
+                self = self.with_failure(operation, &format!("mock {name} failure"));
+            }
+        }
+        self
+    }
+
+    pub fn with_device(self, device: Device) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .devices
+            .insert(device.device_name.clone(), (device, Vec::new()));
+        self
+    }
+
+    /// Adds a snapshot to an already registered device, the num is given by the position. Like
+    /// with timeshift, the name must be a date and the tags valid letters, otherwise the snapshot
+    /// is ignored.
+    pub fn with_snapshot(
+        self,
+        device_name: &str,
+        name: &str,
+        tags: &str,
+        description: &str,
+    ) -> Self {
+        if let Some((_, snapshots)) = self.state.lock().unwrap().devices.get_mut(device_name)
+            && let Some(date) = Snapshot::date_from_name(name)
+            && let Some(tags) = SnapshotTag::parse_set(tags)
+        {
+            let num = snapshots.len() as u32;
+            snapshots.push(Snapshot::new(
+                num,
+                name.to_string(),
+                date,
+                tags,
+                description.to_string(),
+            ));
+        }
+        self
+    }
+
+    /// The content of the `timeshift.json` of the mock, ignored if it cannot be read.
+    pub fn with_config(self, json: &str) -> Self {
+        if let Ok(config) = TimeshiftConfig::parse(json) {
+            self.state.lock().unwrap().config = config;
+        }
+        self
+    }
+
+    pub fn with_cron_jobs(self, cron_jobs: CronJobs) -> Self {
+        self.state.lock().unwrap().cron_jobs = cron_jobs;
+        self
+    }
+
+    /// Every call to `operation` will fail with `message` until the end of times.
+    pub fn with_failure(self, operation: MockOperation, message: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(operation, message.to_string());
+        self
+    }
+
+    /// Time that creation, deletion and restoration take, so we can actually see the throbbers.
+    pub fn with_delay(self, delay: Duration) -> Self {
+        self.state.lock().unwrap().delay = delay;
+        self
+    }
+
+    /// Fails the same way the real timeshift would, with an exit code of 1.
+    fn check_failure(&self, operation: MockOperation) -> Result<()> {
+        match self.state.lock().unwrap().failures.get(&operation) {
+            Some(message) => Err(CommandError {
+                code: Some(1),
+                output: message.clone(),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes `delay` to complete, printing progress lines like timeshift does along the way.
+    /// Stops early when the operation is cancelled.
+    fn wait(&self, context: &OperationContext) -> Result<()> {
+        let delay = self.state.lock().unwrap().delay;
+        let steps = 10;
+        for step in 0..steps {
+            if context.is_cancelled() {
+                return Err(Cancelled {
+                    details: format!("mock stopped at step {} of {steps}", step + 1),
+                }
+                .into());
+            }
+            let remaining = delay * (steps - step) / steps;
+            context.send_line(&format!(
+                "{:.2}% complete (00:00:{:02} remaining)",
+                step as f64 * 100.0 / steps as f64,
+                remaining.as_secs()
+            ));
+            if step % 3 == 0 {
+                context.send_line(&format!("mock: step {} of {steps}", step + 1));
+            }
+            thread::sleep(delay / steps);
+        }
+        Ok(())
+    }
+}
+
+impl Backend for MockBackend {
+    fn get_devices(&self) -> Result<Parsed<Device>> {
+        self.check_failure(MockOperation::GetDevices)?;
+        let state = self.state.lock().unwrap();
+        let devices: Vec<Device> = state
+            .devices
+            .values()
+            .map(|(device, _)| device.clone())
+            .collect();
+        Ok(devices.into())
+    }
+
+    fn get_snapshots(&self, device: &Device) -> Result<Parsed<Snapshot>> {
+        self.check_failure(MockOperation::GetSnapshots)?;
+        let state = self.state.lock().unwrap();
+        match state.devices.get(&device.device_name) {
+            Some((_, snapshots)) => Ok(snapshots.clone().into()),
+            None => anyhow::bail!("Device {} not found", device.device_name),
+        }
+    }
+
+    fn create_snapshot(
+        &self,
+        comment: &str,
+        snapshot_device: &str,
+        include_home: Option<bool>,
+        context: &OperationContext,
+    ) -> Result<()> {
+        self.wait(context)?;
+        self.check_failure(MockOperation::Create)?;
+        let mut state = self.state.lock().unwrap();
+        let Some((_, snapshots)) = state.devices.get_mut(snapshot_device) else {
+            anyhow::bail!("Device {snapshot_device} not found");
+        };
+        let name = Local::now().format(SNAPSHOT_NAME_FORMAT).to_string();
+        let date = Snapshot::date_from_name(&name).unwrap_or_else(Local::now);
+        let num = snapshots.len() as u32;
+        snapshots.push(Snapshot::new(
+            num,
+            name.clone(),
+            date,
+            BTreeSet::from([SnapshotTag::Ondemand]),
+            comment.to_string(),
+        ));
+        if state.config.btrfs_mode
+            && include_home.unwrap_or(state.config.include_btrfs_home_for_backup)
+        {
+            state.home_subvolumes.insert(name);
+        }
+        Ok(())
+    }
+
+    fn delete_snapshot(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+        context: &OperationContext,
+    ) -> Result<()> {
+        self.wait(context)?;
+        self.check_failure(MockOperation::Delete)?;
+        let mut state = self.state.lock().unwrap();
+        let Some((_, snapshots)) = state.devices.get_mut(snapshot_device) else {
+            anyhow::bail!("Device {snapshot_device} not found");
+        };
+        let Some(position) = snapshots.iter().position(|s| s.name == snapshot_name) else {
+            anyhow::bail!("Snapshot {snapshot_name} not found on {snapshot_device}");
+        };
+        snapshots.remove(position);
+        // timeshift renumbers the snapshots after a deletion, so we do the same
+        for (num, snapshot) in snapshots.iter_mut().enumerate() {
+            snapshot.num = num as u32;
+        }
+        Ok(())
+    }
+
+    fn restore_snapshot(&self, options: &RestoreOptions, context: &OperationContext) -> Result<()> {
+        self.wait(context)?;
+        self.check_failure(MockOperation::Restore)?;
+        let state = self.state.lock().unwrap();
+        let Some((_, snapshots)) = state.devices.get(&options.snapshot_device) else {
+            anyhow::bail!("Device {} not found", options.snapshot_device);
+        };
+        if !snapshots.iter().any(|s| s.name == options.snapshot_name) {
+            anyhow::bail!(
+                "Snapshot {} not found on {}",
+                options.snapshot_name,
+                options.snapshot_device
+            );
+        }
+        if !state.devices.contains_key(&options.target_device) {
+            anyhow::bail!("Target device {} not found", options.target_device);
+        }
+        // Nothing to restore for real, the system stays as it is
+        Ok(())
+    }
+
+    /// Writes the `info.json` timeshift would have written, and reads it back with the same
+    /// parser as the real backend.
+    fn snapshot_info(&self, snapshot_name: &str, snapshot_device: &str) -> Result<SnapshotInfo> {
+        self.check_failure(MockOperation::Info)?;
+        let state = self.state.lock().unwrap();
+        let Some((_, snapshots)) = state.devices.get(snapshot_device) else {
+            anyhow::bail!("Device {snapshot_device} not found");
+        };
+        let Some(snapshot) = snapshots.iter().find(|s| s.name == snapshot_name) else {
+            anyhow::bail!("Snapshot {snapshot_name} not found on {snapshot_device}");
+        };
+        let tags: Vec<&str> = snapshot.tags.iter().map(|tag| tag.word()).collect();
+        let json = serde_json::json!({
+            "created": snapshot.date.timestamp().to_string(),
+            "sys-uuid": "0b9a7c2e-5d41-4f7e-9c1a-3e2f6b8d4a10",
+            "sys-distro": "Arch Linux",
+            "app-version": "25.07.7",
+            "snapshot-size": "8123456789",
+            "snapshot-size-compressed": "0",
+            "tags": tags.join(" "),
+            "comments": snapshot.description,
+        });
+        SnapshotInfo::parse(&json.to_string())
+    }
+
+    /// Three quarters of the device are used by the system, and each snapshot takes 1 GiB more.
+    fn device_space(&self, device: &Device) -> Result<DeviceSpace> {
+        self.check_failure(MockOperation::Space)?;
+        let state = self.state.lock().unwrap();
+        let Some((device, snapshots)) = state.devices.get(&device.device_name) else {
+            anyhow::bail!("Device {} not found", device.device_name);
+        };
+        let total = device.size_bytes().unwrap_or(256 * 1_000_000_000);
+        let used = (total / 4 * 3 + snapshots.len() as u64 * 1024 * 1024 * 1024).min(total);
+        Ok(DeviceSpace {
+            total,
+            used,
+            free: total - used,
+        })
+    }
+
+    /// The system is on the first device, the other ones aren't mounted.
+    fn mount_point(&self, device: &Device) -> Option<PathBuf> {
+        (device.num == 0).then(|| PathBuf::from("/"))
+    }
+
+    fn read_config(&self) -> Result<TimeshiftConfig> {
+        self.check_failure(MockOperation::Config)?;
+        Ok(self.state.lock().unwrap().config.clone())
+    }
+
+    /// Same checks as the real backend, but nothing is written on disk.
+    fn write_config(&self, config: &TimeshiftConfig) -> Result<()> {
+        self.check_failure(MockOperation::Config)?;
+        let errors = config.validate();
+        if !errors.is_empty() {
+            anyhow::bail!("Invalid settings: {}", errors.join(", "));
+        }
+        self.state.lock().unwrap().config = config.clone();
+        Ok(())
+    }
+
+    fn read_pins(&self) -> Result<BTreeSet<String>> {
+        self.check_failure(MockOperation::Config)?;
+        Ok(self.state.lock().unwrap().pins.clone())
+    }
+
+    fn write_pins(&self, pins: &BTreeSet<String>) -> Result<()> {
+        self.check_failure(MockOperation::Config)?;
+        self.state.lock().unwrap().pins = pins.clone();
+        Ok(())
+    }
+
+    fn cron_jobs(&self) -> CronJobs {
+        self.state.lock().unwrap().cron_jobs
+    }
+
+    /// A small made up system, written once in the temporary folder. The files change a bit
+    /// from one snapshot to the next so there is something to compare.
+    fn open_snapshot(&self, snapshot_name: &str, snapshot_device: &str) -> Result<SnapshotTree> {
+        self.check_failure(MockOperation::Browse)?;
+        let num = {
+            let state = self.state.lock().unwrap();
+            let Some((_, snapshots)) = state.devices.get(snapshot_device) else {
+                anyhow::bail!("Device {snapshot_device} not found");
+            };
+            let Some(snapshot) = snapshots.iter().find(|s| s.name == snapshot_name) else {
+                anyhow::bail!("Snapshot {snapshot_name} not found on {snapshot_device}");
+            };
+            snapshot.num
+        };
+        let device = Path::new(snapshot_device)
+            .file_name()
+            .context("Invalid device name")?;
+        let root = env::temp_dir()
+            .join("timeshift-tui-mock")
+            .join(device)
+            .join(snapshot_name);
+        let system = root.join("localhost");
+        let mut fstab = String::from("UUID=6f1d2c3b / ext4 defaults 0 1\n");
+        if num > 0 {
+            fstab.push_str("/dev/sdb1 /data ext4 defaults 0 2\n");
+        }
+        let files: Vec<(&str, Vec<u8>)> = vec![
+            ("etc/hostname", b"demo\n".to_vec()),
+            ("etc/fstab", fstab.into_bytes()),
+            (
+                "usr/lib/os-release",
+                b"NAME=\"Arch Linux\"\nID=arch\n".to_vec(),
+            ),
+            ("usr/bin/demo", vec![0x7f, b'E', b'L', b'F', 0, 1, 2, 3]),
+            (
+                "home/demo/notes.txt",
+                format!("Written before snapshot {num}\n").into_bytes(),
+            ),
+            ("var/log/pacman.log", vec![b'.'; 100 * 1024]),
+        ];
+        for (file, content) in files {
+            let path = system.join(file);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, content)?;
+        }
+        let link = system.join("etc/os-release");
+        if fs::symlink_metadata(&link).is_err() {
+            std::os::unix::fs::symlink("../usr/lib/os-release", &link)?;
+        }
+        // The snapshots are renumbered after a deletion, the packages may not be the same
+        let pacman = system.join("var/lib/pacman/local");
+        let _ = fs::remove_dir_all(&pacman);
+        for (name, version) in demo_packages(num) {
+            let package = pacman.join(format!("{name}-{version}"));
+            fs::create_dir_all(&package)?;
+            fs::write(
+                package.join("desc"),
+                format!("%NAME%\n{name}\n\n%VERSION%\n{version}\n"),
+            )?;
+        }
+        Ok(SnapshotTree::new(root, None))
+    }
+
+    /// Read from the pacman database written with the files of the snapshot.
+    fn snapshot_packages(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+    ) -> Result<InstalledPackages> {
+        self.check_failure(MockOperation::Packages)?;
+        let tree = self.open_snapshot(snapshot_name, snapshot_device)?;
+        packages::installed_packages(&tree.system_root())
+    }
+
+    /// One generation after the newest snapshot.
+    fn installed_packages(&self) -> Result<InstalledPackages> {
+        self.check_failure(MockOperation::Packages)?;
+        let state = self.state.lock().unwrap();
+        let generation = state
+            .devices
+            .values()
+            .flat_map(|(_, snapshots)| snapshots)
+            .map(|snapshot| snapshot.num + 1)
+            .max()
+            .unwrap_or(0);
+        Ok(InstalledPackages {
+            manager: PackageManager::Pacman,
+            versions: demo_packages(generation)
+                .into_iter()
+                .map(|(name, version)| (name.to_string(), version))
+                .collect(),
+        })
+    }
+
+    fn restore_files(&self, plan: &FileRestorePlan, context: &OperationContext) -> Result<()> {
+        self.wait(context)?;
+        self.check_failure(MockOperation::RestoreFiles)?;
+        for item in &plan.items {
+            context.send_line(&format!("mock: {}", item.destination.display()));
+        }
+        // Nothing is copied for real, the system stays as it is
+        Ok(())
+    }
+
+    /// `@` in every snapshot, `@home` in some of them, with made up qgroup sizes.
+    fn snapshot_subvolumes(
+        &self,
+        snapshot_name: &str,
+        snapshot_device: &str,
+    ) -> Result<Vec<Subvolume>> {
+        self.check_failure(MockOperation::Info)?;
+        let state = self.state.lock().unwrap();
+        if !state.config.btrfs_mode {
+            anyhow::bail!("Timeshift is not in btrfs mode");
+        }
+        let Some((_, snapshots)) = state.devices.get(snapshot_device) else {
+            anyhow::bail!("Device {snapshot_device} not found");
+        };
+        let Some(snapshot) = snapshots.iter().find(|s| s.name == snapshot_name) else {
+            anyhow::bail!("Snapshot {snapshot_name} not found on {snapshot_device}");
+        };
+        let gib = 1024 * 1024 * 1024;
+        let mib = 1024 * 1024;
+        let mut subvolumes = vec![Subvolume {
+            name: "@".to_string(),
+            usage: Some(QgroupUsage {
+                referenced: 12 * gib + snapshot.num as u64 * 300 * mib,
+                exclusive: (snapshot.num as u64 + 1) * 150 * mib,
+            }),
+        }];
+        if state.home_subvolumes.contains(snapshot_name) {
+            subvolumes.push(Subvolume {
+                name: "@home".to_string(),
+                usage: Some(QgroupUsage {
+                    referenced: 48 * gib,
+                    exclusive: 2 * gib,
+                }),
+            });
+        }
+        Ok(subvolumes)
+    }
+
+    /// The first snapshot of a device owns most of the data, the next ones only what changed,
+    /// like with the hard links of rsync. Each one takes a tenth of the delay to measure.
+    fn snapshot_sizes(
+        &self,
+        snapshot_device: &str,
+        snapshot_names: &[String],
+        cancel: &CancelHandle,
+        on_size: &mut dyn FnMut(&str, Result<SnapshotSize>),
+    ) {
+        let delay = self.state.lock().unwrap().delay;
+        for name in snapshot_names {
+            thread::sleep(delay / 10);
+            if cancel.is_cancelled() {
+                return;
+            }
+            let size = self.check_failure(MockOperation::Sizes).and_then(|()| {
+                let state = self.state.lock().unwrap();
+                let Some((_, snapshots)) = state.devices.get(snapshot_device) else {
+                    anyhow::bail!("Device {snapshot_device} not found");
+                };
+                let Some(position) = snapshots.iter().position(|s| &s.name == name) else {
+                    anyhow::bail!("Snapshot {name} not found on {snapshot_device}");
+                };
+                let mib = 1024 * 1024;
+                Ok(SnapshotSize {
+                    apparent: 9 * 1024 * mib + position as u64 * 40 * mib,
+                    unique: if position == 0 {
+                        8 * 1024 * mib
+                    } else {
+                        (position as u64 + 1) * 120 * mib
+                    },
+                })
+            });
+            on_size(name, size);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+
+/// Where we mount the snapshot devices that aren't mounted yet.
+pub const MOUNT_ROOT: &str = "/run/timeshift-tui";
+
+/// How many `Mount` use each of the mount points we made. A device opened twice (a report and a
+/// browser, two sides of a comparison) shares our mount, which is only unmounted by the last one.
+static OWN_MOUNTS: Mutex<BTreeMap<PathBuf, usize>> = Mutex::new(BTreeMap::new());
+
+/// A snapshot device, mounted somewhere we can read it. When the device was already mounted we
+/// use that mount point, otherwise we mount it read-only ourselves and unmount it when the last
+/// `Mount` using it is dropped.
+#[derive(Debug)]
+pub struct Mount {
+    pub path: PathBuf,
+    owned: bool,
+}
+
+impl Mount {
+    /// When the device is a btrfs filesystem mounted on one of its subvolumes (`/` on `@` for
+    /// example), the snapshots of timeshift aren't visible from there : they are in the top
+    /// level subvolume, which we mount next to it.
+    pub fn device(device_name: &str) -> Result<Self> {
+        let base_name = Path::new(device_name)
+            .file_name()
+            .context("Invalid device name")?;
+        let path = Path::new(MOUNT_ROOT).join(base_name);
+        // Held until the mount is counted, so two threads don't mount the device twice
+        let mut own_mounts = OWN_MOUNTS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(users) = own_mounts.get_mut(&path) {
+            *users += 1;
+            return Ok(Mount { path, owned: true });
+        }
+        let options = match find_mount_point(device_name) {
+            Some(mounted) if mounted.fs_type != "btrfs" || mounted.is_btrfs_top_level() => {
+                return Ok(Mount {
+                    path: mounted.path,
+                    owned: false,
+                });
+            }
+            Some(_) => "ro,subvolid=5",
+            None => "ro",
+        };
+        fs::create_dir_all(&path).with_context(|| format!("Couldn't create {}", path.display()))?;
+        let output = Command::new("mount")
+            .arg("-o")
+            .arg(options)
+            .arg(device_name)
+            .arg(&path)
+            .output()
+            .context("Failed to execute mount")?;
+        if !output.status.success() {
+            bail!(
+                "Couldn't mount {device_name}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        own_mounts.insert(path.clone(), 1);
+        Ok(Mount { path, owned: true })
+    }
+
+    /// The folder of a snapshot on this device. Timeshift keeps rsync snapshots in `timeshift`
+    /// and btrfs ones in `timeshift-btrfs`.
+    pub fn snapshot_dir(&self, snapshot_name: &str) -> Result<PathBuf> {
+        ["timeshift", "timeshift-btrfs"]
+            .iter()
+            .map(|folder| self.path.join(folder).join("snapshots").join(snapshot_name))
+            .find(|dir| dir.is_dir())
+            .with_context(|| {
+                format!(
+                    "Snapshot {snapshot_name} not found in {}",
+                    self.path.display()
+                )
+            })
+    }
+}
+
+impl Drop for Mount {
+    fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        let mut own_mounts = OWN_MOUNTS.lock().unwrap_or_else(PoisonError::into_inner);
+        let users = own_mounts.entry(self.path.clone()).or_insert(1);
+        *users -= 1;
+        if *users == 0 {
+            own_mounts.remove(&self.path);
+            // Nothing we can do if it fails, it is only a read-only mount left behind
+            let _ = Command::new("umount").arg(&self.path).output();
+        }
+    }
+}
+
+/// Where the device is mounted, if it is.
+pub fn mount_point(device_name: &str) -> Option<PathBuf> {
+    find_mount_point(device_name).map(|mounted| mounted.path)
+}
+
+/// A line of /proc/mounts.
+struct MountedDevice {
+    path: PathBuf,
+    fs_type: String,
+    options: String,
+}
+
+impl MountedDevice {
+    fn is_btrfs_top_level(&self) -> bool {
+        self.options
+            .split(',')
+            .any(|option| option == "subvolid=5" || option == "subvol=/")
+    }
+}
+
+/// Looks for the device in /proc/mounts. The devices can be written through symlinks
+/// (`/dev/disk/by-uuid/...`), so both sides are resolved before comparing. A btrfs device can be
+/// mounted several times, the top level subvolume is preferred.
+fn find_mount_point(device_name: &str) -> Option<MountedDevice> {
+    let device = fs::canonicalize(device_name).ok()?;
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    let mut found: Vec<MountedDevice> = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let target = fields.next()?;
+            let fs_type = fields.next()?;
+            let options = fields.next()?;
+            (fs::canonicalize(source).ok()? == device).then(|| MountedDevice {
+                // Spaces in mount points are escaped as \040 in /proc/mounts
+                path: PathBuf::from(target.replace("\\040", " ")),
+                fs_type: fs_type.to_string(),
+                options: options.to_string(),
+            })
+        })
+        .collect();
+    let position = found
+        .iter()
+        .position(MountedDevice::is_btrfs_top_level)
+        .unwrap_or(0);
+    (!found.is_empty()).then(|| found.swap_remove(position))
+}
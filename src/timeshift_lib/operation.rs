@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+/// Only the end of the output is kept, nobody is going to scroll through a whole rsync log.
+pub const OPERATION_LOG_LINES: usize = 500;
+
+/// What timeshift tells us while a create, delete or restore is running.
+#[derive(Debug, Clone)]
+pub enum OperationEvent {
+    /// A line printed by timeshift
+    Line(String),
+    /// Timeshift printed how far it is
+    Progress(Progress),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub percent: f64,
+    /// Time left, as printed by timeshift (`00:03:14`)
+    pub eta: Option<String>,
+}
+
+impl Progress {
+    /// Reads the lines timeshift prints while rsync is running, like
+    /// `45.12% complete (00:03:14 remaining)`.
+    /// Any other line is not progress, even if it has a `%` in it (a file name, a disk usage).
+    pub fn parse(line: &str) -> Option<Self> {
+        let (percent, rest) = line.trim().split_once("% complete")?;
+        let percent = percent.parse::<f64>().ok().filter(|p| p.is_finite())?;
+        let rest = rest.trim();
+        let eta = if rest.is_empty() {
+            None
+        } else {
+            let eta = rest.strip_prefix('(')?.strip_suffix("remaining)")?.trim();
+            (!eta.is_empty()).then(|| eta.to_string())
+        };
+        Some(Progress {
+            percent: percent.clamp(0.0, 100.0),
+            eta,
+        })
+    }
+}
+
+/// Given to the backend for the long operations : where to send what timeshift prints, and a
+/// flag the app raises when the user wants to stop.
+#[derive(Debug, Clone)]
+pub struct OperationContext {
+    pub events: Sender<OperationEvent>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl OperationContext {
+    pub fn new(events: Sender<OperationEvent>) -> Self {
+        OperationContext {
+            events,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// A handle the app keeps to cancel the operation from the UI thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn send_line(&self, line: &str) {
+        send_line(&self.events, line);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The operation was stopped because the user asked for it. `details` tells how it went.
+#[derive(Debug, Clone)]
+pub struct Cancelled {
+    pub details: String,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation cancelled: {}", self.details)
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Sends a line to the app, as a progress update when it is one. Returns the event that was sent,
+/// `None` for blank lines.
+pub fn send_line(events: &Sender<OperationEvent>, line: &str) -> Option<OperationEvent> {
+    let line = line.trim_end();
+    if line.trim().is_empty() {
+        return None;
+    }
+    let event = match Progress::parse(line) {
+        Some(progress) => OperationEvent::Progress(progress),
+        None => OperationEvent::Line(line.to_string()),
+    };
+    // The app may already be gone, nothing to do about it
+    let _ = events.send(event.clone());
+    Some(event)
+}
+
+/// Reads `reader` in its own thread and sends every line to the app. Timeshift redraws its
+/// progress with `\r`, so those end a line too. The thread gives back the last
+/// `OPERATION_LOG_LINES` lines it read, without the progress ones, to be shown if the command
+/// fails.
+pub fn forward_lines<R>(mut reader: R, events: Sender<OperationEvent>) -> JoinHandle<String>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut tail = VecDeque::new();
+        let mut keep = |event: Option<OperationEvent>| {
+            if let Some(OperationEvent::Line(line)) = event {
+                if tail.len() == OPERATION_LOG_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        };
+        let mut line = Vec::new();
+        let mut chunk = [0u8; 4096];
+        while let Ok(read) = reader.read(&mut chunk) {
+            if read == 0 {
+                break;
+            }
+            for &byte in &chunk[..read] {
+                if byte == b'\n' || byte == b'\r' {
+                    keep(send_line(&events, &String::from_utf8_lossy(&line)));
+                    line.clear();
+                } else {
+                    line.push(byte);
+                }
+            }
+        }
+        keep(send_line(&events, &String::from_utf8_lossy(&line)));
+        Vec::from(tail).join("\n")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn progress_of_timeshift() {
+        assert_eq!(
+            Progress::parse("45.12% complete (00:03:14 remaining)"),
+            Some(Progress {
+                percent: 45.12,
+                eta: Some("00:03:14".to_string()),
+            })
+        );
+        assert_eq!(
+            Progress::parse("  100.00% complete"),
+            Some(Progress {
+                percent: 100.0,
+                eta: None,
+            })
+        );
+    }
+
+    #[test]
+    fn other_percentages_are_not_progress() {
+        assert_eq!(Progress::parse("Disk usage: 87% (120 GB remaining)"), None);
+        assert_eq!(Progress::parse("copied /home/user/50% off.pdf"), None);
+        assert_eq!(Progress::parse("45.12% complete (almost done)"), None);
+        assert_eq!(Progress::parse("NaN% complete"), None);
+    }
+
+    #[test]
+    fn only_the_tail_is_kept() {
+        let mut output = String::new();
+        for i in 0..OPERATION_LOG_LINES + 10 {
+            output.push_str(&format!("line {i}\n{i}.00% complete\r"));
+        }
+        let (events, received) = mpsc::channel();
+        let kept = forward_lines(std::io::Cursor::new(output), events)
+            .join()
+            .unwrap();
+        let lines: Vec<&str> = kept.lines().collect();
+        assert_eq!(lines.len(), OPERATION_LOG_LINES);
+        assert_eq!(lines[0], "line 10");
+        assert_eq!(
+            lines.last(),
+            Some(&format!("line {}", OPERATION_LOG_LINES + 9).as_str())
+        );
+        // Everything was still sent to the app
+        assert_eq!(received.iter().count(), 2 * (OPERATION_LOG_LINES + 10));
+    }
+}
@@ -0,0 +1,525 @@
+use anyhow::{Context, Result, bail};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// One folder per package, with a `desc` file in it.
+const PACMAN_DB: &str = "var/lib/pacman/local";
+const DPKG_STATUS: &str = "var/lib/dpkg/status";
+/// Newer distros moved the rpm database, `var/lib/rpm` is then a link to it.
+const RPM_DBS: [&str; 2] = ["usr/lib/sysimage/rpm", "var/lib/rpm"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Pacman,
+    Dpkg,
+    Rpm,
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageManager::Pacman => write!(f, "pacman"),
+            PackageManager::Dpkg => write!(f, "dpkg"),
+            PackageManager::Rpm => write!(f, "rpm"),
+        }
+    }
+}
+
+/// The packages installed on a system, with their version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackages {
+    pub manager: PackageManager,
+    pub versions: BTreeMap<String, String>,
+}
+
+impl InstalledPackages {
+    /// Some packages can be installed twice (kernels with rpm, several architectures with dpkg),
+    /// only the newest version is kept.
+    fn insert(&mut self, name: String, version: String) {
+        match self.versions.get(&name) {
+            Some(known) if self.manager.compare(known, &version) != Ordering::Less => {}
+            _ => {
+                self.versions.insert(name, version);
+            }
+        }
+    }
+}
+
+/// Sorted that way in the diff, the upgrades are what breaks a system most of the time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PackageChangeKind {
+    Upgraded,
+    Downgraded,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageChange {
+    pub name: String,
+    pub kind: PackageChangeKind,
+    /// `None` when the package was added
+    pub old: Option<String>,
+    /// `None` when the package was removed
+    pub new: Option<String>,
+}
+
+/// Reads the package database of the system whose `/` is `root`, whatever the package manager
+/// is. Pacman and dpkg are read directly, rpm keeps a database we can only read through the
+/// `rpm` command.
+pub fn installed_packages(root: &Path) -> Result<InstalledPackages> {
+    if root.join(PACMAN_DB).is_dir() {
+        pacman_packages(&root.join(PACMAN_DB))
+    } else if root.join(DPKG_STATUS).is_file() {
+        dpkg_packages(&root.join(DPKG_STATUS))
+    } else if RPM_DBS.iter().any(|db| root.join(db).is_dir()) {
+        rpm_packages(root)
+    } else {
+        bail!(
+            "No package database in {} (pacman, dpkg or rpm)",
+            root.display()
+        )
+    }
+}
+
+/// What changed from `old` (the snapshot) to `new` (the running system), the upgrades first,
+/// then by name.
+pub fn diff_packages(
+    old: &InstalledPackages,
+    new: &InstalledPackages,
+) -> Result<Vec<PackageChange>> {
+    if old.manager != new.manager {
+        bail!(
+            "The snapshot uses {} but the system uses {}",
+            old.manager,
+            new.manager
+        );
+    }
+    let mut changes = Vec::new();
+    for (name, old_version) in &old.versions {
+        let kind = match new.versions.get(name) {
+            None => PackageChangeKind::Removed,
+            Some(new_version) => match old.manager.compare(old_version, new_version) {
+                Ordering::Less => PackageChangeKind::Upgraded,
+                Ordering::Greater => PackageChangeKind::Downgraded,
+                // Same version, or written differently (`1.0` and `1.00`)
+                Ordering::Equal => continue,
+            },
+        };
+        changes.push(PackageChange {
+            name: name.clone(),
+            kind,
+            old: Some(old_version.clone()),
+            new: new.versions.get(name).cloned(),
+        });
+    }
+    for (name, new_version) in &new.versions {
+        if !old.versions.contains_key(name) {
+            changes.push(PackageChange {
+                name: name.clone(),
+                kind: PackageChangeKind::Added,
+                old: None,
+                new: Some(new_version.clone()),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+    Ok(changes)
+}
+
+/// Each `desc` file has `%NAME%` and `%VERSION%` sections, the value on the next line.
+fn pacman_packages(db: &Path) -> Result<InstalledPackages> {
+    let mut packages = InstalledPackages {
+        manager: PackageManager::Pacman,
+        versions: BTreeMap::new(),
+    };
+    let entries = fs::read_dir(db).with_context(|| format!("Couldn't read {}", db.display()))?;
+    for entry in entries {
+        let path = entry?.path().join("desc");
+        // The `ALPM_DB_VERSION` file is not a package
+        let Ok(desc) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut lines = desc.lines();
+        let (mut name, mut version) = (None, None);
+        while let Some(line) = lines.next() {
+            match line {
+                "%NAME%" => name = lines.next(),
+                "%VERSION%" => version = lines.next(),
+                _ => {}
+            }
+        }
+        if let (Some(name), Some(version)) = (name, version) {
+            packages.insert(name.to_string(), version.to_string());
+        }
+    }
+    Ok(packages)
+}
+
+/// One paragraph per package. The removed ones stay in the file until they are purged, only
+/// the `install ok installed` ones count.
+fn dpkg_packages(status: &Path) -> Result<InstalledPackages> {
+    let text = fs::read_to_string(status)
+        .with_context(|| format!("Couldn't read {}", status.display()))?;
+    let mut packages = InstalledPackages {
+        manager: PackageManager::Dpkg,
+        versions: BTreeMap::new(),
+    };
+    for paragraph in text.split("\n\n") {
+        let (mut name, mut version, mut installed) = (None, None, false);
+        for line in paragraph.lines() {
+            if let Some(value) = line.strip_prefix("Package:") {
+                name = Some(value.trim());
+            } else if let Some(value) = line.strip_prefix("Version:") {
+                version = Some(value.trim());
+            } else if let Some(value) = line.strip_prefix("Status:") {
+                installed = value.split_whitespace().last() == Some("installed");
+            }
+        }
+        if let (Some(name), Some(version), true) = (name, version, installed) {
+            packages.insert(name.to_string(), version.to_string());
+        }
+    }
+    Ok(packages)
+}
+
+/// The database is sqlite (or berkeley db on older systems), `rpm --root` reads it for us.
+fn rpm_packages(root: &Path) -> Result<InstalledPackages> {
+    let output = Command::new("rpm")
+        .arg("--root")
+        .arg(root)
+        .args([
+            "-qa",
+            "--qf",
+            "%{NAME} %|EPOCH?{%{EPOCH}:}:{}|%{VERSION}-%{RELEASE}\\n",
+        ])
+        .output()
+        .context("Failed to execute rpm command, it is needed to read the rpm database")?;
+    if !output.status.success() {
+        bail!(
+            "rpm failed : {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let mut packages = InstalledPackages {
+        manager: PackageManager::Rpm,
+        versions: BTreeMap::new(),
+    };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some((name, version)) = line.split_once(' ') {
+            packages.insert(name.to_string(), version.to_string());
+        }
+    }
+    Ok(packages)
+}
+
+impl PackageManager {
+    /// Orders two versions the way this package manager does. They all write them
+    /// `epoch:version-release`, but dpkg doesn't compare them like pacman and rpm.
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            PackageManager::Dpkg => compare_dpkg(a, b),
+            PackageManager::Pacman => compare_evr(a, b, true),
+            PackageManager::Rpm => compare_evr(a, b, false),
+        }
+    }
+}
+
+/// The epoch (`1:`) first, then the upstream version and the revision after the last `-`,
+/// like `dpkg --compare-versions`.
+fn compare_dpkg(a: &str, b: &str) -> Ordering {
+    let (a_epoch, a) = split_epoch(a);
+    let (b_epoch, b) = split_epoch(b);
+    let (a_version, a_revision) = a.rsplit_once('-').unwrap_or((a, ""));
+    let (b_version, b_revision) = b.rsplit_once('-').unwrap_or((b, ""));
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| compare_part(a_version, b_version))
+        .then_with(|| compare_part(a_revision, b_revision))
+}
+
+/// The epoch, the version, then the release when both have one, like `alpm_pkg_vercmp` and
+/// `rpmVersionCompare`.
+fn compare_evr(a: &str, b: &str, pacman: bool) -> Ordering {
+    let (a_epoch, a) = split_epoch(a);
+    let (b_epoch, b) = split_epoch(b);
+    let (a_version, a_release) = a.rsplit_once('-').map_or((a, None), |(v, r)| (v, Some(r)));
+    let (b_version, b_release) = b.rsplit_once('-').map_or((b, None), |(v, r)| (v, Some(r)));
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| rpmvercmp(a_version, b_version, pacman))
+        .then_with(|| match (a_release, b_release) {
+            (Some(a), Some(b)) => rpmvercmp(a, b, pacman),
+            _ => Ordering::Equal,
+        })
+}
+
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) => match epoch.parse() {
+            Ok(epoch) => (epoch, rest),
+            Err(_) => (0, version),
+        },
+        None => (0, version),
+    }
+}
+
+/// The segments of letters or digits one after the other, what separates them doesn't count.
+/// A number beats letters. Both have their own way with what is left at the end : for pacman
+/// letters never beat nothing (`1.0rc1` is older than `1.0`), for rpm whatever is left wins
+/// and pre-releases are written with `~` (`1.0~rc1`), `^` being the opposite (`1.0^git1` is
+/// after `1.0` but before `1.0.1`).
+fn rpmvercmp(a: &str, b: &str, pacman: bool) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let special = |c: &u8| !pacman && (*c == b'~' || *c == b'^');
+    let separator = |c: &u8| !c.is_ascii_alphanumeric() && !special(c);
+    let (mut one, mut two) = (a.as_bytes(), b.as_bytes());
+    loop {
+        let one_separators = one.iter().take_while(|c| separator(c)).count();
+        let two_separators = two.iter().take_while(|c| separator(c)).count();
+        (one, two) = (&one[one_separators..], &two[two_separators..]);
+        if !pacman {
+            if one.first() == Some(&b'~') || two.first() == Some(&b'~') {
+                if one.first() != Some(&b'~') {
+                    return Ordering::Greater;
+                }
+                if two.first() != Some(&b'~') {
+                    return Ordering::Less;
+                }
+                (one, two) = (&one[1..], &two[1..]);
+                continue;
+            }
+            if one.first() == Some(&b'^') || two.first() == Some(&b'^') {
+                if one.is_empty() {
+                    return Ordering::Less;
+                }
+                if two.is_empty() {
+                    return Ordering::Greater;
+                }
+                if one.first() != Some(&b'^') {
+                    return Ordering::Greater;
+                }
+                if two.first() != Some(&b'^') {
+                    return Ordering::Less;
+                }
+                (one, two) = (&one[1..], &two[1..]);
+                continue;
+            }
+        }
+        if one.is_empty() || two.is_empty() {
+            break;
+        }
+        if pacman && one_separators != two_separators {
+            return one_separators.cmp(&two_separators);
+        }
+        let numeric = one[0].is_ascii_digit();
+        let in_segment = |c: &u8| {
+            if numeric {
+                c.is_ascii_digit()
+            } else {
+                c.is_ascii_alphabetic()
+            }
+        };
+        let segment = |text: &[u8]| text.iter().take_while(|c| in_segment(c)).count();
+        let (one_end, two_end) = (segment(one), segment(two));
+        if two_end == 0 {
+            // Not the same kind of segment, the number wins
+            return if numeric {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+        let order = if numeric {
+            compare_numbers(&one[..one_end], &two[..two_end])
+        } else {
+            one[..one_end].cmp(&two[..two_end])
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+        (one, two) = (&one[one_end..], &two[two_end..]);
+    }
+    match (one.first(), two.first()) {
+        (None, None) => Ordering::Equal,
+        (one, two) if pacman => {
+            if (one.is_none() && !two.is_some_and(u8::is_ascii_alphabetic))
+                || one.is_some_and(u8::is_ascii_alphabetic)
+            {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }
+        (None, _) => Ordering::Less,
+        (Some(_), _) => Ordering::Greater,
+    }
+}
+
+/// Text and numbers one after the other, like `verrevcmp` in dpkg.
+fn compare_part(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    while !a.is_empty() || !b.is_empty() {
+        let (a_text, a_rest) = split_at_digit(a, false);
+        let (b_text, b_rest) = split_at_digit(b, false);
+        let length = a_text.len().max(b_text.len());
+        for i in 0..length {
+            let order = weight(a_text.get(i)).cmp(&weight(b_text.get(i)));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        let (a_number, a_rest) = split_at_digit(a_rest, true);
+        let (b_number, b_rest) = split_at_digit(b_rest, true);
+        let order = compare_numbers(a_number, b_number);
+        if order != Ordering::Equal {
+            return order;
+        }
+        (a, b) = (a_rest, b_rest);
+    }
+    Ordering::Equal
+}
+
+/// The start of `text` made of digits (or of anything else), and what follows.
+fn split_at_digit(text: &[u8], digits: bool) -> (&[u8], &[u8]) {
+    let end = text
+        .iter()
+        .position(|c| c.is_ascii_digit() != digits)
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+/// `~` sorts before the end of the text (`1.0~rc1` is older than `1.0`), the letters before the
+/// other characters.
+fn weight(c: Option<&u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_alphabetic() => *c as i32,
+        Some(c) => *c as i32 + 256,
+    }
+}
+
+/// Any length of digits, without overflowing.
+fn compare_numbers(a: &[u8], b: &[u8]) -> Ordering {
+    let trim = |number: &[u8]| {
+        let start = number
+            .iter()
+            .position(|c| *c != b'0')
+            .unwrap_or(number.len());
+        number[start..].to_vec()
+    };
+    let (a, b) = (trim(a), trim(b));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Ordering::{Equal, Greater, Less};
+
+    fn check(manager: PackageManager, pairs: &[(&str, &str, Ordering)]) {
+        for (a, b, expected) in pairs {
+            assert_eq!(manager.compare(a, b), *expected, "{manager} {a} vs {b}");
+            assert_eq!(
+                manager.compare(b, a),
+                expected.reverse(),
+                "{manager} {b} vs {a}"
+            );
+        }
+    }
+
+    #[test]
+    fn pacman_versions() {
+        check(
+            PackageManager::Pacman,
+            &[
+                ("1.0rc1", "1.0", Less),
+                ("1.0alpha", "1.0beta", Less),
+                ("1.0beta", "1.0", Less),
+                ("1.5.0", "1.5", Greater),
+                ("1.5b", "1.5a", Greater),
+                ("1.10", "1.9", Greater),
+                ("1.5-1", "1.5-2", Less),
+                ("1.5-1", "1.5", Equal),
+                ("1:1.0-1", "2.0-1", Greater),
+                ("6.10.3.arch1-1", "6.10.4.arch1-1", Less),
+                ("1.0.a", "1.0a", Greater),
+            ],
+        );
+    }
+
+    #[test]
+    fn rpm_versions() {
+        check(
+            PackageManager::Rpm,
+            &[
+                ("1.0~rc1", "1.0", Less),
+                ("1.0~rc1", "1.0~rc2", Less),
+                ("1.0^git1", "1.0", Greater),
+                ("1.0^git1", "1.0.1", Less),
+                // rpm lets what is left win, pre-releases need `~`
+                ("1.0rc1", "1.0", Greater),
+                ("5.5p1", "5.5p10", Less),
+                ("1.010", "1.9", Greater),
+                ("2.0-1.fc40", "2.0-2.fc40", Less),
+                ("1:1.0-1", "2.0-1", Greater),
+                ("1.0", "1.0", Equal),
+            ],
+        );
+    }
+
+    #[test]
+    fn dpkg_versions() {
+        check(
+            PackageManager::Dpkg,
+            &[
+                ("1.0~rc1", "1.0", Less),
+                ("1.0", "1.0a", Less),
+                ("1.0+dfsg", "1.0", Greater),
+                ("1.0", "1.0.1", Less),
+                ("1.0-1", "1.0-2", Less),
+                ("2:1.0", "1:9.9", Greater),
+                ("1.00", "1.0", Equal),
+                ("2.40-2ubuntu1", "2.40-10", Less),
+            ],
+        );
+    }
+
+    #[test]
+    fn upgrades_and_downgrades() {
+        let packages = |versions: &[(&str, &str)]| InstalledPackages {
+            manager: PackageManager::Pacman,
+            versions: versions
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect(),
+        };
+        let old = packages(&[
+            ("linux", "6.10rc1-1"),
+            ("openssl", "3.3.0-1"),
+            ("gone", "1-1"),
+        ]);
+        let new = packages(&[("linux", "6.10-1"), ("openssl", "3.2.0-1"), ("new", "1-1")]);
+        let kinds: Vec<(String, PackageChangeKind)> = diff_packages(&old, &new)
+            .unwrap()
+            .into_iter()
+            .map(|change| (change.name, change.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("linux".to_string(), PackageChangeKind::Upgraded),
+                ("openssl".to_string(), PackageChangeKind::Downgraded),
+                ("new".to_string(), PackageChangeKind::Added),
+                ("gone".to_string(), PackageChangeKind::Removed),
+            ]
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use super::btrfs::QgroupUsage;
+use super::{Device, DeviceType, Snapshot, SnapshotTag};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// What went wrong while reading a row of a timeshift table. The line number is counted from 1
+/// in the whole output, so it can be compared with what `timeshift --list` prints in a terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The row stopped before we could read `field`.
+    MissingField {
+        line: usize,
+        text: String,
+        field: &'static str,
+    },
+    /// `field` is there, but `value` is not what we expected.
+    InvalidField {
+        line: usize,
+        text: String,
+        field: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingField { line, text, field } => {
+                write!(f, "line {line}: missing {field} in {text:?}")
+            }
+            ParseError::InvalidField {
+                line,
+                text,
+                field,
+                value,
+            } => write!(f, "line {line}: invalid {field} {value:?} in {text:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The rows that could be read, and the errors for the ones that were skipped.
+#[derive(Debug, Clone)]
+pub struct Parsed<T> {
+    pub items: Vec<T>,
+    pub errors: Vec<ParseError>,
+}
+
+impl<T> From<Vec<T>> for Parsed<T> {
+    fn from(items: Vec<T>) -> Self {
+        Parsed {
+            items,
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// Parses the output of `timeshift --list-devices`.
+pub fn parse_devices(output: &str) -> Parsed<Device> {
+    parse_table(output, parse_device_row)
+}
+
+/// Parses the output of `timeshift --list`. When there is no snapshot, timeshift doesn't print
+/// the table at all, so we simply get an empty list.
+pub fn parse_snapshots(output: &str) -> Parsed<Snapshot> {
+    parse_table(output, parse_snapshot_row)
+}
+
+/// Parses the output of `btrfs qgroup show --raw`, which also puts dashes under its header.
+pub fn parse_qgroups(output: &str) -> Parsed<QgroupUsage> {
+    parse_table(output, parse_qgroup_row)
+}
+
+// I found out that the timeshift command always return dashes under the header of its tables,
+// so everything after the dashes is a row. Rows that cannot be read are kept as errors instead
+// of taking the whole TUI down with them.
+fn parse_table<T>(output: &str, parse_row: fn(&Row) -> Result<T, ParseError>) -> Parsed<T> {
+    let mut parsed = Parsed::from(Vec::new());
+    let lines: Vec<&str> = output.lines().collect();
+    let Some(dashes) = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("----"))
+    else {
+        return parsed;
+    };
+    // The header is right above the dashes, it tells where the columns start
+    let header = dashes
+        .checked_sub(1)
+        .and_then(|i| lines.get(i))
+        .copied()
+        .unwrap_or_default();
+    let rows = lines
+        .iter()
+        .enumerate()
+        .skip(dashes + 1) // pour ignorer la ligne des dashes
+        .filter(|(_, line)| !line.trim().is_empty()); // Filtre les lignes vides !
+    for (index, line) in rows {
+        let row = Row::new(index + 1, line, header);
+        match parse_row(&row) {
+            Ok(item) => parsed.items.push(item),
+            Err(error) => parsed.errors.push(error),
+        }
+    }
+    parsed
+}
+
+fn parse_device_row(row: &Row) -> Result<Device, ParseError> {
+    let mut cursor = row.cursor();
+    let num = cursor.num()?;
+    let device_name = cursor.next("device")?.to_string();
+    // The size is printed like "487.8 GB", so the unit ends up in its own field
+    let mut size = cursor.next("size")?.to_string();
+    if let Some(unit) = cursor.peek()
+        && is_size_unit(unit)
+    {
+        size = format!("{size} {unit}");
+        cursor.advance();
+    }
+    let device_type = DeviceType::parse(cursor.next("type")?);
+    let label = cursor.rest().to_string();
+    Ok(Device::new(num, device_name, size, device_type, label))
+}
+
+fn parse_snapshot_row(row: &Row) -> Result<Snapshot, ParseError> {
+    let mut cursor = row.cursor();
+    let num = cursor.num()?;
+    let name = cursor.next("name")?;
+    let date = Snapshot::date_from_name(name).ok_or_else(|| ParseError::InvalidField {
+        line: row.line,
+        text: row.text.to_string(),
+        field: "name",
+        value: name.to_string(),
+    })?;
+    // The tags column can be blank. Timeshift aligns its columns under the header, so we cut
+    // the row where they start : a description starting with `DB` is not mistaken for tags.
+    let (tags, description) = match (row.column("Tags"), row.column("Description")) {
+        (Some(tags_start), Some(description_start)) => {
+            let letters = row.slice(tags_start, Some(description_start)).trim();
+            let tags = if letters.is_empty() {
+                BTreeSet::new()
+            } else {
+                SnapshotTag::parse_set(letters).ok_or_else(|| ParseError::InvalidField {
+                    line: row.line,
+                    text: row.text.to_string(),
+                    field: "tags",
+                    value: letters.to_string(),
+                })?
+            };
+            (tags, row.slice(description_start, None).trim().to_string())
+        }
+        // Without a header we can only guess from the content
+        _ => {
+            let tags = match cursor.peek().and_then(SnapshotTag::parse_set) {
+                Some(tags) => {
+                    cursor.advance();
+                    tags
+                }
+                None => BTreeSet::new(),
+            };
+            (tags, cursor.rest().to_string())
+        }
+    };
+    Ok(Snapshot::new(
+        num,
+        name.to_string(),
+        date,
+        tags,
+        description,
+    ))
+}
+
+fn parse_qgroup_row(row: &Row) -> Result<QgroupUsage, ParseError> {
+    let mut cursor = row.cursor();
+    cursor.next("qgroupid")?;
+    let referenced = cursor.bytes("referenced")?;
+    let exclusive = cursor.bytes("exclusive")?;
+    Ok(QgroupUsage {
+        referenced,
+        exclusive,
+    })
+}
+
+fn is_size_unit(field: &str) -> bool {
+    matches!(
+        field,
+        "B" | "KB" | "MB" | "GB" | "TB" | "PB" | "KiB" | "MiB" | "GiB" | "TiB" | "PiB"
+    )
+}
+
+/// A line of a table, split on whitespace while remembering where each field starts, so the
+/// last column (label, description) can be taken as is, spaces included.
+struct Row<'a> {
+    line: usize,
+    text: &'a str,
+    fields: Vec<(usize, &'a str)>,
+    /// The header line of the table, empty if there was none
+    header: &'a str,
+}
+
+impl<'a> Row<'a> {
+    fn new(line: usize, text: &'a str, header: &'a str) -> Self {
+        let mut fields = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(s)) => {
+                    fields.push((s, &text[s..i]));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(s) = start {
+            fields.push((s, &text[s..]));
+        }
+        Row {
+            line,
+            text,
+            fields,
+            header,
+        }
+    }
+
+    /// Where the column named `name` starts, from the header.
+    fn column(&self, name: &str) -> Option<usize> {
+        self.header
+            .match_indices(name)
+            .map(|(start, _)| start)
+            .find(|&start| {
+                let before = self.header[..start].chars().next_back();
+                let after = self.header[start + name.len()..].chars().next();
+                before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+            })
+    }
+
+    /// The text of the row between two offsets of the header, empty when the row is shorter.
+    fn slice(&self, start: usize, end: Option<usize>) -> &'a str {
+        let end = end.unwrap_or(self.text.len()).min(self.text.len());
+        self.text.get(start..end).unwrap_or_default()
+    }
+
+    fn cursor(&self) -> RowCursor<'_, 'a> {
+        RowCursor {
+            row: self,
+            position: 0,
+        }
+    }
+}
+
+struct RowCursor<'r, 'a> {
+    row: &'r Row<'a>,
+    position: usize,
+}
+
+impl<'a> RowCursor<'_, 'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.row.fields.get(self.position).map(|(_, field)| *field)
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    fn next(&mut self, field: &'static str) -> Result<&'a str, ParseError> {
+        let value = self.peek().ok_or_else(|| ParseError::MissingField {
+            line: self.row.line,
+            text: self.row.text.to_string(),
+            field,
+        })?;
+        self.advance();
+        Ok(value)
+    }
+
+    /// Reads the number of the row, and the `>` timeshift prints right after it.
+    fn num(&mut self) -> Result<u32, ParseError> {
+        let value = self.next("num")?;
+        let num = value.parse::<u32>().map_err(|_| ParseError::InvalidField {
+            line: self.row.line,
+            text: self.row.text.to_string(),
+            field: "num",
+            value: value.to_string(),
+        })?;
+        if self.peek() == Some(">") {
+            self.advance();
+        }
+        Ok(num)
+    }
+
+    /// A size printed in bytes, like with `--raw`.
+    fn bytes(&mut self, field: &'static str) -> Result<u64, ParseError> {
+        let value = self.next(field)?;
+        value.parse::<u64>().map_err(|_| ParseError::InvalidField {
+            line: self.row.line,
+            text: self.row.text.to_string(),
+            field,
+            value: value.to_string(),
+        })
+    }
+
+    /// Everything left on the line, as printed.
+    fn rest(&self) -> &'a str {
+        match self.row.fields.get(self.position) {
+            Some((start, _)) => self.row.text[*start..].trim_end(),
+            None => "",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_after_the_mount_messages() {
+        let output = "\
+Mounted '/dev/sda2' at '/run/timeshift/12345/backup'
+Devices with Linux file systems:
+
+Num     Device     Size      Type   Label
+------------------------------------------------------------------------------
+0    >  /dev/sda2  487.8 GB  ext4
+1    >  /dev/sdb1  2 TB      btrfs  Backups disk
+
+";
+        let parsed = parse_devices(output);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let devices = parsed.items;
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].device_name, "/dev/sda2");
+        assert_eq!(devices[0].size, "487.8 GB");
+        assert_eq!(devices[0].device_type, DeviceType::Ext4);
+        assert_eq!(devices[0].label, "");
+        assert_eq!(devices[1].num, 1);
+        assert_eq!(devices[1].size, "2 TB");
+        assert_eq!(devices[1].device_type, DeviceType::Btrfs);
+        assert_eq!(devices[1].label, "Backups disk");
+    }
+
+    #[test]
+    fn snapshots_with_warnings_and_every_kind_of_tags() {
+        let output = "\
+** (process:4321): WARNING **: 10:00:00.000: Failed to query the size of /dev/sda2
+Mounted '/dev/sda2' at '/run/timeshift/4321/backup'
+Device : /dev/sda2
+Mode   : RSYNC
+Status : OK
+4 snapshots, 120.5 GB free
+
+Num     Name                 Tags  Description
+------------------------------------------------------------------------------
+0    >  2025-01-01_10-00-00  O     Before the upgrade
+1    >  2025-01-02_10-00-00  BDW
+2    >  2025-01-03_10-00-00        no tags at all
+3    >  not-a-date           O     broken
+";
+        let parsed = parse_snapshots(output);
+        let snapshots = parsed.items;
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[0].tags, BTreeSet::from([SnapshotTag::Ondemand]));
+        assert_eq!(snapshots[0].description, "Before the upgrade");
+        assert_eq!(
+            snapshots[1].tags,
+            BTreeSet::from([SnapshotTag::Boot, SnapshotTag::Daily, SnapshotTag::Weekly])
+        );
+        assert_eq!(snapshots[1].description, "");
+        assert!(snapshots[2].tags.is_empty());
+        assert_eq!(snapshots[2].description, "no tags at all");
+        // The broken row is reported with its line in the whole output
+        assert!(matches!(
+            parsed.errors.as_slice(),
+            [ParseError::InvalidField {
+                line: 13,
+                field: "name",
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn no_table_means_no_snapshot() {
+        let parsed = parse_snapshots("Mounted '/dev/sda2' at '/run/timeshift/backup'\n");
+        assert!(parsed.items.is_empty());
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn description_made_of_tag_letters() {
+        let output = "\
+Num     Name                 Tags  Description
+------------------------------------------------------------------------------
+0    >  2025-01-01_10-00-00        DB upgrade
+1    >  2025-01-02_10-00-00  OD    DB upgrade
+";
+        let parsed = parse_snapshots(output);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let snapshots = parsed.items;
+        assert!(snapshots[0].tags.is_empty());
+        assert_eq!(snapshots[0].description, "DB upgrade");
+        assert_eq!(
+            snapshots[1].tags,
+            BTreeSet::from([SnapshotTag::Ondemand, SnapshotTag::Daily])
+        );
+        assert_eq!(snapshots[1].description, "DB upgrade");
+    }
+}
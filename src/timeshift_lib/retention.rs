@@ -0,0 +1,511 @@
+use super::{Snapshot, SnapshotTag};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Local, TimeDelta};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Where the snapshots pinned in the TUI are kept, next to the settings of timeshift. Timeshift
+/// knows nothing about them, only our retention policy does.
+pub const PINS_PATH: &str = "/etc/timeshift/timeshift-tui-pins.json";
+
+/// A retention policy like the one timeshift applies to its scheduled snapshots, but for all
+/// of them : timeshift never expires the on-demand snapshots by itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// The newest snapshot of each of the last N days that have one
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    pub keep_monthly: u32,
+    /// Everything taken in the last N days
+    pub keep_within_days: u32,
+    /// The snapshots with a schedule tag, timeshift already expires those
+    pub keep_scheduled: bool,
+    /// The snapshots pinned by the user, whatever their age
+    pub keep_pinned: bool,
+    pub keep_commented: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 6,
+            keep_within_days: 1,
+            keep_scheduled: true,
+            keep_pinned: true,
+            keep_commented: true,
+        }
+    }
+}
+
+/// Gives the same key to all the dates of a day, a week or a month.
+type Period = fn(&DateTime<Local>) -> (i32, u32);
+
+/// Why the policy keeps a snapshot, the first rule that matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepReason {
+    /// The newest snapshot is always kept
+    Latest,
+    Pinned,
+    Recent,
+    Scheduled,
+    Commented,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl fmt::Display for KeepReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            KeepReason::Latest => "newest",
+            KeepReason::Pinned => "pinned",
+            KeepReason::Recent => "recent",
+            KeepReason::Scheduled => "scheduled",
+            KeepReason::Commented => "commented",
+            KeepReason::Daily => "daily",
+            KeepReason::Weekly => "weekly",
+            KeepReason::Monthly => "monthly",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+/// The settings of the policy, in the order of the policy popup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionField {
+    Daily,
+    Weekly,
+    Monthly,
+    WithinDays,
+    Scheduled,
+    Pinned,
+    Commented,
+}
+
+impl RetentionField {
+    pub const ALL: [RetentionField; 7] = [
+        RetentionField::Daily,
+        RetentionField::Weekly,
+        RetentionField::Monthly,
+        RetentionField::WithinDays,
+        RetentionField::Scheduled,
+        RetentionField::Pinned,
+        RetentionField::Commented,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RetentionField::Daily => "Daily snapshots kept",
+            RetentionField::Weekly => "Weekly snapshots kept",
+            RetentionField::Monthly => "Monthly snapshots kept",
+            RetentionField::WithinDays => "Keep everything younger than (days)",
+            RetentionField::Scheduled => "Keep the scheduled snapshots",
+            RetentionField::Pinned => "Keep the pinned snapshots",
+            RetentionField::Commented => "Keep the commented snapshots",
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn value(&self, field: RetentionField) -> String {
+        match field {
+            RetentionField::Daily => self.keep_daily.to_string(),
+            RetentionField::Weekly => self.keep_weekly.to_string(),
+            RetentionField::Monthly => self.keep_monthly.to_string(),
+            RetentionField::WithinDays => self.keep_within_days.to_string(),
+            RetentionField::Scheduled => yes_no(self.keep_scheduled),
+            RetentionField::Pinned => yes_no(self.keep_pinned),
+            RetentionField::Commented => yes_no(self.keep_commented),
+        }
+    }
+
+    /// `+` and `-` on the popup : the counts move by one, the flags are toggled.
+    pub fn adjust(&mut self, field: RetentionField, increase: bool) {
+        let count = match field {
+            RetentionField::Daily => &mut self.keep_daily,
+            RetentionField::Weekly => &mut self.keep_weekly,
+            RetentionField::Monthly => &mut self.keep_monthly,
+            RetentionField::WithinDays => &mut self.keep_within_days,
+            RetentionField::Scheduled => {
+                self.keep_scheduled = !self.keep_scheduled;
+                return;
+            }
+            RetentionField::Pinned => {
+                self.keep_pinned = !self.keep_pinned;
+                return;
+            }
+            RetentionField::Commented => {
+                self.keep_commented = !self.keep_commented;
+                return;
+            }
+        };
+        *count = if increase {
+            count.saturating_add(1)
+        } else {
+            count.saturating_sub(1)
+        };
+    }
+
+    /// The snapshots the policy keeps, by name with why. The other ones would be deleted.
+    /// `pinned` are the names of the snapshots the user pinned.
+    pub fn evaluate(
+        &self,
+        snapshots: &[&Snapshot],
+        pinned: &BTreeSet<String>,
+        now: DateTime<Local>,
+    ) -> HashMap<String, KeepReason> {
+        let mut snapshots = snapshots.to_vec();
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.date));
+        let mut kept = HashMap::new();
+        if let Some(newest) = snapshots.first() {
+            kept.insert(newest.name.clone(), KeepReason::Latest);
+        }
+        let within = TimeDelta::days(self.keep_within_days.into());
+        for snapshot in &snapshots {
+            let reason = if self.keep_pinned && pinned.contains(&snapshot.name) {
+                KeepReason::Pinned
+            } else if now.signed_duration_since(snapshot.date) < within {
+                KeepReason::Recent
+            } else if self.keep_scheduled
+                && snapshot
+                    .tags
+                    .iter()
+                    .any(|tag| *tag != SnapshotTag::Ondemand)
+            {
+                KeepReason::Scheduled
+            } else if self.keep_commented && !snapshot.description.trim().is_empty() {
+                KeepReason::Commented
+            } else {
+                continue;
+            };
+            kept.entry(snapshot.name.clone()).or_insert(reason);
+        }
+        // Going from the newest, the first snapshot of each period is the one kept for it
+        let periods: [(u32, KeepReason, Period); 3] = [
+            (self.keep_daily, KeepReason::Daily, |date| {
+                (date.year(), date.ordinal())
+            }),
+            (self.keep_weekly, KeepReason::Weekly, |date| {
+                (date.iso_week().year(), date.iso_week().week())
+            }),
+            (self.keep_monthly, KeepReason::Monthly, |date| {
+                (date.year(), date.month())
+            }),
+        ];
+        for (count, reason, period) in periods {
+            let mut seen = HashSet::new();
+            for snapshot in &snapshots {
+                if seen.len() >= count as usize {
+                    break;
+                }
+                if seen.insert(period(&snapshot.date)) {
+                    kept.entry(snapshot.name.clone()).or_insert(reason);
+                }
+            }
+        }
+        kept
+    }
+}
+
+fn yes_no(flag: bool) -> String {
+    if flag { "yes" } else { "no" }.to_string()
+}
+
+/// The names of the pinned snapshots, stored as a JSON list. Nothing is pinned until the file
+/// is written the first time.
+pub fn read_pins(path: &Path) -> Result<BTreeSet<String>> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeSet::new()),
+        Err(e) => return Err(e).with_context(|| format!("Couldn't read {}", path.display())),
+    };
+    serde_json::from_str(&json).with_context(|| format!("Couldn't parse {}", path.display()))
+}
+
+/// Writes the pins through a temporary file renamed over the old one, like the settings.
+pub fn write_pins(path: &Path, pins: &BTreeSet<String>) -> Result<()> {
+    let temporary = path.with_extension("json.tmp");
+    let json = serde_json::to_string_pretty(pins)?;
+    fs::write(&temporary, json)
+        .with_context(|| format!("Couldn't write {}", temporary.display()))?;
+    fs::rename(&temporary, path).with_context(|| format!("Couldn't replace {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps nothing but the newest, the tests turn on what they look at.
+    const NOTHING: RetentionPolicy = RetentionPolicy {
+        keep_daily: 0,
+        keep_weekly: 0,
+        keep_monthly: 0,
+        keep_within_days: 0,
+        keep_scheduled: false,
+        keep_pinned: false,
+        keep_commented: false,
+    };
+
+    fn snapshot(name: &str, tags: &[SnapshotTag], comment: &str) -> Snapshot {
+        Snapshot::new(
+            0,
+            name.to_string(),
+            Snapshot::date_from_name(name).unwrap(),
+            tags.iter().copied().collect::<BTreeSet<_>>(),
+            comment.to_string(),
+        )
+    }
+
+    fn ondemand(name: &str) -> Snapshot {
+        snapshot(name, &[SnapshotTag::Ondemand], "")
+    }
+
+    /// A year after the snapshots, so nothing is recent.
+    fn later() -> DateTime<Local> {
+        Snapshot::date_from_name("2026-06-01_00-00-00").unwrap()
+    }
+
+    fn kept(
+        policy: &RetentionPolicy,
+        snapshots: &[Snapshot],
+        now: DateTime<Local>,
+    ) -> Vec<(String, KeepReason)> {
+        kept_with_pins(policy, snapshots, &BTreeSet::new(), now)
+    }
+
+    fn kept_with_pins(
+        policy: &RetentionPolicy,
+        snapshots: &[Snapshot],
+        pinned: &BTreeSet<String>,
+        now: DateTime<Local>,
+    ) -> Vec<(String, KeepReason)> {
+        let snapshots: Vec<&Snapshot> = snapshots.iter().collect();
+        let mut kept: Vec<(String, KeepReason)> = policy
+            .evaluate(&snapshots, pinned, now)
+            .into_iter()
+            .collect();
+        kept.sort_by(|a, b| a.0.cmp(&b.0));
+        kept
+    }
+
+    fn expected(kept: &[(&str, KeepReason)]) -> Vec<(String, KeepReason)> {
+        let mut kept: Vec<(String, KeepReason)> = kept
+            .iter()
+            .map(|(name, reason)| (name.to_string(), *reason))
+            .collect();
+        kept.sort_by(|a, b| a.0.cmp(&b.0));
+        kept
+    }
+
+    #[test]
+    fn newest_is_always_kept() {
+        let snapshots = [
+            ondemand("2025-01-01_10-00-00"),
+            ondemand("2025-03-01_10-00-00"),
+            ondemand("2025-02-01_10-00-00"),
+        ];
+        assert_eq!(
+            kept(&NOTHING, &snapshots, later()),
+            expected(&[("2025-03-01_10-00-00", KeepReason::Latest)])
+        );
+        assert!(kept(&NOTHING, &[], later()).is_empty());
+    }
+
+    #[test]
+    fn daily_keeps_the_newest_of_each_day() {
+        let policy = RetentionPolicy {
+            keep_daily: 3,
+            ..NOTHING
+        };
+        // Same day of the year, but not the same year
+        let snapshots = [
+            ondemand("2024-01-01_20-00-00"),
+            ondemand("2024-01-01_08-00-00"),
+            ondemand("2023-01-01_10-00-00"),
+            ondemand("2022-12-31_10-00-00"),
+            ondemand("2022-12-30_10-00-00"),
+        ];
+        assert_eq!(
+            kept(&policy, &snapshots, later()),
+            expected(&[
+                ("2024-01-01_20-00-00", KeepReason::Latest),
+                ("2023-01-01_10-00-00", KeepReason::Daily),
+                ("2022-12-31_10-00-00", KeepReason::Daily),
+            ])
+        );
+    }
+
+    #[test]
+    fn weekly_follows_iso_weeks_across_the_new_year() {
+        let policy = RetentionPolicy {
+            keep_weekly: 2,
+            ..NOTHING
+        };
+        // 2024-12-30 is in the first week of 2025, like 2025-01-02
+        let snapshots = [
+            ondemand("2025-01-02_10-00-00"),
+            ondemand("2024-12-30_10-00-00"),
+            ondemand("2024-12-27_10-00-00"),
+            ondemand("2024-12-20_10-00-00"),
+        ];
+        assert_eq!(
+            kept(&policy, &snapshots, later()),
+            expected(&[
+                ("2025-01-02_10-00-00", KeepReason::Latest),
+                ("2024-12-27_10-00-00", KeepReason::Weekly),
+            ])
+        );
+    }
+
+    #[test]
+    fn monthly_tells_the_years_apart() {
+        let policy = RetentionPolicy {
+            keep_monthly: 3,
+            ..NOTHING
+        };
+        let snapshots = [
+            ondemand("2025-01-15_10-00-00"),
+            ondemand("2025-01-03_10-00-00"),
+            ondemand("2024-12-20_10-00-00"),
+            ondemand("2023-12-20_10-00-00"),
+            ondemand("2023-11-20_10-00-00"),
+        ];
+        assert_eq!(
+            kept(&policy, &snapshots, later()),
+            expected(&[
+                ("2025-01-15_10-00-00", KeepReason::Latest),
+                ("2024-12-20_10-00-00", KeepReason::Monthly),
+                ("2023-12-20_10-00-00", KeepReason::Monthly),
+            ])
+        );
+    }
+
+    #[test]
+    fn recent_snapshots_are_kept() {
+        let policy = RetentionPolicy {
+            keep_within_days: 2,
+            ..NOTHING
+        };
+        let now = Snapshot::date_from_name("2025-01-10_12-00-00").unwrap();
+        let snapshots = [
+            ondemand("2025-01-10_10-00-00"),
+            ondemand("2025-01-09_00-00-00"),
+            ondemand("2025-01-08_11-00-00"),
+        ];
+        assert_eq!(
+            kept(&policy, &snapshots, now),
+            expected(&[
+                ("2025-01-10_10-00-00", KeepReason::Latest),
+                ("2025-01-09_00-00-00", KeepReason::Recent),
+            ])
+        );
+    }
+
+    #[test]
+    fn scheduled_and_commented_flags() {
+        let snapshots = [
+            ondemand("2025-01-05_10-00-00"),
+            snapshot("2025-01-04_10-00-00", &[SnapshotTag::Daily], ""),
+            snapshot(
+                "2025-01-03_10-00-00",
+                &[SnapshotTag::Ondemand],
+                "before kernel",
+            ),
+            snapshot(
+                "2025-01-02_10-00-00",
+                &[SnapshotTag::Boot, SnapshotTag::Ondemand],
+                "",
+            ),
+            snapshot("2025-01-01_10-00-00", &[SnapshotTag::Ondemand], "   "),
+        ];
+        let policy = RetentionPolicy {
+            keep_scheduled: true,
+            keep_commented: true,
+            ..NOTHING
+        };
+        assert_eq!(
+            kept(&policy, &snapshots, later()),
+            expected(&[
+                ("2025-01-05_10-00-00", KeepReason::Latest),
+                ("2025-01-04_10-00-00", KeepReason::Scheduled),
+                ("2025-01-03_10-00-00", KeepReason::Commented),
+                ("2025-01-02_10-00-00", KeepReason::Scheduled),
+            ])
+        );
+        assert_eq!(
+            kept(&NOTHING, &snapshots, later()),
+            expected(&[("2025-01-05_10-00-00", KeepReason::Latest)])
+        );
+    }
+
+    #[test]
+    fn pinned_snapshots_are_kept() {
+        let snapshots = [
+            ondemand("2025-01-03_10-00-00"),
+            ondemand("2025-01-02_10-00-00"),
+            snapshot("2025-01-01_10-00-00", &[SnapshotTag::Ondemand], "a comment"),
+        ];
+        // A pin on a snapshot that is gone changes nothing
+        let pinned = BTreeSet::from([
+            "2025-01-01_10-00-00".to_string(),
+            "2024-12-01_10-00-00".to_string(),
+        ]);
+        let policy = RetentionPolicy {
+            keep_pinned: true,
+            keep_commented: true,
+            ..NOTHING
+        };
+        assert_eq!(
+            kept_with_pins(&policy, &snapshots, &pinned, later()),
+            expected(&[
+                ("2025-01-03_10-00-00", KeepReason::Latest),
+                ("2025-01-01_10-00-00", KeepReason::Pinned),
+            ])
+        );
+        assert_eq!(
+            kept_with_pins(&NOTHING, &snapshots, &pinned, later()),
+            expected(&[("2025-01-03_10-00-00", KeepReason::Latest)])
+        );
+    }
+
+    #[test]
+    fn pins_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("timeshift-tui-pins-{}.json", std::process::id()));
+        assert!(read_pins(&path).unwrap().is_empty());
+        let pins = BTreeSet::from(["2025-01-01_10-00-00".to_string()]);
+        write_pins(&path, &pins).unwrap();
+        assert_eq!(read_pins(&path).unwrap(), pins);
+        fs::write(&path, "{").unwrap();
+        assert!(read_pins(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn no_daily_snapshots() {
+        let policy = RetentionPolicy {
+            keep_daily: 0,
+            keep_monthly: 2,
+            ..NOTHING
+        };
+        let snapshots = [
+            ondemand("2025-02-03_10-00-00"),
+            ondemand("2025-02-02_10-00-00"),
+            ondemand("2025-02-01_10-00-00"),
+            ondemand("2025-01-31_10-00-00"),
+            ondemand("2025-01-30_10-00-00"),
+        ];
+        assert_eq!(
+            kept(&policy, &snapshots, later()),
+            expected(&[
+                ("2025-02-03_10-00-00", KeepReason::Latest),
+                ("2025-01-31_10-00-00", KeepReason::Monthly),
+            ])
+        );
+    }
+}
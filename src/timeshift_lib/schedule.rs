@@ -0,0 +1,117 @@
+use super::{ConfigField, SnapshotTag, TimeshiftConfig};
+use chrono::{DateTime, DurationRound, Local, Months, TimeDelta};
+use std::path::Path;
+
+/// The cron jobs timeshift installs for its scheduled snapshots.
+pub const HOURLY_CRON_JOB: &str = "/etc/cron.d/timeshift-hourly";
+pub const BOOT_CRON_JOB: &str = "/etc/cron.d/timeshift-boot";
+
+/// Which of the cron jobs of timeshift are installed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CronJobs {
+    pub hourly: bool,
+    pub boot: bool,
+}
+
+impl CronJobs {
+    pub fn from_system() -> Self {
+        CronJobs {
+            hourly: Path::new(HOURLY_CRON_JOB).exists(),
+            boot: Path::new(BOOT_CRON_JOB).exists(),
+        }
+    }
+}
+
+/// The levels of scheduled snapshots of timeshift, each one with its own tag and retention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleLevel {
+    Monthly,
+    Weekly,
+    Daily,
+    Hourly,
+    Boot,
+}
+
+impl ScheduleLevel {
+    /// Same order as in the GTK app.
+    pub const ALL: [ScheduleLevel; 5] = [
+        ScheduleLevel::Monthly,
+        ScheduleLevel::Weekly,
+        ScheduleLevel::Daily,
+        ScheduleLevel::Hourly,
+        ScheduleLevel::Boot,
+    ];
+
+    pub fn tag(self) -> SnapshotTag {
+        match self {
+            ScheduleLevel::Monthly => SnapshotTag::Monthly,
+            ScheduleLevel::Weekly => SnapshotTag::Weekly,
+            ScheduleLevel::Daily => SnapshotTag::Daily,
+            ScheduleLevel::Hourly => SnapshotTag::Hourly,
+            ScheduleLevel::Boot => SnapshotTag::Boot,
+        }
+    }
+
+    /// `schedule_*` in timeshift.json
+    pub fn schedule_field(self) -> ConfigField {
+        match self {
+            ScheduleLevel::Monthly => ConfigField::ScheduleMonthly,
+            ScheduleLevel::Weekly => ConfigField::ScheduleWeekly,
+            ScheduleLevel::Daily => ConfigField::ScheduleDaily,
+            ScheduleLevel::Hourly => ConfigField::ScheduleHourly,
+            ScheduleLevel::Boot => ConfigField::ScheduleBoot,
+        }
+    }
+
+    /// `count_*` in timeshift.json
+    pub fn count_field(self) -> ConfigField {
+        match self {
+            ScheduleLevel::Monthly => ConfigField::CountMonthly,
+            ScheduleLevel::Weekly => ConfigField::CountWeekly,
+            ScheduleLevel::Daily => ConfigField::CountDaily,
+            ScheduleLevel::Hourly => ConfigField::CountHourly,
+            ScheduleLevel::Boot => ConfigField::CountBoot,
+        }
+    }
+
+    pub fn is_enabled(self, config: &TimeshiftConfig) -> bool {
+        config.flag(self.schedule_field()).unwrap_or(false)
+    }
+
+    pub fn count(self, config: &TimeshiftConfig) -> u32 {
+        config.count(self.count_field()).unwrap_or(0)
+    }
+
+    /// When timeshift should take the next snapshot of this level, `None` for the boot ones
+    /// which are taken at the next boot. The cron job of timeshift checks at the start of every
+    /// hour if a snapshot is due, a level is due when its last snapshot is older than its
+    /// interval (or when there is none yet).
+    pub fn next_snapshot(
+        self,
+        last: Option<DateTime<Local>>,
+        now: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        let due = match last {
+            Some(last) => match self {
+                ScheduleLevel::Monthly => last.checked_add_months(Months::new(1))?,
+                ScheduleLevel::Weekly => last + TimeDelta::weeks(1),
+                ScheduleLevel::Daily => last + TimeDelta::days(1),
+                ScheduleLevel::Hourly => last + TimeDelta::hours(1),
+                ScheduleLevel::Boot => return None,
+            },
+            None if self == ScheduleLevel::Boot => return None,
+            None => now,
+        };
+        next_cron_run(due.max(now))
+    }
+}
+
+/// The first start of an hour at or after `time`.
+fn next_cron_run(time: DateTime<Local>) -> Option<DateTime<Local>> {
+    let hour = time.duration_trunc(TimeDelta::hours(1)).ok()?;
+    if hour == time {
+        Some(hour)
+    } else {
+        Some(hour + TimeDelta::hours(1))
+    }
+}
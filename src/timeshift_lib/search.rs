@@ -0,0 +1,63 @@
+use super::Snapshot;
+
+/// How well `query` matches `text`, `None` when it doesn't. The letters of the query have to be
+/// found in order, not next to each other, so `krnl` finds `kernel`. Letters that follow each
+/// other or start a word count more, like in the fuzzy finders of the editors.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars() {
+        let found = position + text[position..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Matches the name, the comment and the tags of the snapshot. Every word of the query has to
+/// match one of them, the best one counts.
+pub fn search_score(snapshot: &Snapshot, query: &str) -> Option<u32> {
+    let tags: Vec<&str> = snapshot.tags.iter().map(|tag| tag.word()).collect();
+    let tags = tags.join(" ");
+    let fields = [snapshot.name.as_str(), &snapshot.description, &tags];
+    query.split_whitespace().try_fold(0, |total, word| {
+        let best = fields
+            .iter()
+            .filter_map(|field| fuzzy_score(word, field))
+            .max()?;
+        Some(total + best)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_in_order() {
+        assert!(fuzzy_score("krnl", "linux kernel upgrade").is_some());
+        assert!(fuzzy_score("KERNEL", "Kernel").is_some());
+        assert_eq!(fuzzy_score("lnrk", "kernel"), None);
+        assert_eq!(fuzzy_score("kernelx", "kernel"), None);
+        assert_eq!(fuzzy_score("", "kernel"), Some(0));
+    }
+
+    #[test]
+    fn close_letters_and_word_starts_count_more() {
+        let together = fuzzy_score("up", "before upgrade").unwrap();
+        let apart = fuzzy_score("up", "before usual package").unwrap();
+        assert!(together > apart);
+        let word_start = fuzzy_score("g", "go").unwrap();
+        let inside = fuzzy_score("g", "ago").unwrap();
+        assert!(word_start > inside);
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::{Context, Result, bail};
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// How many of the newest snapshots are averaged to guess how much the next one will take.
+pub const GROWTH_SAMPLE: usize = 5;
+
+/// The space on a snapshot device, read with statvfs on where it is mounted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DeviceSpace {
+    pub total: u64,
+    pub used: u64,
+    /// What can still be written, the blocks reserved for root are not counted
+    pub free: u64,
+}
+
+impl DeviceSpace {
+    // The fields of statvfs aren't u64 on every target
+    #[allow(clippy::unnecessary_cast)]
+    pub fn read(path: &Path) -> Result<Self> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .with_context(|| format!("Invalid path {}", path.display()))?;
+        let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: the path is a valid C string and statvfs fills the struct when it returns 0
+        let stats = unsafe {
+            if libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) != 0 {
+                bail!(
+                    "statvfs failed on {}: {}",
+                    path.display(),
+                    std::io::Error::last_os_error()
+                );
+            }
+            stats.assume_init()
+        };
+        let block = stats.f_frsize as u64;
+        Ok(DeviceSpace {
+            total: stats.f_blocks as u64 * block,
+            used: (stats.f_blocks - stats.f_bfree) as u64 * block,
+            free: stats.f_bavail as u64 * block,
+        })
+    }
+
+    /// Between 0 and 1, for the gauge.
+    pub fn used_ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.used as f64 / self.total as f64).clamp(0.0, 1.0)
+    }
+
+    /// How many snapshots of `growth` bytes still fit, `None` when they don't take any space.
+    pub fn snapshots_that_fit(&self, growth: u64) -> Option<u64> {
+        self.free.checked_div(growth)
+    }
+}
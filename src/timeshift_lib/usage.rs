@@ -0,0 +1,150 @@
+use super::operation::{CancelHandle, Cancelled};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// How much space a rsync snapshot takes. Timeshift hard links the files that didn't change
+/// since the previous snapshot, so most of the apparent size is shared with the other snapshots.
+/// `unique` is what deleting this snapshot alone would free.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SnapshotSize {
+    /// Size of the files, every inode counted once
+    pub apparent: u64,
+    /// Disk space of the inodes that no other snapshot links to
+    pub unique: u64,
+}
+
+/// Walks the folder of a snapshot and counts the links of every inode. An inode is only owned by
+/// this snapshot when all of its links were found inside of it. We don't follow symlinks and we
+/// stay on the filesystem of the snapshot.
+pub fn measure(snapshot_dir: &Path, cancel: &CancelHandle) -> Result<SnapshotSize> {
+    let root = fs::symlink_metadata(snapshot_dir)
+        .with_context(|| format!("Couldn't read {}", snapshot_dir.display()))?;
+    // (links found, links in total, disk space) by inode
+    let mut inodes: HashMap<u64, (u64, u64, u64)> = HashMap::new();
+    let mut size = SnapshotSize::default();
+    let mut folders = vec![snapshot_dir.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        if cancel.is_cancelled() {
+            return Err(Cancelled {
+                details: format!("stopped measuring {}", snapshot_dir.display()),
+            }
+            .into());
+        }
+        // Some folders can't be read (lost+found...), they are simply not counted
+        let Ok(entries) = fs::read_dir(&folder) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.dev() != root.dev() {
+                continue;
+            }
+            let disk = metadata.blocks() * 512;
+            if metadata.is_dir() {
+                // Folders can't be hard linked, they always belong to the snapshot
+                size.unique += disk;
+                folders.push(entry.path());
+                continue;
+            }
+            let links = inodes
+                .entry(metadata.ino())
+                .or_insert((0, metadata.nlink(), disk));
+            if links.0 == 0 {
+                size.apparent += metadata.len();
+            }
+            links.0 += 1;
+        }
+    }
+    size.unique += inodes
+        .values()
+        .filter(|(found, total, _)| found >= total)
+        .map(|(_, _, disk)| disk)
+        .sum::<u64>();
+    Ok(size)
+}
+
+/// Sizes with the biggest unit that keeps the number above 1, like `12.3 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("timeshift-tui-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn hard_links_are_shared_between_snapshots() {
+        let root = temp_dir("usage");
+        let (first, second) = (root.join("first"), root.join("second"));
+        fs::create_dir_all(first.join("etc")).unwrap();
+        fs::create_dir_all(second.join("etc")).unwrap();
+        // Unchanged since the first snapshot : linked in both
+        fs::write(first.join("etc/shared"), vec![b'a'; 10_000]).unwrap();
+        fs::hard_link(first.join("etc/shared"), second.join("etc/shared")).unwrap();
+        // Only in the second snapshot, linked twice inside of it
+        fs::write(second.join("etc/own"), vec![b'b'; 5_000]).unwrap();
+        fs::hard_link(second.join("etc/own"), second.join("etc/own-link")).unwrap();
+
+        let size = measure(&second, &CancelHandle::default()).unwrap();
+        // Every inode counted once
+        assert_eq!(size.apparent, 15_000);
+        let disk = |path: &Path| fs::symlink_metadata(path).unwrap().blocks() * 512;
+        // The snapshot folder itself isn't counted, only what is inside of it
+        let own = disk(&second.join("etc")) + disk(&second.join("etc/own"));
+        assert_eq!(size.unique, own);
+
+        // Once the second snapshot is gone, the shared file belongs to the first alone
+        fs::remove_dir_all(&second).unwrap();
+        let size = measure(&first, &CancelHandle::default()).unwrap();
+        assert_eq!(size.apparent, 10_000);
+        assert_eq!(
+            size.unique,
+            disk(&first.join("etc")) + disk(&first.join("etc/shared"))
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn missing_snapshot_is_an_error() {
+        assert!(
+            measure(
+                Path::new("/nonexistent/timeshift-tui"),
+                &CancelHandle::default()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn cancelled_measure_stops() {
+        let cancel = CancelHandle::default();
+        cancel.cancel();
+        let error = measure(&std::env::temp_dir(), &cancel).unwrap_err();
+        assert!(error.is::<Cancelled>());
+    }
+}
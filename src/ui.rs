@@ -1,5 +1,11 @@
+pub mod browser;
+pub mod details;
 pub mod devices;
+pub mod diff;
 pub mod popups;
+pub mod report;
+pub mod schedule;
+pub mod settings;
 pub mod snapshots;
 pub mod widgets;
 
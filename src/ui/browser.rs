@@ -0,0 +1,148 @@
+use crate::app::App;
+use crate::timeshift_lib::{Backend, FileEntry, FileKind, Preview, format_bytes};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap},
+};
+
+impl<B: Backend> App<B> {
+    /// The folder being browsed on the left, the selected file on the right.
+    pub fn render_browser(&self, area: Rect, buf: &mut Buffer) {
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        let [files_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(area);
+        let instructions = Line::from(vec![
+            " Mark ".into(),
+            " <Space> ".blue().bold(),
+            " Restore ".into(),
+            " <R> ".blue().bold(),
+            " Diff ".into(),
+            " <D> ".blue().bold(),
+            " Up ".into(),
+            " <Q> ".blue().bold(),
+            " Close ".into(),
+            " <Esc> ".blue().bold(),
+        ]);
+        let mut block = Block::bordered()
+            .title(format!(
+                "{} : /{}",
+                browser.snapshot_name,
+                browser.dir.display()
+            ))
+            .title(Line::from(" Read-only ".black().on_yellow().bold()).right_aligned())
+            .title_bottom(instructions.centered());
+        if !browser.marked.is_empty() {
+            block = block.title(Line::from(
+                format!(" {} marked ", browser.marked.len()).green().bold(),
+            ));
+        }
+
+        let entries = match &browser.entries {
+            Ok(entries) => entries,
+            Err(error) => {
+                Paragraph::new(Line::from(error.clone()).red())
+                    .wrap(Wrap { trim: false })
+                    .block(block)
+                    .render(files_area, buf);
+                return;
+            }
+        };
+        if entries.is_empty() {
+            Paragraph::new("Empty folder")
+                .block(block)
+                .render(files_area, buf);
+        } else {
+            let rows: Vec<Row> = entries
+                .iter()
+                .map(|entry| {
+                    file_row(
+                        entry,
+                        browser.marked.contains(&browser.dir.join(&entry.name)),
+                    )
+                })
+                .collect();
+            let widths = [
+                Constraint::Length(2),
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(16),
+                Constraint::Length(10),
+            ];
+            let table = Table::new(rows, widths)
+                .header(Row::new(vec!["", "Name", "Size", "Modified", "Mode"]).bold())
+                .block(block)
+                .row_highlight_style(Style::default().bg(Color::Blue));
+            let mut state = TableState::default().with_selected(Some(self.current_index));
+            StatefulWidget::render(table, files_area, buf, &mut state);
+        }
+        self.render_file_preview(entries.get(self.current_index), preview_area, buf);
+    }
+
+    fn render_file_preview(&self, entry: Option<&FileEntry>, area: Rect, buf: &mut Buffer) {
+        let Some(entry) = entry else {
+            Block::bordered().title("Preview").render(area, buf);
+            return;
+        };
+        let block = Block::bordered().title(format!("Preview : {}", entry.name));
+        let preview = self.browser.as_ref().and_then(|browser| {
+            let path = browser.dir.join(&entry.name);
+            browser.preview.as_ref().filter(|(p, _)| *p == path)
+        });
+        let paragraph = match (&entry.kind, preview) {
+            (FileKind::Directory, _) => Paragraph::new("Folder, <Enter> to open it".dark_gray()),
+            (FileKind::Symlink(target), _) => {
+                Paragraph::new(format!("Link to {}", target.display()).cyan())
+            }
+            (FileKind::Other, _) => Paragraph::new("Special file (device, socket...)".dark_gray()),
+            (FileKind::File, Some((_, Ok(Preview::Text(text))))) => Paragraph::new(text.as_str()),
+            (FileKind::File, Some((_, Ok(Preview::Binary)))) => {
+                Paragraph::new("Binary file".dark_gray())
+            }
+            (FileKind::File, Some((_, Ok(Preview::TooBig(size))))) => {
+                Paragraph::new(format!("Too big to preview ({})", format_bytes(*size)).dark_gray())
+            }
+            (FileKind::File, Some((_, Err(error)))) => {
+                Paragraph::new(Line::from(error.clone()).red()).wrap(Wrap { trim: false })
+            }
+            (FileKind::File, None) => Paragraph::new("Reading...".dark_gray()),
+        };
+        paragraph.block(block).render(area, buf);
+    }
+}
+
+fn file_row(entry: &FileEntry, marked: bool) -> Row<'static> {
+    let mark = if marked {
+        "●".green().bold()
+    } else {
+        "".into()
+    };
+    let name = match &entry.kind {
+        FileKind::Directory => Cell::from(format!("{}/", entry.name).bold()),
+        FileKind::Symlink(target) => {
+            Cell::from(format!("{} -> {}", entry.name, target.display()).cyan())
+        }
+        _ => Cell::from(entry.name.clone()),
+    };
+    let size = if entry.kind == FileKind::File {
+        format_bytes(entry.size)
+    } else {
+        String::new()
+    };
+    let modified = entry
+        .modified
+        .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    Row::new(vec![
+        Cell::from(mark),
+        name,
+        Cell::from(size),
+        Cell::from(modified),
+        Cell::from(entry.permissions()),
+    ])
+}
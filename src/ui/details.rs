@@ -0,0 +1,151 @@
+use super::snapshots::{format_age, tag_badge};
+use crate::app::App;
+use crate::timeshift_lib::{
+    Backend, PackageChange, PackageChangeKind, SnapshotInfo, Subvolume, format_bytes,
+};
+use chrono::Local;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget, Wrap},
+};
+
+impl<B: Backend> App<B> {
+    /// Everything timeshift knows about the selected snapshot, read from its `info.json`.
+    pub fn render_snapshot_details(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered().title("Details");
+        let mut lines = match self.current_details() {
+            Some(Ok(info)) => info_lines(info),
+            Some(Err(error)) => vec![
+                Line::from("Couldn't read the info.json of this snapshot :").red(),
+                Line::from(""),
+                Line::from(error.clone()),
+            ],
+            None if self.current_snapshot().is_none() => vec![Line::from("No snapshot selected")],
+            None => vec![Line::from("Reading info.json...")],
+        };
+        match self.current_subvolumes() {
+            Some(Ok(subvolumes)) => lines.extend(subvolume_lines(subvolumes)),
+            Some(Err(error)) => {
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!("Couldn't read the subvolumes : {error}")).red());
+            }
+            None => {}
+        }
+        match self.current_packages() {
+            Some(Ok(changes)) => lines.extend(package_lines(changes)),
+            Some(Err(error)) => {
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!("Couldn't compare the packages : {error}")).red());
+            }
+            // Forgotten after a refresh, they are being compared again
+            None if self.current_details().is_some() => {
+                lines.push(Line::from(""));
+                lines.push(Line::from("Comparing the packages...").dark_gray());
+            }
+            None => {}
+        }
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+fn info_lines(info: &SnapshotInfo) -> Vec<Line<'static>> {
+    let created = match info.created {
+        Some(date) => format!(
+            "{} ({})",
+            date.format("%Y-%m-%d %H:%M:%S"),
+            format_age(Local::now().signed_duration_since(date))
+        ),
+        None => "unknown".to_string(),
+    };
+    let mut tags = vec![field_label("Tags")];
+    tags.extend(info.tags.iter().map(|tag| tag_badge(*tag)));
+
+    let mut lines = vec![
+        field("Created", created),
+        field("Distro", info.sys_distro.clone()),
+        field("System UUID", info.sys_uuid.clone()),
+        field("Timeshift", info.app_version.clone()),
+        Line::from(tags),
+        field("Comments", info.comments.clone()),
+    ];
+    if !info.other.is_empty() {
+        lines.push(Line::from(""));
+        lines.extend(
+            info.other
+                .iter()
+                .map(|(key, value)| field(key, value.clone())),
+        );
+    }
+    lines
+}
+
+/// The subvolumes of a btrfs snapshot, with their qgroup sizes when quotas are on.
+fn subvolume_lines(subvolumes: &[Subvolume]) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(""), Line::from("Subvolumes".yellow().bold())];
+    for subvolume in subvolumes {
+        let usage = match subvolume.usage {
+            Some(usage) => format!(
+                "{} exclusive, {} referenced",
+                format_bytes(usage.exclusive),
+                format_bytes(usage.referenced)
+            ),
+            None => "no size, quotas are disabled".to_string(),
+        };
+        lines.push(field(&subvolume.name, usage));
+    }
+    lines
+}
+
+/// What the package manager did between the snapshot and now, to find the last snapshot
+/// taken before an upgrade that broke something.
+fn package_lines(changes: &[PackageChange]) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(""),
+        Line::from("Packages since this snapshot".yellow().bold()),
+    ];
+    if changes.is_empty() {
+        lines.push(Line::from("Same packages as the running system"));
+        return lines;
+    }
+    let count = |kind| changes.iter().filter(|change| change.kind == kind).count();
+    lines.push(Line::from(format!(
+        "{} upgraded, {} downgraded, {} added, {} removed",
+        count(PackageChangeKind::Upgraded),
+        count(PackageChangeKind::Downgraded),
+        count(PackageChangeKind::Added),
+        count(PackageChangeKind::Removed)
+    )));
+    for change in changes {
+        let old = change.old.clone().unwrap_or_default();
+        let new = change.new.clone().unwrap_or_default();
+        let (sign, versions) = match change.kind {
+            PackageChangeKind::Upgraded => ("↑ ".green(), format!("{old} → {new}")),
+            PackageChangeKind::Downgraded => ("↓ ".yellow(), format!("{old} → {new}")),
+            PackageChangeKind::Added => ("+ ".green(), new),
+            PackageChangeKind::Removed => ("- ".red(), old),
+        };
+        lines.push(Line::from(vec![
+            sign,
+            Span::raw(format!("{} ", change.name)).bold(),
+            Span::raw(versions).gray(),
+        ]));
+    }
+    lines
+}
+
+fn field_label(label: &str) -> Span<'static> {
+    Span::styled(
+        format!("{label:<13}: "),
+        Style::default().fg(Color::Yellow).bold(),
+    )
+}
+
+fn field(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![field_label(label), Span::raw(value)])
+}
@@ -1,44 +1,220 @@
-use crate::app::App;
+use super::widgets::fit_columns;
+use crate::app::{App, DeviceColumn, GrowthEstimate, SortOrder};
+use crate::timeshift_lib::{Backend, Device, DeviceSpace, format_bytes};
 use ratatui::{
     buffer::Buffer,
-    layout::Rect,
+    layout::{Constraint, Layout, Rect},
     style::{Color, Style, Stylize},
     text::Line,
-    widgets::{Block, List, ListItem, Widget},
+    widgets::{
+        Block, Cell, Gauge, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap,
+    },
 };
 
-impl App {
+/// Under this many snapshots of room left, the projection is shown in red.
+const LOW_ROOM: u64 = 5;
+
+impl<B: Backend> App<B> {
     pub fn render_devices(&self, area: Rect, buf: &mut Buffer) {
         let instructions = Line::from(vec![
             " Choose a device ".into(),
             " <Enter> ".blue().bold(),
+            " Schedule ".into(),
+            " <S> ".blue().bold(),
+            " Settings ".into(),
+            " <O> ".blue().bold(),
+            " Export ".into(),
+            " <E> ".blue().bold(),
             " Quit ".into(),
             " <Q> ".blue().bold(),
         ]);
-        // Conversion en string pour le rendering
-        let items: Vec<ListItem> = self
-            .timeshift_instance
-            .devices_map
+        // On a narrow terminal the label, the type and the size go first, the borders take 2
+        let columns = fit_columns(
+            &DeviceColumn::ALL,
+            &[DeviceColumn::Label, DeviceColumn::Type, DeviceColumn::Size],
+            area.width.saturating_sub(2),
+            |column| match column {
+                DeviceColumn::Num => 3,
+                DeviceColumn::Name => 10,
+                DeviceColumn::Size => 10,
+                DeviceColumn::Type => 6,
+                DeviceColumn::Label => 8,
+                DeviceColumn::Mount => 12,
+                DeviceColumn::Snapshots => 11,
+            },
+        );
+        let rows: Vec<Row> = self
+            .visible_devices()
+            .into_iter()
+            .map(|device| {
+                let snapshots = self
+                    .timeshift_instance
+                    .devices_map
+                    .get(device)
+                    .map_or(0, |snapshots| snapshots.len());
+                let cells = columns.iter().map(|column| match column {
+                    DeviceColumn::Num => Cell::from(device.num.to_string()),
+                    DeviceColumn::Name => Cell::from(device.device_name.clone()),
+                    DeviceColumn::Size => Cell::from(device.size.clone()),
+                    DeviceColumn::Type => Cell::from(device.device_type.to_string()),
+                    DeviceColumn::Label => Cell::from(device.label.clone()),
+                    DeviceColumn::Mount => match &device.mount_point {
+                        Some(path) => Cell::from(path.display().to_string()),
+                        None => Cell::from("not mounted".dark_gray()),
+                    },
+                    DeviceColumn::Snapshots => Cell::from(snapshots.to_string()),
+                });
+                Row::new(cells)
+            })
+            .collect();
+        let header: Vec<Cell> = columns
             .iter()
-            .enumerate()
-            .map(|(i, s)| {
-                // S est un tuple (Device, Vec<Snapshot>)
-                if i == self.current_index {
-                    ListItem::from(s.0.to_string()).bg(Color::Blue)
-                } else {
-                    ListItem::from(s.0.to_string())
+            .map(|&column| {
+                if self.device_sort.column != column {
+                    return Cell::from(column.label());
                 }
+                let arrow = match self.device_sort.order {
+                    SortOrder::Ascending => "▲",
+                    SortOrder::Descending => "▼",
+                };
+                Cell::from(Line::from(vec![
+                    column.label().into(),
+                    format!(" {arrow}").yellow(),
+                ]))
             })
             .collect();
-        let snapshot_list_widget = List::new(items)
-            .block(
-                Block::bordered()
-                    .title("Device List")
-                    .title_bottom(instructions.centered()),
+        let widths = columns.iter().map(|column| match column {
+            DeviceColumn::Num => Constraint::Length(3),
+            DeviceColumn::Name => Constraint::Min(10),
+            DeviceColumn::Size => Constraint::Length(10),
+            DeviceColumn::Type => Constraint::Length(6),
+            DeviceColumn::Label => Constraint::Fill(1),
+            DeviceColumn::Mount => Constraint::Fill(2),
+            DeviceColumn::Snapshots => Constraint::Length(11),
+        });
+        let mut block = Block::bordered()
+            .title("Device List")
+            .title(
+                Line::from(vec![
+                    " Sort ".into(),
+                    format!(" <1-{}> ", DeviceColumn::ALL.len()).blue().bold(),
+                ])
+                .right_aligned(),
             )
-            .highlight_style(Style::new().reversed())
-            .highlight_symbol(">>")
-            .repeat_highlight_symbol(true);
-        snapshot_list_widget.render(area, buf);
+            .title_bottom(instructions.centered());
+        // Rows of timeshift output that we could not read are skipped, but we still tell the user
+        if let Some(error) = self.timeshift_instance.parse_errors.first() {
+            let warning = format!(
+                " ⚠ {} line(s) of timeshift output skipped ({error}) ",
+                self.timeshift_instance.parse_errors.len()
+            );
+            block = block.title(Line::from(warning.yellow()).right_aligned());
+        }
+        if let Some(error) = &self.timeshift_instance.list_error {
+            block = block.title(Line::from(format!(" ⚠ {error} ").red()).right_aligned());
+        }
+        let empty = rows.is_empty();
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).bold())
+            .block(block)
+            .row_highlight_style(Style::default().bg(Color::Blue));
+        let [list_area, space_area] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(5)]).areas(area);
+        let mut state = TableState::default().with_selected(Some(self.current_index));
+        StatefulWidget::render(table, list_area, buf, &mut state);
+        if empty {
+            let message = if self.timeshift_instance.list_error.is_some() {
+                "Timeshift couldn't list the devices, see the error above"
+            } else {
+                "No devices found, timeshift needs a device to put the snapshots on"
+            };
+            let [_, message_area] =
+                Layout::vertical([Constraint::Length(2), Constraint::Min(1)]).areas(list_area);
+            Paragraph::new(Line::from(message).dark_gray())
+                .centered()
+                .render(message_area, buf);
+        }
+        if let Some(device) = self.selected_device() {
+            self.render_device_space(device, space_area, buf);
+        }
+    }
+
+    /// How full the selected device is, and how many more snapshots it can take.
+    fn render_device_space(&self, device: &Device, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered().title(format!("Space on {}", device.device_name));
+        let [gauge_area, text_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(block.inner(area));
+        block.render(area, buf);
+
+        let space = match &device.space {
+            Some(Ok(space)) => space,
+            Some(Err(error)) => {
+                Paragraph::new(Line::from(format!("Couldn't read the free space : {error}")).red())
+                    .wrap(Wrap { trim: false })
+                    .render(gauge_area.union(text_area), buf);
+                return;
+            }
+            None => return,
+        };
+        let ratio = space.used_ratio();
+        let color = if ratio >= 0.9 {
+            Color::Red
+        } else if ratio >= 0.75 {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+        Gauge::default()
+            .gauge_style(Style::default().fg(color).bg(Color::DarkGray))
+            .ratio(ratio)
+            .label(format!(
+                "{} used of {} ({:.0}%), {} free",
+                format_bytes(space.used),
+                format_bytes(space.total),
+                ratio * 100.0,
+                format_bytes(space.free)
+            ))
+            .render(gauge_area, buf);
+        Paragraph::new(projection_line(
+            space,
+            self.growth_estimate(&device.device_name),
+        ))
+        .wrap(Wrap { trim: false })
+        .render(text_area, buf);
+    }
+}
+
+fn projection_line(space: &DeviceSpace, estimate: GrowthEstimate) -> Line<'static> {
+    match estimate {
+        GrowthEstimate::Average { bytes, samples } => {
+            let Some(room) = space.snapshots_that_fit(bytes) else {
+                return Line::from(format!(
+                    "The last {samples} snapshot(s) took no space of their own"
+                ));
+            };
+            let line = Line::from(format!(
+                "Room for about {room} more snapshot(s), the last {samples} took {} each on \
+                 average",
+                format_bytes(bytes)
+            ));
+            if room < LOW_ROOM {
+                line.red().bold()
+            } else {
+                line
+            }
+        }
+        GrowthEstimate::NotMeasured => {
+            Line::from("<U> measures the recent snapshots to estimate how many more fit").gray()
+        }
+        GrowthEstimate::Measuring => {
+            Line::from("Measuring the recent snapshots to estimate how many more fit...").gray()
+        }
+        GrowthEstimate::NotEnoughSnapshots => {
+            Line::from("Not enough snapshots yet to estimate how many more fit").gray()
+        }
+        GrowthEstimate::Unknown(error) => Line::from(format!(
+            "Can't estimate how many more snapshots fit : {error}"
+        ))
+        .gray(),
     }
 }
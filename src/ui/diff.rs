@@ -0,0 +1,85 @@
+use crate::app::App;
+use crate::timeshift_lib::diff::FileDigest;
+use crate::timeshift_lib::{Backend, DiffLine, FileDiff, format_bytes};
+use crate::ui::center;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Widget},
+};
+
+impl<B: Backend> App<B> {
+    /// The unified diff over most of the screen, `---` is the oldest version.
+    pub fn render_diff(&self, area: Rect, buf: &mut Buffer) {
+        let Some(view) = &self.diff else {
+            return;
+        };
+        let popup_area = center(area, Constraint::Percentage(90), Constraint::Percentage(90));
+        Clear.render(popup_area, buf);
+        let (added, removed) = view.diff.as_ref().map_or((0, 0), FileDiff::counts);
+        let mut title = vec![" Diff ".cyan().bold()];
+        if added + removed > 0 {
+            title.push(format!("+{added} ").green().bold());
+            title.push(format!("-{removed} ").red().bold());
+        }
+        let instructions = Line::from(vec![
+            " Scroll ".into(),
+            " <J/K> ".blue().bold(),
+            " Page ".into(),
+            " <PgDn/PgUp> ".blue().bold(),
+            " Close ".into(),
+            " <Esc> ".blue().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(Line::from(title))
+            .title_bottom(instructions.centered())
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+        let inner_area = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        let [header_area, diff_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(inner_area);
+        Paragraph::new(vec![
+            Line::from(format!("--- {}", view.old_label)).red().bold(),
+            Line::from(format!("+++ {}", view.new_label)).green().bold(),
+        ])
+        .render(header_area, buf);
+
+        let lines: Vec<Line> = match &view.diff {
+            None => vec![Line::from("Comparing the files...").dark_gray()],
+            Some(FileDiff::Identical) => vec![Line::from("The files are identical.").green()],
+            Some(FileDiff::Text(lines)) => lines.iter().skip(view.scroll).map(diff_line).collect(),
+            Some(FileDiff::Binary { old, new }) => vec![
+                Line::from("Binary files differ.").yellow().bold(),
+                Line::from(""),
+                digest_line("---", old.as_ref()),
+                digest_line("+++", new.as_ref()),
+            ],
+        };
+        Paragraph::new(lines).render(diff_area, buf);
+    }
+}
+
+fn diff_line(line: &DiffLine) -> Line<'_> {
+    match line {
+        DiffLine::Hunk(header) => Line::from(header.as_str()).cyan(),
+        DiffLine::Context(text) => Line::from(format!(" {text}")),
+        DiffLine::Added(text) => Line::from(format!("+{text}")).green(),
+        DiffLine::Removed(text) => Line::from(format!("-{text}")).red(),
+    }
+}
+
+fn digest_line(side: &str, digest: Option<&FileDigest>) -> Line<'static> {
+    match digest {
+        Some(digest) => Line::from(format!(
+            "{side} {} ({} bytes)  sha256 {}",
+            format_bytes(digest.size),
+            digest.size,
+            digest.sha256
+        )),
+        None => Line::from(format!("{side} doesn't exist")).dark_gray(),
+    }
+}
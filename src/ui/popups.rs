@@ -1,2 +1,12 @@
 mod create_popup;
 mod delete_popups;
+mod diff_popups;
+mod error_popup;
+mod export_popups;
+mod file_restore_popups;
+mod mark_popups;
+mod progress_popup;
+mod restore_popups;
+mod retention_popup;
+
+pub use create_popup::CursorPosition;
@@ -1,26 +1,22 @@
 use crate::app::App;
 use crate::app::InputMode;
-use crate::ui::Popup;
-use crate::ui::center;
+use crate::timeshift_lib::Backend;
+use crate::ui::{Popup, center};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::text::Text;
-use ratatui::widgets::StatefulWidget;
 use ratatui::widgets::{Block, Paragraph};
 use ratatui::{
     buffer::Buffer,
     style::{Color, Style, Stylize},
-    text::{Line, Span},
+    text::{Line, Span, Text},
     widgets::Widget,
 };
-use throbber_widgets_tui::BRAILLE_EIGHT;
-use throbber_widgets_tui::Throbber;
 
 pub struct CursorPosition {
     pub x: u16,
     pub y: u16,
 }
 
-impl App {
+impl<B: Backend> App<B> {
     /// This method create a popup to create a snapshot. It allow for a state where you are editing
     /// in the popup, and a state where you can exit editing without closing the windows BUT i do
     /// not use the former, because for now I only need a comment. I close the windows whenever we
@@ -95,6 +91,21 @@ impl App {
         let help = Paragraph::new(help_text);
         help.render(chunks[3], buf);
 
+        // In btrfs mode, @home is a separate subvolume that can be left out
+        if self.timeshift_instance.btrfs_mode {
+            let choice = if self.create_include_home {
+                Span::styled("yes", Style::default().fg(Color::Green).bold())
+            } else {
+                Span::styled("no", Style::default().fg(Color::Red).bold())
+            };
+            Paragraph::new(Line::from(vec![
+                Span::raw("Include @home : "),
+                choice,
+                Span::styled("  <Tab>", Style::default().fg(Color::Gray)),
+            ]))
+            .render(chunks[2], buf);
+        }
+
         // Retourner la position du curseur si en mode édition
         if self.input_mode == InputMode::Editing {
             let x = self.input.visual_cursor().max(scroll) - scroll;
@@ -108,34 +119,46 @@ impl App {
     }
 
     pub fn render_creation_progress(&self, area: Rect, buf: &mut Buffer) {
-        let popup_area = center(area, Constraint::Percentage(30), Constraint::Length(8));
+        if self.is_cancelling() {
+            self.render_operation_progress(
+                area,
+                buf,
+                "⏳ Cancelling... ",
+                "Stopping timeshift and removing the partial snapshot...",
+            );
+        } else {
+            self.render_operation_progress(
+                area,
+                buf,
+                "⏳ Creating... ",
+                "Creating the snapshot... (Esc to cancel)",
+            );
+        }
+    }
+
+    pub fn render_cancel_confirmation(&self, area: Rect, buf: &mut Buffer) {
+        let popup_area = center(area, Constraint::Percentage(40), Constraint::Length(10));
 
         let popup = Popup::default()
-            .title("⏳ Creating... ")
-            .title_style(Style::default().fg(Color::Cyan).bold())
+            .title("⚠ Cancel")
+            .title_style(Style::default().fg(Color::Yellow).bold())
             .content(Text::from(vec![
                 Line::from(""),
-                Line::from("Creating the snapshot...").centered(),
+                Line::from("Do you really want to stop the snapshot creation ?"),
                 Line::from(""),
-                Line::from("Please wait").style(Style::default().fg(Color::Gray)),
+                Line::from("The partial snapshot will be removed.")
+                    .style(Style::default().fg(Color::Red)),
+                Line::from(""),
+                Line::from(vec![
+                    " Stop ".into(),
+                    " <Y> ".green().bold(),
+                    "  Continue ".into(),
+                    " <N/Esc> ".red().bold(),
+                ]),
             ]))
-            .border_style(Style::default().fg(Color::Cyan))
+            .border_style(Style::default().fg(Color::Yellow))
             .style(Style::default().bg(Color::Black));
 
         popup.render(popup_area, buf);
-
-        let throbber_area = center(popup_area, Constraint::Length(3), Constraint::Length(1));
-        let throbber = Throbber::default()
-            .label("")
-            .throbber_set(BRAILLE_EIGHT)
-            .use_type(throbber_widgets_tui::WhichUse::Spin)
-            .style(Style::default().fg(Color::Cyan));
-
-        StatefulWidget::render(
-            throbber,
-            throbber_area,
-            buf,
-            &mut *self.throbber_state.borrow_mut(),
-        );
     }
 }
@@ -1,6 +1,6 @@
 use crate::app::App;
+use crate::timeshift_lib::{Backend, format_bytes};
 use crate::ui::{Popup, center};
-use ratatui::widgets::StatefulWidget;
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Rect},
@@ -8,18 +8,13 @@ use ratatui::{
     text::{Line, Text},
     widgets::Widget,
 };
-use throbber_widgets_tui::{BRAILLE_EIGHT, Throbber};
 
-impl App {
-    pub fn render_delete_confirmation(
-        &self,
-        area: Rect,
-        buf: &mut Buffer,
-        current_device_name: String,
-    ) {
+impl<B: Backend> App<B> {
+    pub fn render_delete_confirmation(&self, area: Rect, buf: &mut Buffer) {
         let popup_area = center(area, Constraint::Percentage(30), Constraint::Length(10));
-        let snapshot_name =
-            &self.timeshift_instance.devices_map_by_name[&current_device_name][self.current_index];
+        let Some(snapshot_name) = self.current_snapshot() else {
+            return;
+        };
 
         let popup = Popup::default()
             .title("⚠ Confirmation")
@@ -49,34 +44,158 @@ impl App {
     }
 
     pub fn render_deletion_progress(&self, area: Rect, buf: &mut Buffer) {
-        let popup_area = center(area, Constraint::Percentage(30), Constraint::Length(8));
-
-        let popup = Popup::default()
-            .title("⏳ Deleting... ")
-            .title_style(Style::default().fg(Color::Cyan).bold())
-            .content(Text::from(vec![
-                Line::from(""),
-                Line::from("Deleting the snapshot...").centered(),
-                Line::from(""),
-                Line::from("Please wait").style(Style::default().fg(Color::Gray)),
-            ]))
-            .border_style(Style::default().fg(Color::Cyan))
-            .style(Style::default().bg(Color::Black));
+        self.render_operation_progress(area, buf, "⏳ Deleting... ", "Deleting the snapshot...");
+    }
 
-        popup.render(popup_area, buf);
+    /// One confirmation for all the marked snapshots, with what deleting them frees.
+    pub fn render_bulk_delete_confirmation(&self, area: Rect, buf: &mut Buffer) {
+        let marked = self.marked_snapshots();
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(vec![
+                "Do you really want to delete these ".into(),
+                marked.len().to_string().yellow().bold(),
+                " snapshots ?".into(),
+            ]),
+            Line::from(""),
+        ];
+        let mut freed = 0;
+        let mut unmeasured = 0;
+        for (i, snapshot) in marked.iter().enumerate() {
+            let size = self
+                .snapshot_sizes
+                .get(&(self.current_device_name.clone(), snapshot.name.clone()));
+            let size_text = match size {
+                Some(Ok(size)) => {
+                    freed += size.unique;
+                    format_bytes(size.unique)
+                }
+                _ => {
+                    unmeasured += 1;
+                    "?".to_string()
+                }
+            };
+            if i < MAX_LISTED {
+                lines.push(Line::from(vec![
+                    format!("  {} ", snapshot.name).yellow(),
+                    format!("{:<4}", snapshot.tag_letters()).into(),
+                    size_text.gray(),
+                ]));
+            }
+        }
+        if marked.len() > MAX_LISTED {
+            lines.push(Line::from(format!("  ... and {} more", marked.len() - MAX_LISTED)).gray());
+        }
+        lines.push(Line::from(""));
+        // Files shared only between the marked snapshots are freed too, hence "at least"
+        if !self.timeshift_instance.btrfs_mode {
+            let mut total = format!("At least {} will be freed", format_bytes(freed));
+            if unmeasured > 0 {
+                total.push_str(&format!(" ({unmeasured} not measured yet)"));
+            }
+            lines.push(Line::from(total).bold());
+        }
+        lines.push(Line::from("This action cannot be recovered.").red());
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            " Confirm ".into(),
+            " <Y> ".green().bold(),
+            "  Cancel ".into(),
+            " <N/Esc> ".red().bold(),
+        ]));
 
-        let throbber_area = center(popup_area, Constraint::Length(3), Constraint::Length(1));
-        let throbber = Throbber::default()
-            .label("")
-            .throbber_set(BRAILLE_EIGHT)
-            .use_type(throbber_widgets_tui::WhichUse::Spin)
-            .style(Style::default().fg(Color::Cyan));
+        let popup_area = wrapped_popup_area(area, &lines);
+        Popup::default()
+            .title("⚠ Confirmation")
+            .title_style(Style::default().fg(Color::Yellow).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black))
+            .render(popup_area, buf);
+    }
 
-        StatefulWidget::render(
-            throbber,
-            throbber_area,
+    pub fn render_bulk_deletion_progress(&self, area: Rect, buf: &mut Buffer) {
+        let done = self.bulk.results.len();
+        let failed = self.bulk.results.iter().filter(|(_, r)| r.is_err()).count();
+        let name = self.bulk.current.as_deref().unwrap_or_default();
+        let mut message = if self.bulk.stopping {
+            format!("Stopping after {name}...")
+        } else {
+            format!("Deleting {name}... (Esc to stop after this one)")
+        };
+        if failed > 0 {
+            message.push_str(&format!(" {failed} failed so far"));
+        }
+        self.render_operation_progress(
+            area,
             buf,
-            &mut *self.throbber_state.borrow_mut(),
+            &format!("⏳ Deleting {}/{} ", done + 1, self.bulk.total()),
+            &message,
         );
     }
+
+    /// What the bulk deletion did, once it is over.
+    pub fn render_bulk_deletion_summary(&self, area: Rect, buf: &mut Buffer) {
+        let deleted = self.bulk.results.iter().filter(|(_, r)| r.is_ok()).count();
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(format!("✔ {deleted} snapshot(s) deleted")).green(),
+        ];
+        let failures: Vec<_> = self
+            .bulk
+            .results
+            .iter()
+            .filter_map(|(name, result)| Some((name, result.as_ref().err()?)))
+            .collect();
+        if !failures.is_empty() {
+            lines.push(Line::from(format!("✖ {} failed, still marked :", failures.len())).red());
+            for (name, error) in failures.iter().take(MAX_LISTED) {
+                // The last line is usually the one saying why
+                let reason = error.lines().last().unwrap_or_default();
+                lines.push(Line::from(vec![
+                    format!("  {name} : ").yellow(),
+                    reason.to_string().into(),
+                ]));
+            }
+        }
+        if !self.bulk.queue.is_empty() {
+            lines.push(Line::from(format!("⏸ {} skipped :", self.bulk.queue.len())).yellow());
+            for name in self.bulk.queue.iter().take(MAX_LISTED) {
+                lines.push(Line::from(format!("  {name}")).gray());
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            " Close ".into(),
+            " <Enter> ".blue().bold(),
+        ]));
+
+        let popup_area = wrapped_popup_area(area, &lines);
+        Popup::default()
+            .title(" Bulk deletion ")
+            .title_style(Style::default().fg(Color::Cyan).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black))
+            .render(popup_area, buf);
+    }
+}
+
+/// The popups list this many snapshots at most, the rest is counted.
+pub(super) const MAX_LISTED: usize = 10;
+
+/// Half of the screen wide, and high enough for the lines once wrapped (the errors of timeshift
+/// can be long).
+pub(super) fn wrapped_popup_area(area: Rect, lines: &[Line]) -> Rect {
+    let width = center(area, Constraint::Percentage(50), Constraint::Length(0)).width;
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let height: usize = lines
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(inner_width))
+        .sum();
+    center(
+        area,
+        Constraint::Percentage(50),
+        Constraint::Length(height as u16 + 2),
+    )
 }
@@ -0,0 +1,46 @@
+use super::restore_popups::render_choices;
+use crate::app::{App, CompareTarget};
+use crate::timeshift_lib::Backend;
+use ratatui::{buffer::Buffer, layout::Rect};
+
+impl<B: Backend> App<B> {
+    pub fn render_diff_menu(&self, area: Rect, buf: &mut Buffer) {
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        render_choices(
+            area,
+            buf,
+            " Compare ",
+            "Compare the file with :",
+            " Diff ",
+            choices(&browser.diff_targets),
+            browser.diff_index,
+        );
+    }
+
+    pub fn render_compare_menu(&self, area: Rect, buf: &mut Buffer) {
+        let Some(snapshot) = self.current_snapshot() else {
+            return;
+        };
+        render_choices(
+            area,
+            buf,
+            " Changes ",
+            &format!("Compare {} with :", snapshot.name),
+            " Compare ",
+            choices(&self.compare_targets),
+            self.compare_index,
+        );
+    }
+}
+
+fn choices(targets: &[CompareTarget]) -> Vec<String> {
+    targets
+        .iter()
+        .map(|target| match target {
+            CompareTarget::Live => "The running system".to_string(),
+            CompareTarget::Snapshot(name) => format!("Snapshot {name}"),
+        })
+        .collect()
+}
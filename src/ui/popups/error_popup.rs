@@ -0,0 +1,81 @@
+use crate::app::App;
+use crate::timeshift_lib::Backend;
+use crate::ui::{Popup, center};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::Widget,
+};
+
+impl<B: Backend> App<B> {
+    /// Shows what went wrong with the last operation, with the exit code and the output of
+    /// timeshift, until the user dismisses it.
+    pub fn render_operation_failure(&self, area: Rect, buf: &mut Buffer) {
+        let Some(failure) = &self.operation_failure else {
+            return;
+        };
+        let popup_area = center(area, Constraint::Percentage(50), Constraint::Percentage(50));
+        if failure.cancelled {
+            self.render_operation_cancelled(popup_area, buf);
+            return;
+        }
+
+        let code = match failure.code {
+            Some(code) => code.to_string(),
+            None => "none".to_string(),
+        };
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(vec!["Exit code : ".into(), code.red().bold()]),
+            Line::from(""),
+        ];
+        lines.extend(
+            failure
+                .details
+                .lines()
+                .map(|line| Line::from(line.to_string()).style(Style::default().fg(Color::Gray))),
+        );
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            " Dismiss ".into(),
+            " <Enter/Esc> ".red().bold(),
+        ]));
+
+        let popup = Popup::default()
+            .title(format!("✖ {} ", failure.title))
+            .title_style(Style::default().fg(Color::Red).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Red))
+            .style(Style::default().bg(Color::Black));
+
+        popup.render(popup_area, buf);
+    }
+
+    /// Not an error : the user stopped the operation, we only tell how the cleanup went.
+    fn render_operation_cancelled(&self, popup_area: Rect, buf: &mut Buffer) {
+        let Some(failure) = &self.operation_failure else {
+            return;
+        };
+        let mut lines = vec![Line::from("")];
+        lines.extend(
+            failure
+                .details
+                .lines()
+                .map(|line| Line::from(line.to_string())),
+        );
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            " Dismiss ".into(),
+            " <Enter/Esc> ".yellow().bold(),
+        ]));
+        Popup::default()
+            .title(format!(" ⏹ {} ", failure.title))
+            .title_style(Style::default().fg(Color::Yellow).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black))
+            .render(popup_area, buf);
+    }
+}
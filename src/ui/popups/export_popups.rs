@@ -0,0 +1,50 @@
+use super::restore_popups::render_choices;
+use crate::app::App;
+use crate::timeshift_lib::{Backend, ExportFormat};
+use crate::ui::{Popup, center};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::Widget,
+};
+
+impl<B: Backend> App<B> {
+    pub fn render_export_menu(&self, area: Rect, buf: &mut Buffer) {
+        let items = ExportFormat::ALL
+            .into_iter()
+            .map(|format| format!("{format} (.{})", format.extension()))
+            .collect();
+        render_choices(
+            area,
+            buf,
+            " Export the inventory ",
+            "Every snapshot, with the sizes measured so far :",
+            " Export ",
+            items,
+            self.export_index,
+        );
+    }
+
+    pub fn render_exported(&self, area: Rect, buf: &mut Buffer) {
+        let Some(path) = &self.exported_path else {
+            return;
+        };
+        let popup_area = center(area, Constraint::Percentage(50), Constraint::Length(7));
+        let lines = vec![
+            Line::from(""),
+            Line::from("Inventory written to :"),
+            Line::from(path.display().to_string().green()),
+            Line::from(""),
+            Line::from(vec![" Dismiss ".into(), " <Enter/Esc> ".green().bold()]),
+        ];
+        let popup = Popup::default()
+            .title(" ✔ Exported ")
+            .title_style(Style::default().fg(Color::Green).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Green))
+            .style(Style::default().bg(Color::Black));
+        popup.render(popup_area, buf);
+    }
+}
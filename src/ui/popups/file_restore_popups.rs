@@ -0,0 +1,220 @@
+use super::create_popup::CursorPosition;
+use super::delete_popups::{MAX_LISTED, wrapped_popup_area};
+use super::restore_popups::render_choices;
+use crate::app::{App, FILE_RESTORE_CHOICES};
+use crate::timeshift_lib::Backend;
+use crate::ui::{Popup, center};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, Widget},
+};
+use std::path::PathBuf;
+
+impl<B: Backend> App<B> {
+    pub fn render_file_restore_destination(&self, area: Rect, buf: &mut Buffer) {
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        let count = browser.marked.len().max(1);
+        render_choices(
+            area,
+            buf,
+            " Restore files ",
+            &format!("Where should the {count} file(s) go ?"),
+            " Next ",
+            FILE_RESTORE_CHOICES.map(String::from).to_vec(),
+            browser.destination_index,
+        );
+    }
+
+    pub fn render_file_restore_folder(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+    ) -> Option<CursorPosition> {
+        let invalid = self
+            .browser
+            .as_ref()
+            .is_some_and(|browser| browser.invalid_folder);
+        let popup_area = center(area, Constraint::Percentage(40), Constraint::Length(8));
+        Clear.render(popup_area, buf);
+        let block = Block::bordered()
+            .title(" Restore files ".yellow().bold())
+            .title_bottom(
+                Line::from(vec![
+                    " Next ".into(),
+                    " <Enter> ".green().bold(),
+                    " Back ".into(),
+                    " <Esc> ".red().bold(),
+                ])
+                .centered(),
+            )
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black));
+        let inner_area = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        let [question_area, input_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Length(3)]).areas(inner_area);
+        Paragraph::new("The files keep their path under this folder :").render(question_area, buf);
+        let input_style = if invalid {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let input_block = Block::bordered()
+            .title(if invalid {
+                "Folder (not an absolute path)"
+            } else {
+                "Folder"
+            })
+            .border_style(input_style);
+        let input_inner = input_block.inner(input_area);
+        input_block.render(input_area, buf);
+        let width = input_inner.width.max(1) - 1;
+        let scroll = self.input.visual_scroll(width as usize);
+        Paragraph::new(self.input.value())
+            .style(input_style)
+            .scroll((0, scroll as u16))
+            .render(input_inner, buf);
+
+        let x = self.input.visual_cursor().max(scroll) - scroll;
+        Some(CursorPosition {
+            x: input_inner.x + x as u16,
+            y: input_inner.y,
+        })
+    }
+
+    /// Every file with what is already there, and where the replaced files will be kept.
+    pub fn render_file_restore_confirmation(&self, area: Rect, buf: &mut Buffer) {
+        let Some(plan) = self
+            .browser
+            .as_ref()
+            .and_then(|browser| browser.restore_plan.as_ref())
+        else {
+            return;
+        };
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(vec![
+                "Restore ".into(),
+                plan.items.len().to_string().yellow().bold(),
+                " file(s) from the snapshot ?".into(),
+            ]),
+            Line::from(""),
+        ];
+        for item in plan.items.iter().take(MAX_LISTED) {
+            let status = match (item.exists, item.is_dir) {
+                (false, _) => " new     ".green(),
+                (true, true) => " merge   ".yellow(),
+                (true, false) if item.overwritten.is_empty() => " same    ".gray(),
+                (true, false) => " replace ".red(),
+            };
+            lines.push(Line::from(vec![
+                status,
+                item.destination.display().to_string().into(),
+            ]));
+        }
+        if plan.items.len() > MAX_LISTED {
+            lines.push(
+                Line::from(format!("  ... and {} more", plan.items.len() - MAX_LISTED)).gray(),
+            );
+        }
+        lines.push(Line::from(""));
+        let overwritten: Vec<&PathBuf> = plan
+            .items
+            .iter()
+            .flat_map(|item| &item.overwritten)
+            .collect();
+        if !overwritten.is_empty() {
+            lines.push(Line::from(format!(
+                "{}{} live file(s) overwritten :",
+                if plan.preview_incomplete {
+                    "At least "
+                } else {
+                    ""
+                },
+                overwritten.len()
+            )));
+            for path in overwritten.iter().take(MAX_LISTED) {
+                lines.push(Line::from(format!("  {}", path.display())).red());
+            }
+            if overwritten.len() > MAX_LISTED {
+                lines.push(
+                    Line::from(format!("  ... and {} more", overwritten.len() - MAX_LISTED)).gray(),
+                );
+            }
+            lines.push(Line::from(""));
+        } else if plan.preview_incomplete {
+            lines.push(Line::from(
+                "The folders are too big to look at every file, some may be overwritten.",
+            ));
+            lines.push(Line::from(""));
+        }
+        if plan.replaced() > 0 || plan.preview_incomplete {
+            lines.push(Line::from("The files overwritten are moved to :"));
+            lines.push(Line::from(plan.backup_dir.display().to_string()).bold());
+        } else {
+            lines.push(Line::from("Nothing is overwritten."));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            " Confirm ".into(),
+            " <Y> ".green().bold(),
+            "  Cancel ".into(),
+            " <N/Esc> ".red().bold(),
+        ]));
+
+        let popup_area = wrapped_popup_area(area, &lines);
+        Popup::default()
+            .title("⚠ Confirmation")
+            .title_style(Style::default().fg(Color::Yellow).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black))
+            .render(popup_area, buf);
+    }
+
+    pub fn render_file_restore_progress(&self, area: Rect, buf: &mut Buffer) {
+        let message = if self.is_cancelling() {
+            "Stopping..."
+        } else {
+            "Copying the files back... (Esc to stop)"
+        };
+        self.render_operation_progress(area, buf, "⏳ Restoring files... ", message);
+    }
+
+    pub fn render_files_restored(&self, area: Rect, buf: &mut Buffer) {
+        let Some(plan) = self
+            .browser
+            .as_ref()
+            .and_then(|browser| browser.restore_plan.as_ref())
+        else {
+            return;
+        };
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(format!("✔ {} file(s) restored", plan.items.len())).green(),
+        ];
+        if plan.replaced() > 0 || plan.preview_incomplete {
+            lines.push(Line::from("What they replaced is in :"));
+            lines.push(Line::from(plan.backup_dir.display().to_string()).bold());
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            " Dismiss ".into(),
+            " <Enter/Esc> ".green().bold(),
+        ]));
+        let popup_area = wrapped_popup_area(area, &lines);
+        Popup::default()
+            .title(" ✔ Restored ")
+            .title_style(Style::default().fg(Color::Green).bold())
+            .content(Text::from(lines))
+            .border_style(Style::default().fg(Color::Green))
+            .style(Style::default().bg(Color::Black))
+            .render(popup_area, buf);
+    }
+}
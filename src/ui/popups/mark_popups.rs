@@ -0,0 +1,85 @@
+use super::create_popup::CursorPosition;
+use super::restore_popups::render_choices;
+use crate::app::{App, MarkChoice};
+use crate::timeshift_lib::Backend;
+use crate::ui::center;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Widget},
+};
+
+impl<B: Backend> App<B> {
+    pub fn render_mark_menu(&self, area: Rect, buf: &mut Buffer) {
+        let items = MarkChoice::all()
+            .into_iter()
+            .map(|choice| match choice {
+                MarkChoice::Tag(tag) => format!("Every {tag} snapshot"),
+                MarkChoice::OlderThan => "Older than...".to_string(),
+                MarkChoice::Unmark => "Unmark everything".to_string(),
+            })
+            .collect();
+        render_choices(
+            area,
+            buf,
+            " Mark snapshots ",
+            &format!("{} snapshot(s) marked, add :", self.bulk.marked.len()),
+            " Mark ",
+            items,
+            self.bulk.menu_index,
+        );
+    }
+
+    /// The number of days for "older than", typed like the name in the restore confirmation.
+    pub fn render_mark_older_than(&self, area: Rect, buf: &mut Buffer) -> Option<CursorPosition> {
+        let popup_area = center(area, Constraint::Percentage(30), Constraint::Length(7));
+        Clear.render(popup_area, buf);
+        let block = Block::bordered()
+            .title(" Mark snapshots older than ".yellow().bold())
+            .title_bottom(
+                Line::from(vec![
+                    " Mark ".into(),
+                    " <Enter> ".green().bold(),
+                    " Back ".into(),
+                    " <Esc> ".red().bold(),
+                ])
+                .centered(),
+            )
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black));
+        let inner_area = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        let [question_area, input_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Length(3)]).areas(inner_area);
+        Paragraph::new("Number of days :").render(question_area, buf);
+        let input_style = if self.bulk.invalid_days {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let input_block = Block::bordered()
+            .title(if self.bulk.invalid_days {
+                "Days (not a number)"
+            } else {
+                "Days"
+            })
+            .border_style(input_style);
+        let input_inner = input_block.inner(input_area);
+        input_block.render(input_area, buf);
+        let width = input_inner.width.max(1) - 1;
+        let scroll = self.input.visual_scroll(width as usize);
+        Paragraph::new(self.input.value())
+            .style(input_style)
+            .scroll((0, scroll as u16))
+            .render(input_inner, buf);
+
+        let x = self.input.visual_cursor().max(scroll) - scroll;
+        Some(CursorPosition {
+            x: input_inner.x + x as u16,
+            y: input_inner.y,
+        })
+    }
+}
@@ -0,0 +1,96 @@
+use crate::app::App;
+use crate::timeshift_lib::Backend;
+use crate::ui::{Popup, center};
+use ratatui::widgets::StatefulWidget;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Gauge, Paragraph, Widget},
+};
+use throbber_widgets_tui::{BRAILLE_EIGHT, Throbber};
+
+impl<B: Backend> App<B> {
+    /// The popup shown while an operation runs : a throbber, the progress timeshift gives us
+    /// and the end of what it printed, so we can tell whether it is stuck or not.
+    pub fn render_operation_progress(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        title: &str,
+        message: &str,
+    ) {
+        let popup_area = center(area, Constraint::Percentage(60), Constraint::Percentage(60));
+
+        let popup = Popup::default()
+            .title(title.to_string())
+            .title_style(Style::default().fg(Color::Cyan).bold())
+            .content(Text::from(vec![
+                Line::from(""),
+                Line::from(message.to_string()).centered(),
+            ]))
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+        popup.render(popup_area, buf);
+
+        let inner_area = Block::bordered().inner(popup_area);
+        let [_, wait_area, gauge_area, _, log_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(3),
+        ])
+        .areas(inner_area);
+
+        let [wait_label_area, throbber_area] =
+            Layout::horizontal([Constraint::Length(12), Constraint::Length(3)]).areas(center(
+                wait_area,
+                Constraint::Length(15),
+                Constraint::Length(1),
+            ));
+        Line::from("Please wait")
+            .style(Style::default().fg(Color::Gray))
+            .render(wait_label_area, buf);
+        let throbber = Throbber::default()
+            .label("")
+            .throbber_set(BRAILLE_EIGHT)
+            .use_type(throbber_widgets_tui::WhichUse::Spin)
+            .style(Style::default().fg(Color::Cyan));
+        StatefulWidget::render(
+            throbber,
+            throbber_area,
+            buf,
+            &mut *self.throbber_state.borrow_mut(),
+        );
+
+        let (ratio, label) = match &self.operation_progress {
+            Some(progress) => {
+                let label = match &progress.eta {
+                    Some(eta) => format!("{:.2}% ({eta} remaining)", progress.percent),
+                    None => format!("{:.2}%", progress.percent),
+                };
+                (progress.percent / 100.0, label)
+            }
+            None => (0.0, "No progress reported yet".to_string()),
+        };
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::Cyan).bg(Color::DarkGray))
+            .ratio(ratio)
+            .label(label)
+            .render(gauge_area, buf);
+
+        // Only the last lines that fit, so it scrolls as timeshift prints
+        let log_block = Block::bordered()
+            .title(" Output ")
+            .border_style(Style::default().fg(Color::DarkGray));
+        let visible_lines = log_block.inner(log_area).height as usize;
+        let start = self.operation_log.len().saturating_sub(visible_lines);
+        let lines: Vec<Line> = self.operation_log[start..]
+            .iter()
+            .map(|line| Line::from(line.as_str()).style(Style::default().fg(Color::Gray)))
+            .collect();
+        Paragraph::new(lines).block(log_block).render(log_area, buf);
+    }
+}
@@ -0,0 +1,223 @@
+use super::create_popup::CursorPosition;
+use crate::app::App;
+use crate::timeshift_lib::{Backend, Bootloader};
+use crate::ui::{Popup, center};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+impl<B: Backend> App<B> {
+    pub fn render_restore_target(&self, area: Rect, buf: &mut Buffer) {
+        let items = self.restore_targets();
+        render_choices(
+            area,
+            buf,
+            " Restore : 1/4 Target device ",
+            &format!(
+                "Where should {} be restored ?",
+                self.restore_wizard.snapshot_name
+            ),
+            " Next ",
+            items,
+            self.restore_wizard.target_index,
+        );
+    }
+
+    pub fn render_restore_bootloader(&self, area: Rect, buf: &mut Buffer) {
+        let items = self
+            .bootloader_choices()
+            .iter()
+            .map(bootloader_label)
+            .collect();
+        render_choices(
+            area,
+            buf,
+            " Restore : 2/4 Bootloader ",
+            "Where should GRUB be installed ?",
+            " Next ",
+            items,
+            self.restore_wizard.bootloader_index,
+        );
+    }
+
+    pub fn render_restore_summary(&self, area: Rect, buf: &mut Buffer) {
+        let popup_area = center(area, Constraint::Percentage(50), Constraint::Length(14));
+        let Some(options) = self.restore_options() else {
+            return;
+        };
+
+        let popup = Popup::default()
+            .title(" Restore : 3/4 Summary ")
+            .title_style(Style::default().fg(Color::Yellow).bold())
+            .content(Text::from(vec![
+                Line::from(""),
+                Line::from(vec![
+                    "Snapshot       : ".into(),
+                    options.snapshot_name.clone().yellow().bold(),
+                ]),
+                Line::from(vec![
+                    "From device    : ".into(),
+                    options.snapshot_device.clone().into(),
+                ]),
+                Line::from(vec![
+                    "Restore on     : ".into(),
+                    options.target_device.clone().yellow().bold(),
+                ]),
+                Line::from(vec![
+                    "Bootloader     : ".into(),
+                    bootloader_label(&options.bootloader).into(),
+                ]),
+                Line::from(""),
+                Line::from("Everything on the target device will be replaced by the snapshot.")
+                    .style(Style::default().fg(Color::Red)),
+                Line::from("The system will reboot when the restore is done.")
+                    .style(Style::default().fg(Color::Red)),
+                Line::from(""),
+                Line::from(vec![
+                    " Continue ".into(),
+                    " <Enter> ".green().bold(),
+                    "  Back ".into(),
+                    " <Esc> ".red().bold(),
+                ]),
+            ]))
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black));
+
+        popup.render(popup_area, buf);
+    }
+
+    /// Same idea as the creation popup : the user types the name of the snapshot in an input,
+    /// and we give back where the cursor should be.
+    pub fn render_restore_confirmation(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+    ) -> Option<CursorPosition> {
+        let popup_area = center(area, Constraint::Percentage(40), Constraint::Length(10));
+        Clear.render(popup_area, buf);
+
+        let main_block = Block::bordered()
+            .title(" Restore : 4/4 Confirmation ".bold())
+            .border_style(Style::default().fg(Color::Red))
+            .style(Style::default().bg(Color::Black));
+        let inner_area = main_block.inner(popup_area);
+        main_block.render(popup_area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2), // Instructions
+                Constraint::Length(3), // Input
+                Constraint::Min(1),    // Espace
+                Constraint::Length(1), // Aide en bas
+            ])
+            .split(inner_area);
+
+        Paragraph::new(Line::from(vec![
+            "Type ".into(),
+            self.restore_wizard.snapshot_name.clone().yellow().bold(),
+            " to restore it:".into(),
+        ]))
+        .render(chunks[0], buf);
+
+        let width = chunks[1].width.max(3) - 3;
+        let scroll = self.input.visual_scroll(width as usize);
+        let input_style = if self.restore_wizard.confirmation_mismatch {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let input_title = if self.restore_wizard.confirmation_mismatch {
+            "Snapshot name (does not match)"
+        } else {
+            "Snapshot name"
+        };
+        let input_block = Block::bordered()
+            .title(input_title)
+            .border_style(input_style);
+        let input_inner = input_block.inner(chunks[1]);
+        input_block.render(chunks[1], buf);
+        Paragraph::new(self.input.value())
+            .style(input_style)
+            .scroll((0, scroll as u16))
+            .render(input_inner, buf);
+
+        Paragraph::new(Line::from(vec![
+            " Restore ".into(),
+            " <Enter> ".green().bold(),
+            "  Back ".into(),
+            " <Esc> ".red().bold(),
+        ]))
+        .render(chunks[3], buf);
+
+        let x = self.input.visual_cursor().max(scroll) - scroll;
+        Some(CursorPosition {
+            x: input_inner.x + x as u16,
+            y: input_inner.y,
+        })
+    }
+
+    pub fn render_restore_progress(&self, area: Rect, buf: &mut Buffer) {
+        self.render_operation_progress(area, buf, "⏳ Restoring... ", "Restoring the snapshot...");
+    }
+}
+
+fn bootloader_label(bootloader: &Bootloader) -> String {
+    match bootloader {
+        Bootloader::Reinstall(device) => format!("Reinstall GRUB on {device}"),
+        Bootloader::Skip => "Skip GRUB (keep the current bootloader)".to_string(),
+    }
+}
+
+/// A popup with a question and a list of choices, used by the first steps of the wizard and by
+/// the mark menu. `confirm` says what Enter does.
+pub(super) fn render_choices(
+    area: Rect,
+    buf: &mut Buffer,
+    title: &str,
+    question: &str,
+    confirm: &str,
+    items: Vec<String>,
+    selected: usize,
+) {
+    let height = items.len() as u16 + 6;
+    let popup_area = center(area, Constraint::Percentage(40), Constraint::Length(height));
+    Clear.render(popup_area, buf);
+
+    let instructions = Line::from(vec![
+        confirm.to_string().into(),
+        " <Enter> ".green().bold(),
+        " Back ".into(),
+        " <Esc> ".red().bold(),
+    ]);
+    let block = Block::bordered()
+        .title(title.to_string().yellow().bold())
+        .title_bottom(instructions.centered())
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+    let inner_area = block.inner(popup_area);
+    block.render(popup_area, buf);
+
+    let [question_area, list_area] =
+        Layout::vertical([Constraint::Length(2), Constraint::Min(1)]).areas(inner_area);
+    Paragraph::new(question.to_string()).render(question_area, buf);
+
+    let items: Vec<ListItem> = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            if i == selected {
+                ListItem::from(item).bg(Color::Blue)
+            } else {
+                ListItem::from(item)
+            }
+        })
+        .collect();
+    // Scrolls to the selection when there are more choices than lines
+    let mut state = ListState::default().with_selected(Some(selected));
+    StatefulWidget::render(List::new(items), list_area, buf, &mut state);
+}
@@ -0,0 +1,74 @@
+use crate::app::App;
+use crate::timeshift_lib::{Backend, RetentionField};
+use crate::ui::center;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, Paragraph, Widget},
+};
+
+impl<B: Backend> App<B> {
+    /// The settings of the retention policy, at the bottom of the screen so the preview on the
+    /// snapshot list stays visible.
+    pub fn render_retention_policy(&self, area: Rect, buf: &mut Buffer) {
+        let height = RetentionField::ALL.len() as u16 + 5;
+        let [_, bottom] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(height + 2)]).areas(area);
+        let popup_area = center(
+            bottom,
+            Constraint::Percentage(50),
+            Constraint::Length(height),
+        );
+        Clear.render(popup_area, buf);
+
+        let instructions = Line::from(vec![
+            " Change ".into(),
+            " <+/-> ".blue().bold(),
+            " Done ".into(),
+            " <Enter> ".green().bold(),
+        ]);
+        let block = Block::bordered()
+            .title(" Retention policy ".yellow().bold())
+            .title_bottom(instructions.centered())
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black));
+        let inner_area = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        let [list_area, _, summary_area] = Layout::vertical([
+            Constraint::Length(RetentionField::ALL.len() as u16),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(inner_area);
+        let items: Vec<ListItem> = RetentionField::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let item = ListItem::from(Line::from(vec![
+                    Span::raw(format!("{:<38}", field.label())),
+                    Span::from(self.retention.value(field)).bold(),
+                ]));
+                if i == self.retention_index {
+                    item.bg(Color::Blue)
+                } else {
+                    item
+                }
+            })
+            .collect();
+        Widget::render(List::new(items), list_area, buf);
+
+        let to_delete = self.snapshots_to_prune().len();
+        let summary = if to_delete == 0 {
+            Line::from("Nothing to prune with this policy").green()
+        } else {
+            Line::from(format!(
+                "{to_delete} snapshot(s) would be deleted, <Shift-P> on the list to prune"
+            ))
+            .red()
+        };
+        Paragraph::new(summary).render(summary_area, buf);
+    }
+}
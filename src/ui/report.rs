@@ -0,0 +1,172 @@
+use crate::app::{App, ChangeReportView};
+use crate::timeshift_lib::{Backend, ChangeKind, ChangeReport, format_bytes};
+use crate::ui::center;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap},
+};
+use throbber_widgets_tui::{BRAILLE_EIGHT, Throbber};
+
+impl<B: Backend> App<B> {
+    /// The changes by top-level folder on the left, the files of the selected folder on the
+    /// right.
+    pub fn render_report(&self, area: Rect, buf: &mut Buffer) {
+        let Some(report) = &self.report else {
+            return;
+        };
+        let title = format!("Changes : {} → {}", report.old.label(), report.new.label());
+        let instructions = if report.job.is_some() {
+            Line::from(vec![" Stop ".into(), " <Esc> ".blue().bold()])
+        } else {
+            Line::from(vec![
+                " Files ".into(),
+                " <Tab> ".blue().bold(),
+                " Diff ".into(),
+                " <Enter> ".blue().bold(),
+                " Back ".into(),
+                " <Esc> ".blue().bold(),
+            ])
+        };
+        let block = Block::bordered()
+            .title(title)
+            .title_bottom(instructions.centered());
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        match &report.result {
+            None => self.render_report_progress(report, inner_area, buf),
+            Some(Err(error)) => Paragraph::new(Line::from(error.clone()).red())
+                .wrap(Wrap { trim: false })
+                .render(inner_area, buf),
+            Some(Ok(changes)) => self.render_changes(report, changes, inner_area, buf),
+        }
+    }
+
+    fn render_report_progress(&self, report: &ChangeReportView, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Percentage(80), Constraint::Length(3));
+        let [line_area, path_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(area);
+        let [throbber_area, label_area] =
+            Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)]).areas(line_area);
+        let throbber = Throbber::default()
+            .label("")
+            .throbber_set(BRAILLE_EIGHT)
+            .use_type(throbber_widgets_tui::WhichUse::Spin)
+            .style(Style::default().fg(Color::Cyan));
+        StatefulWidget::render(
+            throbber,
+            throbber_area,
+            buf,
+            &mut *self.throbber_state.borrow_mut(),
+        );
+        let (scanned, path) = &report.progress;
+        Line::from(format!("Comparing... {scanned} files scanned")).render(label_area, buf);
+        Line::from(format!("/{}", path.display()).dark_gray()).render(path_area, buf);
+    }
+
+    fn render_changes(
+        &self,
+        report: &ChangeReportView,
+        changes: &ChangeReport,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let [summary_area, tables_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(area);
+        let total = changes.total();
+        let mut summary = vec![
+            format!("{} files scanned : ", changes.scanned).into(),
+            format!("{} added", total.added).green(),
+            ", ".into(),
+            format!("{} removed", total.removed).red(),
+            ", ".into(),
+            format!("{} modified", total.modified).yellow(),
+            format!(", {}", signed_bytes(total.delta)).into(),
+        ];
+        if !changes.unreadable.is_empty() {
+            summary.push(format!(" ({} folders couldn't be read)", changes.unreadable.len()).red());
+        }
+        Line::from(summary).render(summary_area, buf);
+        if changes.groups.is_empty() {
+            Paragraph::new("No differences").render(tables_area, buf);
+            return;
+        }
+
+        let [groups_area, files_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(tables_area);
+        let rows: Vec<Row> = changes
+            .groups
+            .iter()
+            .map(|group| {
+                Row::new(vec![
+                    Cell::from(group.name.clone()),
+                    Cell::from(group.added.to_string().green()),
+                    Cell::from(group.removed.to_string().red()),
+                    Cell::from(group.modified.to_string().yellow()),
+                    Cell::from(signed_bytes(group.delta)),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Fill(1),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(12),
+        ];
+        let highlight = |focused: bool| {
+            if focused {
+                Style::default().bg(Color::Blue)
+            } else {
+                Style::default().bg(Color::DarkGray)
+            }
+        };
+        let table = Table::new(rows, widths)
+            .header(Row::new(vec!["Folder", "Added", "Removed", "Modified", "Size"]).bold())
+            .block(Block::bordered().title("Folders"))
+            .row_highlight_style(highlight(!report.focus_files));
+        let mut state = TableState::default().with_selected(Some(self.current_index));
+        StatefulWidget::render(table, groups_area, buf, &mut state);
+
+        let rows: Vec<Row> = report
+            .files(self.current_index)
+            .into_iter()
+            .map(|change| {
+                let kind = match change.kind {
+                    ChangeKind::Added => Span::from("+").green().bold(),
+                    ChangeKind::Removed => Span::from("-").red().bold(),
+                    ChangeKind::Modified => Span::from("~").yellow().bold(),
+                };
+                Row::new(vec![
+                    Cell::from(kind),
+                    Cell::from(format!("/{}", change.path.display())),
+                    Cell::from(signed_bytes(change.delta())),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(12),
+        ];
+        let table = Table::new(rows, widths)
+            .block(Block::bordered().title("Files"))
+            .row_highlight_style(highlight(report.focus_files));
+        let selected = report.focus_files.then_some(report.file_index);
+        let mut state = TableState::default().with_selected(selected);
+        StatefulWidget::render(table, files_area, buf, &mut state);
+    }
+}
+
+/// `+1.2 MiB`, `-300 B`, or nothing when the size didn't change.
+fn signed_bytes(delta: i64) -> String {
+    match delta {
+        0 => String::new(),
+        1.. => format!("+{}", format_bytes(delta.unsigned_abs())),
+        _ => format!("-{}", format_bytes(delta.unsigned_abs())),
+    }
+}
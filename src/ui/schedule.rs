@@ -0,0 +1,141 @@
+use super::snapshots::{format_age, format_duration, tag_badge};
+use crate::app::App;
+use crate::timeshift_lib::schedule::{BOOT_CRON_JOB, HOURLY_CRON_JOB};
+use crate::timeshift_lib::{Backend, ScheduleLevel, TimeshiftConfig};
+use chrono::{DateTime, Local};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, Paragraph, Widget, Wrap},
+};
+
+impl<B: Backend> App<B> {
+    /// One line per level of scheduled snapshots, with when the next one should be taken.
+    pub fn render_schedule(&self, area: Rect, buf: &mut Buffer) {
+        let instructions = Line::from(vec![
+            " On/Off ".into(),
+            " <Enter> ".blue().bold(),
+            " Keep more/less ".into(),
+            " <+/-> ".blue().bold(),
+            " Save ".into(),
+            " <W> ".blue().bold(),
+            " Back ".into(),
+            " <Q> ".blue().bold(),
+        ]);
+        let mut block = Block::bordered()
+            .title("Schedule")
+            .title_bottom(instructions.centered());
+        if self.settings.is_modified() {
+            block = block.title(Line::from(" modified ".yellow().bold()).right_aligned());
+        }
+
+        let mut status_lines = self.schedule_warnings();
+        status_lines.extend(self.settings_status_lines());
+        let [list_area, status_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(status_lines.len() as u16 * 2),
+        ])
+        .areas(block.inner(area));
+        block.render(area, buf);
+
+        if let Some(config) = &self.settings.config {
+            let now = Local::now();
+            let items: Vec<ListItem> = ScheduleLevel::ALL
+                .into_iter()
+                .enumerate()
+                .map(|(i, level)| {
+                    let last = self.last_snapshot_with(level.tag()).map(|s| s.date);
+                    let item = ListItem::from(schedule_line(config, level, last, now));
+                    if i == self.current_index {
+                        item.bg(Color::Blue)
+                    } else {
+                        item
+                    }
+                })
+                .collect();
+            Widget::render(List::new(items), list_area, buf);
+        }
+        Paragraph::new(status_lines)
+            .wrap(Wrap { trim: false })
+            .render(status_area, buf);
+    }
+
+    /// Timeshift only takes the scheduled snapshots through its cron jobs, without them the
+    /// schedule does nothing.
+    fn schedule_warnings(&self) -> Vec<Line<'static>> {
+        let Some(config) = &self.settings.config else {
+            return Vec::new();
+        };
+        let cron_jobs = self.settings.cron_jobs;
+        let periodic = ScheduleLevel::ALL
+            .into_iter()
+            .any(|level| level != ScheduleLevel::Boot && level.is_enabled(config));
+        let mut warnings = Vec::new();
+        if periodic && !cron_jobs.hourly {
+            warnings.push(
+                Line::from(format!(
+                    "⚠ Scheduled snapshots are on, but {HOURLY_CRON_JOB} is missing : timeshift \
+                     will never take them"
+                ))
+                .yellow(),
+            );
+        }
+        if ScheduleLevel::Boot.is_enabled(config) && !cron_jobs.boot {
+            warnings.push(
+                Line::from(format!(
+                    "⚠ Boot snapshots are on, but {BOOT_CRON_JOB} is missing : timeshift will \
+                     never take them"
+                ))
+                .yellow(),
+            );
+        }
+        warnings
+    }
+}
+
+fn schedule_line(
+    config: &TimeshiftConfig,
+    level: ScheduleLevel,
+    last: Option<DateTime<Local>>,
+    now: DateTime<Local>,
+) -> Line<'static> {
+    let enabled = level.is_enabled(config);
+    let state = if enabled {
+        Span::from(" on  ").green().bold()
+    } else {
+        Span::from(" off ").red()
+    };
+    let last_text = match last {
+        Some(date) => format!(
+            "{} ({})",
+            date.format("%Y-%m-%d %H:%M"),
+            format_age(now.signed_duration_since(date))
+        ),
+        None => "never".to_string(),
+    };
+    let next = if !enabled {
+        "-".to_string()
+    } else {
+        match level.next_snapshot(last, now) {
+            None if level == ScheduleLevel::Boot => "at the next boot".to_string(),
+            Some(date) => match format_duration(date.signed_duration_since(now)) {
+                Some(duration) => format!("{} (in {duration})", date.format("%Y-%m-%d %H:%M")),
+                None => format!("{} (now)", date.format("%Y-%m-%d %H:%M")),
+            },
+            None => "unknown".to_string(),
+        }
+    };
+    Line::from(vec![
+        tag_badge(level.tag()),
+        Span::raw(format!(" {:<9}", level.tag().to_string())),
+        state,
+        Span::raw(format!(" keep {:<4}", level.count(config))),
+        Span::styled(
+            format!(" last : {last_text:<32}"),
+            Style::default().fg(Color::Gray),
+        ),
+        Span::raw(format!(" next : {next}")),
+    ])
+}
@@ -0,0 +1,145 @@
+use crate::app::{App, SettingsRow, SettingsStatus};
+use crate::timeshift_lib::{Backend, Filter, TimeshiftConfig};
+use crate::ui::center;
+use crate::ui::popups::CursorPosition;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, Paragraph, Widget, Wrap},
+};
+
+impl<B: Backend> App<B> {
+    /// The content of `timeshift.json`, one line per setting and per filter.
+    pub fn render_settings(&self, area: Rect, buf: &mut Buffer) {
+        let instructions = Line::from(vec![
+            " Edit ".into(),
+            " <Enter> ".blue().bold(),
+            " Delete filter ".into(),
+            " <D> ".blue().bold(),
+            " Save ".into(),
+            " <W> ".blue().bold(),
+            " Back ".into(),
+            " <Q> ".blue().bold(),
+        ]);
+        let mut block = Block::bordered()
+            .title("Timeshift Settings")
+            .title_bottom(instructions.centered());
+        if self.settings.is_modified() {
+            block = block.title(Line::from(" modified ".yellow().bold()).right_aligned());
+        }
+
+        let status_lines = self.settings_status_lines();
+        let [list_area, status_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(status_lines.len() as u16),
+        ])
+        .areas(block.inner(area));
+        block.render(area, buf);
+
+        if let Some(config) = &self.settings.config {
+            let items: Vec<ListItem> = self
+                .settings_rows()
+                .into_iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let item = ListItem::from(settings_line(config, row));
+                    if i == self.current_index {
+                        item.bg(Color::Blue)
+                    } else {
+                        item
+                    }
+                })
+                .collect();
+            Widget::render(List::new(items), list_area, buf);
+        }
+        Paragraph::new(status_lines)
+            .wrap(Wrap { trim: false })
+            .render(status_area, buf);
+    }
+
+    /// What happened to the config, shared with the Schedule screen.
+    pub fn settings_status_lines(&self) -> Vec<Line<'static>> {
+        match &self.settings.status {
+            None => Vec::new(),
+            Some(SettingsStatus::Saved) => vec![
+                Line::from("✔ Settings saved, the previous file was kept as timeshift.json.bak")
+                    .green(),
+            ],
+            Some(SettingsStatus::Errors(errors)) => errors
+                .iter()
+                .map(|error| Line::from(format!("✖ {error}")).red())
+                .collect(),
+            Some(SettingsStatus::UnsavedChanges) => vec![
+                Line::from("⚠ Unsaved changes, press <Q> again to discard them or <W> to save")
+                    .yellow(),
+            ],
+        }
+    }
+
+    /// The input where the value of a setting is typed, same look as the creation popup.
+    pub fn render_settings_input(&self, area: Rect, buf: &mut Buffer) -> Option<CursorPosition> {
+        let row = self.settings_rows().get(self.current_index).copied()?;
+        let title = match row {
+            SettingsRow::Field(field) => format!(" {} ", field.key()),
+            SettingsRow::Filter(_) | SettingsRow::AddFilter => {
+                " Filter (\"+ \" in front to include) ".to_string()
+            }
+        };
+        let popup_area = center(area, Constraint::Percentage(40), Constraint::Length(4));
+        Clear.render(popup_area, buf);
+        let block = Block::bordered()
+            .title(title.bold())
+            .title_bottom(
+                Line::from(vec![
+                    " Apply ".into(),
+                    " <Enter> ".green().bold(),
+                    " Cancel ".into(),
+                    " <Esc> ".red().bold(),
+                ])
+                .centered(),
+            )
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black));
+        let inner_area = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        let width = inner_area.width.max(1) - 1;
+        let scroll = self.input.visual_scroll(width as usize);
+        Paragraph::new(self.input.value())
+            .scroll((0, scroll as u16))
+            .render(inner_area, buf);
+
+        let x = self.input.visual_cursor().max(scroll) - scroll;
+        Some(CursorPosition {
+            x: inner_area.x + x as u16,
+            y: inner_area.y,
+        })
+    }
+}
+
+fn settings_line(config: &TimeshiftConfig, row: SettingsRow) -> Line<'static> {
+    match row {
+        SettingsRow::Field(field) => {
+            let value = match config.flag(field) {
+                Some(true) => Span::from("true").green(),
+                Some(false) => Span::from("false").red(),
+                None => Span::from(config.value(field)),
+            };
+            Line::from(vec![Span::raw(format!("{:<32}", field.key())), value])
+        }
+        SettingsRow::Filter(i) => {
+            let (kind, pattern) = match &config.filters[i] {
+                Filter::Include(pattern) => (Span::from("include ").green(), pattern.clone()),
+                Filter::Exclude(pattern) => (Span::from("exclude ").red(), pattern.clone()),
+            };
+            Line::from(vec![
+                Span::raw(format!("{:<32}", format!("filter {}", i + 1))),
+                kind,
+                Span::raw(pattern),
+            ])
+        }
+        SettingsRow::AddFilter => Line::from("+ Add a filter").gray(),
+    }
+}
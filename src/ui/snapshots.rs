@@ -1,36 +1,103 @@
-use crate::app::App;
+use super::popups::CursorPosition;
+use super::widgets::fit_columns;
+use crate::app::{App, CurrentAction, SnapshotColumn, SortOrder};
+use crate::timeshift_lib::{Backend, KeepReason, Snapshot, SnapshotTag, format_bytes};
+use chrono::TimeDelta;
 use ratatui::{
     buffer::Buffer,
-    layout::Rect,
+    layout::{Constraint, Layout, Rect},
     style::{Color, Style, Stylize},
-    text::Line,
-    widgets::{Block, List, ListItem, Paragraph, Widget},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
 };
-impl App {
-    pub fn render_snapshots(&self, area: Rect, buf: &mut Buffer, current_device_name: String) {
+impl<B: Backend> App<B> {
+    pub fn render_snapshots(&self, area: Rect, buf: &mut Buffer) {
+        let (area, details_area) = self.split_details(area);
+        if let Some(details_area) = details_area {
+            self.render_snapshot_details(details_area, buf);
+        }
+        let (search_area, area) = self.split_search(area);
+        if let Some(search_area) = search_area {
+            self.render_search(search_area, buf);
+        }
+        let columns = self.snapshot_columns();
+        let sort_hint = Line::from(vec![
+            " Changes ".into(),
+            " <X> ".blue().bold(),
+            " Search ".into(),
+            " </> ".blue().bold(),
+            " Sort ".into(),
+            format!(" <1-{}> ", columns.len()).blue().bold(),
+            " Reverse ".into(),
+            " <S> ".blue().bold(),
+        ]);
         let instructions = Line::from(vec![
             " Delete ".into(),
             " <D> ".blue().bold(),
+            " Mark ".into(),
+            " <Space/V/M> ".blue().bold(),
+            " Prune ".into(),
+            " <P> ".blue().bold(),
+            " Pin ".into(),
+            " <*> ".blue().bold(),
             " Create ".into(),
             " <C> ".blue().bold(),
+            " Restore ".into(),
+            " <R> ".blue().bold(),
+            " Browse ".into(),
+            " <Enter> ".blue().bold(),
+            " Info ".into(),
+            " <I> ".blue().bold(),
+            " Tag ".into(),
+            " <T> ".blue().bold(),
             " Back ".into(),
             " <Q> ".blue().bold(),
         ]);
 
-        let items: Vec<ListItem> = self.timeshift_instance.devices_map_by_name
-            [&current_device_name]
+        let verdicts = self.prune_preview.then(|| self.prune_verdicts());
+        let snapshots = self.visible_snapshots();
+        // The tags are badges of 3 characters
+        let tags_width = snapshots
+            .iter()
+            .map(|s| s.tags.len() as u16 * 3)
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        // On a narrow terminal the size and the age go first, so the comment stays readable.
+        // The borders, the mark and the retention take the rest.
+        let reserved = 2 + 3 + if verdicts.is_some() { 18 } else { 0 };
+        let shown = fit_columns(
+            &columns,
+            &[SnapshotColumn::Size, SnapshotColumn::Age],
+            area.width.saturating_sub(reserved),
+            |column| match column {
+                SnapshotColumn::Num => 3,
+                SnapshotColumn::Date => 19,
+                SnapshotColumn::Age => 14,
+                SnapshotColumn::Tags => tags_width,
+                SnapshotColumn::Size => 21,
+                SnapshotColumn::Comment => 12,
+            },
+        );
+        let rows: Vec<Row> = snapshots
             .iter()
             .enumerate()
             .map(|(i, s)| {
-                if i == self.current_index {
-                    ListItem::from(s.to_string()).bg(Color::Blue)
-                } else {
-                    ListItem::from(s.to_string())
-                }
+                let verdict = verdicts
+                    .as_ref()
+                    .map(|verdicts| verdict_span(verdicts.get(&s.name).copied()));
+                self.snapshot_row(i, s, &shown, verdict)
             })
             .collect();
-        if items.is_empty() {
-            let message = Paragraph::new("No snapshots on this device").block(
+        if rows.is_empty() {
+            let text = match self.tag_filter {
+                _ if !self.search.value().trim().is_empty() => {
+                    format!("No snapshots match \"{}\"", self.search.value())
+                }
+                Some(tag) => format!("No {tag} snapshots on this device"),
+                None => "No snapshots on this device".to_string(),
+            };
+            let message = Paragraph::new(text).block(
                 Block::bordered()
                     .title("Snapshot List")
                     .title_bottom(instructions.centered()),
@@ -38,16 +105,275 @@ impl App {
             message.render(area, buf);
             return;
         }
-        let list = List::new(items)
-            .block(
-                Block::bordered()
-                    .title("Snapshot List")
-                    .title_bottom(instructions.centered()),
+        let mut block = Block::bordered()
+            .title("Snapshot List")
+            .title(sort_hint.right_aligned())
+            .title_bottom(instructions.centered());
+        if self.timeshift_instance.btrfs_mode {
+            block = block.title(Line::from(" BTRFS ".black().on_yellow().bold()));
+        }
+        let marked = self.marked_snapshots().len();
+        if marked > 0 {
+            block = block.title(Line::from(format!(" {marked} marked ").green().bold()));
+        }
+        if self.prune_preview {
+            block = block.title(Line::from(
+                format!(
+                    " Prune preview : {} to delete, <Shift-P> to prune ",
+                    self.snapshots_to_prune().len()
+                )
+                .red()
+                .bold(),
+            ));
+        }
+        if let Some(start) = self.bulk.range_start {
+            block = block.title(Line::from(
+                format!(" Range from {start}, <V> to mark it ").yellow(),
+            ));
+        }
+        if let Some(tag) = self.tag_filter {
+            block = block.title(Line::from(vec![
+                " Tag : ".into(),
+                tag_badge(tag),
+                " ".into(),
+            ]));
+        }
+        let mut widths = vec![Constraint::Length(2)];
+        let mut header = vec![Cell::from("")];
+        if verdicts.is_some() {
+            widths.push(Constraint::Length(17));
+            header.push(Cell::from("Retention"));
+        }
+        for column in &shown {
+            widths.push(match column {
+                SnapshotColumn::Num => Constraint::Length(3),
+                SnapshotColumn::Date => Constraint::Length(19),
+                SnapshotColumn::Age => Constraint::Length(14),
+                SnapshotColumn::Tags => Constraint::Length(tags_width),
+                SnapshotColumn::Size => Constraint::Length(21),
+                SnapshotColumn::Comment => Constraint::Fill(1),
+            });
+            header.push(self.header_cell(*column));
+        }
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).bold())
+            .block(block)
+            .row_highlight_style(Style::default().bg(Color::Blue));
+        let mut state = TableState::default().with_selected(Some(self.current_index));
+        StatefulWidget::render(table, area, buf, &mut state);
+    }
+
+    /// The label of the column, with an arrow on the one the table is sorted by.
+    fn header_cell(&self, column: SnapshotColumn) -> Cell<'static> {
+        if self.snapshot_sort.column != column {
+            return Cell::from(column.label());
+        }
+        let arrow = match self.snapshot_sort.order {
+            SortOrder::Ascending => "▲",
+            SortOrder::Descending => "▼",
+        };
+        Cell::from(Line::from(vec![
+            column.label().into(),
+            format!(" {arrow}").yellow(),
+        ]))
+    }
+
+    /// With the detail pane, the list only takes the left part of the screen.
+    fn split_details(&self, area: Rect) -> (Rect, Option<Rect>) {
+        if !self.show_details {
+            return (area, None);
+        }
+        let [list_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(area);
+        (list_area, Some(details_area))
+    }
+
+    /// The search box goes above the list while it is typed or filtering something.
+    fn split_search(&self, area: Rect) -> (Option<Rect>, Rect) {
+        let searching =
+            matches!(self.current_action, CurrentAction::Search) || !self.search.value().is_empty();
+        if !searching {
+            return (None, area);
+        }
+        let [search_area, list_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(3)]).areas(area);
+        (Some(search_area), list_area)
+    }
+
+    fn render_search(&self, area: Rect, buf: &mut Buffer) {
+        let typing = matches!(self.current_action, CurrentAction::Search);
+        let style = if typing {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let instructions = if typing {
+            Line::from(vec![
+                " Keep ".into(),
+                " <Enter> ".green().bold(),
+                " Clear ".into(),
+                " <Esc> ".red().bold(),
+            ])
+        } else {
+            Line::from(vec![" Edit ".into(), " </> ".blue().bold()])
+        };
+        let block = Block::bordered()
+            .title("Search (name, comment, tags)")
+            .title(
+                Line::from(format!(" {} match(es) ", self.visible_snapshots().len()))
+                    .right_aligned(),
             )
-            .highlight_style(Style::new().reversed())
-            .highlight_symbol(">>")
-            .repeat_highlight_symbol(true);
+            .title_bottom(instructions.right_aligned())
+            .border_style(style);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let width = inner.width.max(1) - 1;
+        let scroll = self.search.visual_scroll(width as usize);
+        Paragraph::new(self.search.value())
+            .style(style)
+            .scroll((0, scroll as u16))
+            .render(inner, buf);
+    }
+
+    /// Where the cursor goes in the search box, same layout as `render_snapshots`.
+    pub fn search_cursor(&self, area: Rect) -> Option<CursorPosition> {
+        let (area, _) = self.split_details(area);
+        let (search_area, _) = self.split_search(area);
+        let inner = Block::bordered().inner(search_area?);
+        let width = inner.width.max(1) - 1;
+        let scroll = self.search.visual_scroll(width as usize);
+        let x = self.search.visual_cursor().max(scroll) - scroll;
+        Some(CursorPosition {
+            x: inner.x + x as u16,
+            y: inner.y,
+        })
+    }
+
+    /// One line of the table. The age is in red when the snapshot is older than `stale_after`.
+    /// `index` is the position in the list, for the range being marked.
+    fn snapshot_row(
+        &self,
+        index: usize,
+        snapshot: &Snapshot,
+        columns: &[SnapshotColumn],
+        verdict: Option<Span<'static>>,
+    ) -> Row<'static> {
+        let age = snapshot.age();
+        let age_style = if age > self.stale_after {
+            Style::default().fg(Color::Red).bold()
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        let in_range = self.bulk.range_start.is_some_and(|start| {
+            (start.min(self.current_index)..=start.max(self.current_index)).contains(&index)
+        });
+        let mark = if self.bulk.marked.contains(&snapshot.name) {
+            Span::styled("●", Style::default().fg(Color::Green).bold())
+        } else if in_range {
+            Span::styled("○", Style::default().fg(Color::Yellow))
+        } else {
+            Span::raw(" ")
+        };
+        let pin = if self.pinned.contains(&snapshot.name) {
+            Span::styled("*", Style::default().fg(Color::Yellow).bold())
+        } else {
+            Span::raw("")
+        };
+        let mut cells = vec![Cell::from(Line::from(vec![mark, pin]))];
+        cells.extend(verdict.map(Cell::from));
+        for column in columns {
+            cells.push(match column {
+                SnapshotColumn::Num => Cell::from(snapshot.num.to_string()),
+                SnapshotColumn::Date => Cell::from(snapshot.name.clone()),
+                SnapshotColumn::Age => Cell::from(Span::styled(format_age(age), age_style)),
+                SnapshotColumn::Tags => Cell::from(Line::from(
+                    snapshot
+                        .tags
+                        .iter()
+                        .map(|tag| tag_badge(*tag))
+                        .collect::<Vec<_>>(),
+                )),
+                SnapshotColumn::Size => Cell::from(self.size_span(snapshot)),
+                SnapshotColumn::Comment => Cell::from(snapshot.description.clone()),
+            });
+        }
+        Row::new(cells)
+    }
+
+    /// What deleting the snapshot would free, next to its apparent size.
+    fn size_span(&self, snapshot: &Snapshot) -> Span<'static> {
+        match self
+            .snapshot_sizes
+            .get(&(self.current_device_name.clone(), snapshot.name.clone()))
+        {
+            Some(Ok(size)) => Span::raw(format!(
+                "{} / {}",
+                format_bytes(size.unique),
+                format_bytes(size.apparent)
+            )),
+            Some(Err(_)) => Span::styled("size unknown", Style::default().fg(Color::Red)),
+            None if self.size_job.is_some() => {
+                Span::styled("measuring...", Style::default().fg(Color::Gray))
+            }
+            None => Span::styled("<U> to measure", Style::default().fg(Color::DarkGray)),
+        }
+    }
+}
+
+/// What the retention policy does with a snapshot, in front of it on the prune preview.
+fn verdict_span(reason: Option<KeepReason>) -> Span<'static> {
+    match reason {
+        Some(reason) => Span::styled(
+            format!("keep ({reason})"),
+            Style::default().fg(Color::Green),
+        ),
+        None => Span::styled("delete", Style::default().fg(Color::Red).bold()),
+    }
+}
+
+/// The letter of the tag on a colored background, one color per tag.
+pub fn tag_badge(tag: SnapshotTag) -> Span<'static> {
+    let color = match tag {
+        SnapshotTag::Ondemand => Color::Cyan,
+        SnapshotTag::Boot => Color::Magenta,
+        SnapshotTag::Hourly => Color::Yellow,
+        SnapshotTag::Daily => Color::Green,
+        SnapshotTag::Weekly => Color::Blue,
+        SnapshotTag::Monthly => Color::Red,
+    };
+    Span::styled(
+        format!(" {} ", tag.letter()),
+        Style::default().fg(Color::Black).bg(color).bold(),
+    )
+}
+
+/// Turns an age into something like "3 days ago", we only keep the biggest unit.
+pub fn format_age(age: TimeDelta) -> String {
+    match format_duration(age) {
+        Some(duration) => format!("{duration} ago"),
+        None => "just now".to_string(),
+    }
+}
 
-        list.render(area, buf);
+/// Something like "3 days", `None` under a minute.
+pub fn format_duration(duration: TimeDelta) -> Option<String> {
+    let (value, unit) = if duration.num_days() >= 365 {
+        (duration.num_days() / 365, "year")
+    } else if duration.num_days() >= 30 {
+        (duration.num_days() / 30, "month")
+    } else if duration.num_days() >= 1 {
+        (duration.num_days(), "day")
+    } else if duration.num_hours() >= 1 {
+        (duration.num_hours(), "hour")
+    } else if duration.num_minutes() >= 1 {
+        (duration.num_minutes(), "minute")
+    } else {
+        return None;
+    };
+    if value > 1 {
+        Some(format!("{value} {unit}s"))
+    } else {
+        Some(format!("{value} {unit}"))
     }
 }
@@ -43,3 +43,25 @@ pub fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect
     let [area] = Layout::vertical([vertical]).flex(Flex::Center).areas(area);
     area
 }
+
+/// The columns of a table that fit in `width`. The ones of `optional` are dropped in that order
+/// until the others get at least their `min_width`, with the space the table puts between them.
+pub fn fit_columns<C: Copy + PartialEq>(
+    columns: &[C],
+    optional: &[C],
+    width: u16,
+    min_width: impl Fn(C) -> u16,
+) -> Vec<C> {
+    let mut shown = columns.to_vec();
+    let needed = |shown: &[C]| {
+        shown.iter().map(|column| min_width(*column)).sum::<u16>()
+            + shown.len().saturating_sub(1) as u16
+    };
+    for column in optional {
+        if needed(&shown) <= width {
+            break;
+        }
+        shown.retain(|c| c != column);
+    }
+    shown
+}